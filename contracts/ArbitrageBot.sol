@@ -16,7 +16,8 @@ abstract contract ArbitrageBot is Ownable, ReentrancyGuard, IFlashLoanReceiver {
 
     IUniswapV2Router02 public immutable uniswapRouter;
     IPool public immutable POOL;
-    address public constant WETH = 0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2;
+    // Wrapped native token of the chain the bot is deployed on, as the router reports it
+    address public immutable WETH;
     mapping(address => AggregatorV3Interface) public priceFeeds;
     mapping(address => bool) public supportedTokens;
 
@@ -30,6 +31,7 @@ abstract contract ArbitrageBot is Ownable, ReentrancyGuard, IFlashLoanReceiver {
     constructor(address _uniswapRouter, address _poolAddress, address initialOwner) Ownable(initialOwner) {
         uniswapRouter = IUniswapV2Router02(_uniswapRouter);
         POOL = IPool(_poolAddress);
+        WETH = IUniswapV2Router02(_uniswapRouter).WETH();
     }
 
 
@@ -46,36 +48,71 @@ abstract contract ArbitrageBot is Ownable, ReentrancyGuard, IFlashLoanReceiver {
         IERC20(path[0]).safeTransferFrom(msg.sender, address(this), amountIn);
         IERC20(path[0]).approve(address(uniswapRouter), amountIn);
 
-        try uniswapRouter.swapExactTokensForTokens(
+        // A failed swap reverts with the router's own reason
+        uint256[] memory amounts = uniswapRouter.swapExactTokensForTokens(
             amountIn,
             minAmountOut,
             path,
             address(this),
             deadline
-        ) returns (uint256[] memory amounts) {
-            uint256 profit = amounts[amounts.length - 1] - amountIn;
-            require(profit > 0, "No profit");
+        );
+        uint256 profit = amounts[amounts.length - 1] - amountIn;
+        require(profit > 0, "No profit");
 
-            emit ArbitrageExecuted(path, profit);
+        emit ArbitrageExecuted(path, profit);
 
-            IERC20(path[path.length - 1]).safeTransfer(msg.sender, amounts[amounts.length - 1]);
-        } catch Error(string memory reason) {
-            emit ErrorOccurred("Swap failed", reason);
-            revert("Swap failed");
+        IERC20(path[path.length - 1]).safeTransfer(msg.sender, amounts[amounts.length - 1]);
+    }
+
+    // Swaps path[i] to path[i + 1] on routers[i], so a round trip can buy on one DEX and sell on another
+    function executeRoutedArbitrage(
+        address[] memory routers,
+        address[] memory path,
+        uint256 amountIn,
+        uint256 minAmountOut,
+        uint256 deadline
+    ) external onlyOwner nonReentrant {
+        require(block.timestamp <= deadline, "Transaction expired");
+        require(path.length >= 2 && routers.length == path.length - 1, "Invalid path");
+        require(path[0] == path[path.length - 1], "Not a round trip");
+        require(supportedTokens[path[0]], "Unsupported tokens");
+
+        IERC20(path[0]).safeTransferFrom(msg.sender, address(this), amountIn);
+
+        uint256 amount = amountIn;
+        address[] memory hop = new address[](2);
+        for (uint256 i = 0; i < routers.length; i++) {
+            hop[0] = path[i];
+            hop[1] = path[i + 1];
+            IERC20(hop[0]).approve(routers[i], amount);
+            // A failed hop reverts with the router's own reason
+            uint256[] memory amounts = IUniswapV2Router02(routers[i]).swapExactTokensForTokens(amount, 0, hop, address(this), deadline);
+            amount = amounts[1];
         }
+        require(amount >= minAmountOut, "Insufficient output");
+        require(amount > amountIn, "No profit");
+
+        emit ArbitrageExecuted(path, amount - amountIn);
+
+        IERC20(path[0]).safeTransfer(msg.sender, amount);
     }
 
+    // params = abi.encode(address[] path, uint256 minAmountOut, uint256 deadline), decoded in executeOperation
     function executeFlashLoanArbitrage(
-        address flashLoanToken
-        // uint256 flashLoanAmount,
-        // address[] memory path,
-        // uint256 minAmountOut,
-        // uint256 deadline
+        address flashLoanToken,
+        uint256 flashLoanAmount,
+        bytes calldata params
     ) external onlyOwner nonReentrant {
         require(supportedTokens[flashLoanToken], "Unsupported token");
-        // bytes memory params = abi.encode(path, minAmountOut, deadline);
-        // Call to flash loan provider would go here
-        // For example: flashLoanProvider.flashLoan(address(this), flashLoanToken, flashLoanAmount, params);
+
+        address[] memory assets = new address[](1);
+        assets[0] = flashLoanToken;
+        uint256[] memory amounts = new uint256[](1);
+        amounts[0] = flashLoanAmount;
+        uint256[] memory interestRateModes = new uint256[](1);
+
+        POOL.flashLoan(address(this), assets, amounts, interestRateModes, address(this), params, 0);
+        emit FlashLoanExecuted(flashLoanToken, flashLoanAmount);
     }
 
     function executeOperation(
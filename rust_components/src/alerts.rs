@@ -0,0 +1,299 @@
+use crate::circuit_breaker::BreakerReason;
+use crate::logger;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use log::{debug, error, info, warn};
+use thiserror::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "INFO"),
+            Severity::Warning => write!(f, "WARNING"),
+            Severity::Critical => write!(f, "CRITICAL"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Alert {
+    pub severity: Severity,
+    pub title: String,
+    pub message: String,
+    /// Alerts sharing a key within the dedup window are sent once. Defaults to the title.
+    pub dedup_key: String,
+    pub at: DateTime<Utc>,
+}
+
+impl Alert {
+    pub fn new(severity: Severity, title: &str, message: &str) -> Self {
+        Alert {
+            severity,
+            title: title.to_string(),
+            message: message.to_string(),
+            dedup_key: title.to_string(),
+            at: Utc::now(),
+        }
+    }
+
+    pub fn with_dedup_key(mut self, dedup_key: &str) -> Self {
+        self.dedup_key = dedup_key.to_string();
+        self
+    }
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}\n{}", self.severity, self.title, logger::redact(&self.message))
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum AlertError {
+    #[error("Alert request failed: {0}")]
+    Http(String),
+    #[error("Alert rejected with HTTP {status}: {body}")]
+    Rejected { status: u16, body: String },
+}
+
+#[async_trait]
+pub trait AlertSink: Send + Sync {
+    fn name(&self) -> &str;
+    async fn send(&self, alert: &Alert) -> Result<(), AlertError>;
+}
+
+pub struct StdoutSink;
+
+#[async_trait]
+impl AlertSink for StdoutSink {
+    fn name(&self) -> &str {
+        "stdout"
+    }
+
+    async fn send(&self, alert: &Alert) -> Result<(), AlertError> {
+        println!("{}", alert);
+        Ok(())
+    }
+}
+
+async fn post_json<T: Serialize + ?Sized>(client: &reqwest::Client, url: &str, body: &T) -> Result<(), AlertError> {
+    let response = client.post(url).json(body).send().await.map_err(|e| AlertError::Http(e.to_string()))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(AlertError::Rejected { status: status.as_u16(), body });
+    }
+    Ok(())
+}
+
+/// Posts the alert as JSON to any endpoint, e.g. a Slack or PagerDuty relay.
+pub struct WebhookSink {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl WebhookSink {
+    pub fn new(url: &str) -> Self {
+        WebhookSink { url: url.to_string(), client: reqwest::Client::new() }
+    }
+}
+
+#[async_trait]
+impl AlertSink for WebhookSink {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    async fn send(&self, alert: &Alert) -> Result<(), AlertError> {
+        post_json(&self.client, &self.url, alert).await
+    }
+}
+
+const TELEGRAM_API: &str = "https://api.telegram.org";
+
+/// Sends alerts to a chat through the Telegram Bot API `sendMessage` method.
+pub struct TelegramSink {
+    api_base: String,
+    token: String,
+    chat_id: String,
+    client: reqwest::Client,
+}
+
+impl TelegramSink {
+    /// The bot token is registered as a secret so it never appears in logs.
+    pub fn new(token: &str, chat_id: &str) -> Self {
+        logger::register_secret(token);
+        TelegramSink {
+            api_base: TELEGRAM_API.to_string(),
+            token: token.to_string(),
+            chat_id: chat_id.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Points the sink at another Bot API server, such as a local stand-in.
+    pub fn with_api_base(mut self, api_base: &str) -> Self {
+        self.api_base = api_base.trim_end_matches('/').to_string();
+        self
+    }
+}
+
+#[async_trait]
+impl AlertSink for TelegramSink {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    async fn send(&self, alert: &Alert) -> Result<(), AlertError> {
+        let url = format!("{}/bot{}/sendMessage", self.api_base, self.token);
+        let body = serde_json::json!({ "chat_id": self.chat_id, "text": alert.to_string() });
+        post_json(&self.client, &url, &body).await.map_err(|e| match e {
+            // reqwest errors include the URL, and with it the token
+            AlertError::Http(message) => AlertError::Http(logger::redact(&message)),
+            e => e,
+        })
+    }
+}
+
+/// What `dispatch` did with an alert.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DispatchOutcome {
+    /// Delivered to this many sinks.
+    Sent(usize),
+    Duplicate,
+    RateLimited,
+}
+
+#[derive(Default)]
+struct DispatchState {
+    last_sent: HashMap<String, DateTime<Utc>>,
+    recent: VecDeque<DateTime<Utc>>,
+}
+
+/// Fans alerts out to sinks by severity, dropping duplicates and limiting the
+/// rate. Critical alerts are deduplicated but never rate limited.
+pub struct AlertDispatcher {
+    sinks: Vec<(Box<dyn AlertSink>, Severity)>,
+    dedup_window: Duration,
+    max_alerts: usize,
+    rate_window: Duration,
+    state: Mutex<DispatchState>,
+}
+
+impl Default for AlertDispatcher {
+    fn default() -> Self {
+        AlertDispatcher::new()
+    }
+}
+
+impl AlertDispatcher {
+    pub fn new() -> Self {
+        AlertDispatcher {
+            sinks: Vec::new(),
+            dedup_window: Duration::minutes(10),
+            max_alerts: 20,
+            rate_window: Duration::minutes(1),
+            state: Mutex::new(DispatchState::default()),
+        }
+    }
+
+    /// Sends alerts of `min_severity` and above to `sink`.
+    pub fn add_sink(&mut self, sink: Box<dyn AlertSink>, min_severity: Severity) {
+        info!("Sending {}+ alerts to {}", min_severity, sink.name());
+        self.sinks.push((sink, min_severity));
+    }
+
+    pub fn set_dedup_window(&mut self, dedup_window: Duration) {
+        self.dedup_window = dedup_window;
+    }
+
+    /// At most `max_alerts` non-critical alerts per `window`.
+    pub fn set_rate_limit(&mut self, max_alerts: usize, window: Duration) {
+        self.max_alerts = max_alerts;
+        self.rate_window = window;
+    }
+
+    fn admit(&self, alert: &Alert) -> Option<DispatchOutcome> {
+        let mut state = self.state.lock().unwrap();
+        let now = alert.at;
+
+        if let Some(last_sent) = state.last_sent.get(&alert.dedup_key) {
+            if now - *last_sent < self.dedup_window {
+                return Some(DispatchOutcome::Duplicate);
+            }
+        }
+        while state.recent.front().is_some_and(|at| now - *at >= self.rate_window) {
+            state.recent.pop_front();
+        }
+        if alert.severity < Severity::Critical && state.recent.len() >= self.max_alerts {
+            return Some(DispatchOutcome::RateLimited);
+        }
+
+        state.last_sent.insert(alert.dedup_key.clone(), now);
+        state.recent.push_back(now);
+        None
+    }
+
+    pub async fn dispatch(&self, alert: Alert) -> DispatchOutcome {
+        if let Some(outcome) = self.admit(&alert) {
+            debug!("Dropped alert {}: {:?}", alert.title, outcome);
+            return outcome;
+        }
+
+        let mut sent = 0;
+        for (sink, min_severity) in &self.sinks {
+            if alert.severity < *min_severity {
+                continue;
+            }
+            match sink.send(&alert).await {
+                Ok(()) => sent += 1,
+                Err(e) => error!("Failed to send alert {} to {}: {}", alert.title, sink.name(), e),
+            }
+        }
+        DispatchOutcome::Sent(sent)
+    }
+
+    /// Dispatches in the background so callers on the trading path never wait
+    /// on a sink. Uses the current tokio runtime, or a helper thread outside one.
+    pub fn notify(self: &Arc<Self>, alert: Alert) {
+        let dispatcher = self.clone();
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(async move {
+                    dispatcher.dispatch(alert).await;
+                });
+            }
+            Err(_) => {
+                std::thread::spawn(move || match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                    Ok(runtime) => {
+                        runtime.block_on(dispatcher.dispatch(alert));
+                    }
+                    Err(e) => warn!("Dropped alert {}: no runtime: {}", alert.title, e),
+                });
+            }
+        }
+    }
+
+    /// Handler for `CircuitBreaker::set_alert_handler` that raises a critical alert
+    /// on every trip of `chain`'s breaker.
+    pub fn breaker_alert_handler(self: &Arc<Self>, chain: &str) -> Box<dyn Fn(&BreakerReason) + Send + Sync> {
+        let dispatcher = self.clone();
+        let chain = chain.to_string();
+        Box::new(move |reason| {
+            let alert = Alert::new(Severity::Critical, "Circuit breaker tripped", &format!("Trading halted on {}: {}", chain, reason));
+            dispatcher.notify(alert.with_dedup_key(&format!("breaker:{}:{}", chain, reason)));
+        })
+    }
+}
@@ -0,0 +1,70 @@
+use ethers::abi::{RawLog, Token};
+use ethers::contract::EthLogDecode;
+use ethers::prelude::*;
+use log::warn;
+
+// Generated from the hardhat artifact; refresh with `npx hardhat run scripts/export-abi.js`.
+abigen!(
+    ArbitrageBotContract,
+    "./abi/ConcreteArbitrageBot.json",
+    event_derives(serde::Deserialize, serde::Serialize)
+);
+
+/// Receipt of a transaction sent to the bot contract, with its events decoded.
+#[derive(Clone, Debug)]
+pub struct BotReceipt {
+    pub receipt: TransactionReceipt,
+    pub executions: Vec<ArbitrageExecutedFilter>,
+    pub errors: Vec<ErrorOccurredFilter>,
+}
+
+impl BotReceipt {
+    /// Decodes the `ArbitrageExecuted` and `ErrorOccurred` events emitted by the
+    /// bot at `bot_address`; logs from other contracts are ignored.
+    pub fn from_receipt(receipt: TransactionReceipt, bot_address: Address) -> Self {
+        let mut executions = Vec::new();
+        let mut errors = Vec::new();
+
+        for log in receipt.logs.iter().filter(|log| log.address == bot_address) {
+            match ArbitrageBotContractEvents::decode_log(&RawLog::from(log.clone())) {
+                Ok(ArbitrageBotContractEvents::ArbitrageExecutedFilter(event)) => executions.push(event),
+                Ok(ArbitrageBotContractEvents::ErrorOccurredFilter(event)) => errors.push(event),
+                Ok(_) => {}
+                Err(e) => warn!("Failed to decode log from arbitrage bot: {}", e),
+            }
+        }
+
+        BotReceipt {
+            receipt,
+            executions,
+            errors,
+        }
+    }
+
+    pub fn success(&self) -> bool {
+        self.receipt.status == Some(U64::from(1))
+    }
+
+    pub fn total_profit(&self) -> U256 {
+        self.executions
+            .iter()
+            .fold(U256::zero(), |total, execution| total + execution.profit)
+    }
+}
+
+/// Decodes one of the bot's custom errors, e.g. `OwnableUnauthorizedAccount`,
+/// from revert data as `Name(arg, ...)`.
+pub fn decode_custom_error(data: &[u8]) -> Option<String> {
+    let selector = data.get(..4)?;
+    let error = ARBITRAGEBOTCONTRACT_ABI.errors().find(|error| &error.signature()[..4] == selector)?;
+    let args: Vec<String> = error
+        .decode(&data[4..])
+        .ok()?
+        .into_iter()
+        .map(|token| match token {
+            Token::Address(address) => format!("{:?}", address),
+            token => token.to_string(),
+        })
+        .collect();
+    Some(format!("{}({})", error.name, args.join(", ")))
+}
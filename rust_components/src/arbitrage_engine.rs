@@ -1,4 +1,5 @@
 use log::{error, info};
+use crate::arbitrage_finder::ArbitrageOpportunity;
 use crate::order_book::OrderBook;
 use crate::errors::ArbitrageEngineError;
 use crate::order::Order;
@@ -30,6 +31,18 @@ impl ArbitrageEngine {
     
 
 
+    /// Like `find_arbitrage`, but returns a trackable opportunity that buys on
+    /// book B and sells on book A.
+    pub fn find_opportunity(
+        venue_a: &str,
+        book_a: &OrderBook,
+        venue_b: &str,
+        book_b: &OrderBook
+    ) -> Result<Option<ArbitrageOpportunity>, ArbitrageEngineError> {
+        let profit = Self::find_arbitrage(book_a, book_b)?;
+        Ok(profit.map(|profit| ArbitrageOpportunity::new(venue_b, venue_a, profit)))
+    }
+
     pub fn find_arbitrage(
         book_a: &OrderBook,
         book_b: &OrderBook
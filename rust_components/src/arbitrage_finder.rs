@@ -1,62 +1,166 @@
+use crate::control_api::ControlState;
+use crate::metrics;
+use crate::opportunity_tracker::OpportunityTracker;
 use crate::price_monitor::PriceMonitor;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use log::{info, debug};
+use tracing::field::Empty;
+use tracing::info_span;
+
+static NEXT_OPPORTUNITY_SEQ: AtomicU64 = AtomicU64::new(0);
 
 pub struct ArbitrageFinder {
     price_monitor: PriceMonitor,
     min_profit_threshold: f64,
+    poll_interval_ms: u64,
+    tracker: Option<Arc<OpportunityTracker>>,
+    control: Option<Arc<ControlState>>,
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct ArbitrageOpportunity {
+    /// Unique per process run: detection time in milliseconds plus a sequence number.
+    pub id: String,
+    pub detected_at: DateTime<Utc>,
+    /// Chain the prices were read on, when the source knows it.
+    pub chain_id: Option<u64>,
+    pub block_number: Option<u64>,
+    /// Traded pair, e.g. `"WETH/USDC"`, when the source knows it.
+    pub pair: Option<String>,
     pub buy_dex: String,
     pub sell_dex: String,
     pub profit: f64,
 }
 
+impl ArbitrageOpportunity {
+    pub fn new(buy_dex: &str, sell_dex: &str, profit: f64) -> Self {
+        let detected_at = Utc::now();
+        let seq = NEXT_OPPORTUNITY_SEQ.fetch_add(1, Ordering::Relaxed);
+        ArbitrageOpportunity {
+            id: format!("opp-{}-{}", detected_at.timestamp_millis(), seq),
+            detected_at,
+            chain_id: None,
+            block_number: None,
+            pair: None,
+            buy_dex: buy_dex.to_string(),
+            sell_dex: sell_dex.to_string(),
+            profit,
+        }
+    }
+
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    /// Block the prices were read at.
+    pub fn with_block_number(mut self, block_number: u64) -> Self {
+        self.block_number = Some(block_number);
+        self
+    }
+
+    pub fn with_pair(mut self, pair: &str) -> Self {
+        self.pair = Some(pair.to_string());
+        self
+    }
+}
+
 impl ArbitrageFinder {
     pub fn new(price_monitor: PriceMonitor, min_profit_threshold: f64) -> Self {
         ArbitrageFinder {
             price_monitor,
             min_profit_threshold,
+            poll_interval_ms: 10_000,
+            tracker: None,
+            control: None,
         }
     }
 
+    pub fn set_poll_interval(&mut self, poll_interval_ms: u64) {
+        self.poll_interval_ms = poll_interval_ms;
+    }
+
+    /// Records every opportunity found in `tracker`.
+    pub fn set_tracker(&mut self, tracker: Arc<OpportunityTracker>) {
+        self.tracker = Some(tracker);
+    }
+
+    /// Reports prices and opportunities to the control API and takes the
+    /// minimum profit from its thresholds.
+    pub fn set_control(&mut self, control: Arc<ControlState>) {
+        self.control = Some(control);
+    }
+
     pub async fn monitor_for_arbitrage_opportunities<F>(&self, callback: F)
     where
-        F: Fn(ArbitrageOpportunity) + Send + 'static,
+        F: Fn(ArbitrageOpportunity) + Send,
     {
-        self.price_monitor.monitor_prices(move |prices| {
+        self.price_monitor.monitor_prices(move |readings| {
+            // Stale and missing prices are never traded on
+            let prices: HashMap<String, f64> = readings.iter().filter_map(|(dex, reading)| Some((dex.clone(), reading.fresh()?))).collect();
+            if prices.len() < readings.len() {
+                debug!("Comparing {} of {} DEX prices, the rest are stale or missing", prices.len(), readings.len());
+            }
             if let Some(opportunity) = self.find_arbitrage_opportunity(&prices) {
                 callback(opportunity);
             }
-        }, 10000).await;
+        }, self.poll_interval_ms).await;
     }
 
     fn find_arbitrage_opportunity(&self, prices: &HashMap<String, f64>) -> Option<ArbitrageOpportunity> {
-        let mut best_opportunity = None;
+        let span = info_span!("detect", opportunity_id = Empty, block_number = Empty);
+        let _entered = span.enter();
+        let mut best = None;
         let mut max_profit = 0.0;
+        let min_profit = match &self.control {
+            Some(control) => {
+                for (dex, price) in prices {
+                    control.record_pool_price(dex, *price);
+                }
+                control.thresholds().min_profit
+            }
+            None => self.min_profit_threshold,
+        };
 
         for (buy_dex, buy_price) in prices {
             for (sell_dex, sell_price) in prices {
                 if buy_dex != sell_dex {
                     let profit = sell_price - buy_price;
-                    if profit > max_profit && profit > self.min_profit_threshold {
+                    if profit > max_profit && profit > min_profit {
                         max_profit = profit;
-                        best_opportunity = Some(ArbitrageOpportunity {
-                            buy_dex: buy_dex.clone(),
-                            sell_dex: sell_dex.clone(),
-                            profit,
-                        });
+                        best = Some((buy_dex, sell_dex, profit));
                     }
                 }
             }
         }
+        let best_opportunity = best.map(|(buy_dex, sell_dex, profit)| {
+            let opportunity = ArbitrageOpportunity::new(buy_dex, sell_dex, profit);
+            match self.price_monitor.chain_id() {
+                Some(chain_id) => opportunity.with_chain_id(chain_id),
+                None => opportunity,
+            }
+        });
 
         if let Some(ref opportunity) = best_opportunity {
+            span.record("opportunity_id", opportunity.id.as_str());
+            if let Some(block_number) = opportunity.block_number {
+                span.record("block_number", block_number);
+            }
             info!("Arbitrage opportunity found: Buy from {} at {}, Sell to {} at {}, Profit: {}",
                   opportunity.buy_dex, prices[&opportunity.buy_dex],
                   opportunity.sell_dex, prices[&opportunity.sell_dex],
                   opportunity.profit);
+            metrics::opportunity_found();
+            if let Some(tracker) = &self.tracker {
+                tracker.detect(opportunity.clone());
+            }
+            if let Some(control) = &self.control {
+                control.record_opportunity(opportunity);
+            }
         } else {
             debug!("No arbitrage opportunity found");
         }
@@ -0,0 +1,142 @@
+use crate::arbitrage_bot_contract::ArbitrageBotContract;
+use crate::gas_strategy::GasStrategy;
+use crate::logger::AUDIT_TARGET;
+use crate::provider_pool::{PooledProvider, ProviderPool};
+use crate::signer::SignerPool;
+use crate::trade_executor::{decode_revert_reason, SimulationError};
+use crate::transaction_manager::{TransactionManager, TransactionManagerConfig};
+use ethers::prelude::*;
+use log::{info, warn};
+use std::fmt;
+use std::sync::Arc;
+
+/// Owner-only operations on the deployed `ArbitrageBot`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AdminAction {
+    AddSupportedToken { token: Address },
+    RemoveSupportedToken { token: Address },
+    AddPriceFeed { token: Address, price_feed: Address },
+    WithdrawToken { token: Address, amount: U256 },
+}
+
+impl AdminAction {
+    /// Token whose on-chain state the action changes.
+    pub fn token(&self) -> Address {
+        match self {
+            AdminAction::AddSupportedToken { token }
+            | AdminAction::RemoveSupportedToken { token }
+            | AdminAction::AddPriceFeed { token, .. }
+            | AdminAction::WithdrawToken { token, .. } => *token,
+        }
+    }
+}
+
+impl fmt::Display for AdminAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdminAction::AddSupportedToken { token } => write!(f, "addSupportedToken({:?})", token),
+            AdminAction::RemoveSupportedToken { token } => write!(f, "removeSupportedToken({:?})", token),
+            AdminAction::AddPriceFeed { token, price_feed } => write!(f, "addPriceFeed({:?}, {:?})", token, price_feed),
+            AdminAction::WithdrawToken { token, amount } => write!(f, "withdrawToken({:?}, {})", token, amount),
+        }
+    }
+}
+
+/// On-chain configuration of the bot for a single token.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenState {
+    pub token: Address,
+    pub supported: bool,
+    pub price_feed: Option<Address>,
+}
+
+impl fmt::Display for TokenState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let price_feed = self.price_feed.map(|feed| format!("{:?}", feed)).unwrap_or_else(|| "none".to_string());
+        write!(f, "token {:?}: supported = {}, price feed = {}", self.token, self.supported, price_feed)
+    }
+}
+
+/// Sends owner operations to the bot through the same transaction manager and
+/// signers as the trading path.
+pub struct BotAdmin {
+    provider: Arc<PooledProvider>,
+    bot: ArbitrageBotContract<PooledProvider>,
+    gas_strategy: GasStrategy,
+    transaction_manager: TransactionManager,
+}
+
+impl BotAdmin {
+    pub fn new(provider_url: &str, bot_address: Address) -> Result<Self, Box<dyn std::error::Error>> {
+        let provider = Arc::new(ProviderPool::new(&[provider_url.to_string()])?.into_provider());
+        let bot = ArbitrageBotContract::new(bot_address, provider.clone());
+        let transaction_manager = TransactionManager::new(provider.clone(), TransactionManagerConfig::default())?;
+
+        Ok(BotAdmin {
+            provider,
+            bot,
+            gas_strategy: GasStrategy::default(),
+            transaction_manager,
+        })
+    }
+
+    pub fn set_signers(&mut self, signers: Arc<SignerPool>) {
+        self.transaction_manager.set_signers(signers);
+    }
+
+    pub fn provider(&self) -> &PooledProvider {
+        &self.provider
+    }
+
+    pub async fn owner(&self) -> Result<Address, Box<dyn std::error::Error>> {
+        Ok(self.bot.owner().call().await?)
+    }
+
+    pub async fn token_state(&self, token: Address) -> Result<TokenState, Box<dyn std::error::Error>> {
+        let supported = self.bot.supported_tokens(token).call().await?;
+        let price_feed = self.bot.price_feeds(token).call().await?;
+        Ok(TokenState {
+            token,
+            supported,
+            price_feed: (!price_feed.is_zero()).then_some(price_feed),
+        })
+    }
+
+    fn call(&self, action: &AdminAction) -> FunctionCall<Arc<PooledProvider>, PooledProvider, ()> {
+        match action {
+            AdminAction::AddSupportedToken { token } => self.bot.add_supported_token(*token),
+            AdminAction::RemoveSupportedToken { token } => self.bot.remove_supported_token(*token),
+            AdminAction::AddPriceFeed { token, price_feed } => self.bot.add_price_feed(*token, *price_feed),
+            AdminAction::WithdrawToken { token, amount } => self.bot.withdraw_token(*token, *amount),
+        }
+    }
+
+    /// `eth_call`s the action from `from` at the pending block, surfacing
+    /// reverts such as `OwnableUnauthorizedAccount` without sending anything.
+    pub async fn simulate(&self, action: &AdminAction, from: Address) -> Result<(), SimulationError> {
+        let call = self.call(action).from(from).block(BlockNumber::Pending);
+        call.call().await.map_err(|e| match e.as_revert() {
+            Some(data) => SimulationError::Reverted(decode_revert_reason(data)),
+            None => SimulationError::CallFailed(e.to_string()),
+        })?;
+        info!("Simulated {} from {:?}", action, from);
+        Ok(())
+    }
+
+    /// Simulates, then sends the action and waits for confirmation.
+    pub async fn execute(&self, action: &AdminAction, from: Address) -> Result<TransactionReceipt, Box<dyn std::error::Error>> {
+        if let Err(e) = self.simulate(action, from).await {
+            warn!("Aborting {} after simulation: {}", action, e);
+            return Err(Box::new(e));
+        }
+
+        let mut call = self.call(action).from(from);
+        let gas_limit = self.gas_strategy.gas_limit_with_margin(call.estimate_gas().await?);
+        let fees = self.gas_strategy.estimate_fees(&self.provider).await?;
+        call = call.gas(gas_limit);
+
+        let receipt = self.transaction_manager.send_and_confirm(call.tx, fees).await?;
+        info!(target: AUDIT_TARGET, "{} from {:?} confirmed: {:?}", action, from, receipt.transaction_hash);
+        Ok(receipt)
+    }
+}
@@ -0,0 +1,257 @@
+use crate::logger::AUDIT_TARGET;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use ethers::types::U256;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use log::{error, info, warn};
+
+/// Why trading was halted.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BreakerReason {
+    DailyLoss { loss: f64, limit: f64 },
+    ConsecutiveFailures { count: u32, limit: u32 },
+    HourlyGas { gas_spent: U256, limit: U256 },
+    Volatility { volatility: f64, limit: f64 },
+    KillSwitch(String),
+}
+
+impl fmt::Display for BreakerReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BreakerReason::DailyLoss { loss, limit } => write!(f, "daily loss {:.2} exceeds limit {:.2}", loss, limit),
+            BreakerReason::ConsecutiveFailures { count, limit } => write!(f, "{} consecutive failed transactions (limit {})", count, limit),
+            BreakerReason::HourlyGas { gas_spent, limit } => write!(f, "gas spent in the last hour {} exceeds limit {}", gas_spent, limit),
+            BreakerReason::Volatility { volatility, limit } => write!(f, "volatility {} exceeds limit {}", volatility, limit),
+            BreakerReason::KillSwitch(source) => write!(f, "kill switch engaged by {}", source),
+        }
+    }
+}
+
+/// A trip that has not yet been cleared by an operator.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TrippedState {
+    pub reason: BreakerReason,
+    pub tripped_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug)]
+pub struct CircuitBreakerConfig {
+    /// Realized loss per UTC day, in the inventory's quote currency.
+    pub max_daily_loss: f64,
+    pub max_consecutive_failures: u32,
+    /// Gas spent over a rolling hour, in wei.
+    pub max_gas_per_hour: U256,
+    /// Trading halts while this file exists.
+    pub kill_switch_path: Option<PathBuf>,
+    /// Where a trip is persisted so that restarting does not resume trading.
+    /// Deleting the file (`rust_components breaker reset`) resumes a running bot.
+    pub state_path: Option<PathBuf>,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        CircuitBreakerConfig {
+            max_daily_loss: 1_000.0,
+            max_consecutive_failures: 3,
+            max_gas_per_hour: U256::exp10(18),
+            kill_switch_path: None,
+            state_path: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    tripped: Option<TrippedState>,
+    persisted: bool,
+    day: Option<NaiveDate>,
+    daily_pnl: f64,
+    consecutive_failures: u32,
+    gas_window: VecDeque<(DateTime<Utc>, U256)>,
+}
+
+type AlertHandler = Box<dyn Fn(&BreakerReason) + Send + Sync>;
+
+/// Stateful breakers shared by the executor and risk manager. Once tripped, it
+/// stays tripped until `reset` is called.
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: Mutex<BreakerState>,
+    alert_handler: Option<AlertHandler>,
+}
+
+impl CircuitBreaker {
+    /// Restores a persisted trip from `config.state_path`, if any.
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        let tripped = config.state_path.as_deref().and_then(load_tripped_state);
+        let persisted = tripped.is_some();
+        if let Some(tripped) = &tripped {
+            warn!("Circuit breaker still tripped since {}: {}", tripped.tripped_at, tripped.reason);
+        }
+        CircuitBreaker {
+            config,
+            state: Mutex::new(BreakerState { tripped, persisted, ..Default::default() }),
+            alert_handler: None,
+        }
+    }
+
+    /// Called once for every trip, e.g. to page the operator.
+    pub fn set_alert_handler(&mut self, alert_handler: AlertHandler) {
+        self.alert_handler = Some(alert_handler);
+    }
+
+    pub fn tripped(&self) -> Option<TrippedState> {
+        self.state.lock().unwrap().tripped.clone()
+    }
+
+    pub fn is_tripped(&self) -> bool {
+        self.tripped().is_some()
+    }
+
+    /// Fails while tripped or while the kill-switch file exists.
+    pub fn check(&self) -> Result<(), BreakerReason> {
+        if self.state_file_removed() {
+            if let Err(e) = self.reset("state file removal") {
+                error!("Failed to reset circuit breaker: {}", e);
+            }
+        }
+        if let Some(path) = &self.config.kill_switch_path {
+            if path.exists() {
+                self.trip(BreakerReason::KillSwitch(path.display().to_string()));
+            }
+        }
+        match self.tripped() {
+            Some(tripped) => Err(tripped.reason),
+            None => Ok(()),
+        }
+    }
+
+    fn state_file_removed(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        match &self.config.state_path {
+            Some(path) => state.persisted && state.tripped.is_some() && !path.exists(),
+            None => false,
+        }
+    }
+
+    /// Halts trading. Only the first reason is kept until the breaker is reset.
+    pub fn trip(&self, reason: BreakerReason) {
+        self.trip_at(reason, Utc::now());
+    }
+
+    fn trip_at(&self, reason: BreakerReason, now: DateTime<Utc>) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.tripped.is_some() {
+                return;
+            }
+            let tripped = TrippedState { reason: reason.clone(), tripped_at: now };
+            if let Some(path) = &self.config.state_path {
+                match save_tripped_state(path, &tripped) {
+                    Ok(()) => state.persisted = true,
+                    Err(e) => error!("Failed to persist circuit breaker state: {}", e),
+                }
+            }
+            state.tripped = Some(tripped);
+        }
+
+        error!(target: AUDIT_TARGET, "Circuit breaker tripped, trading halted: {}", reason);
+        if let Some(alert_handler) = &self.alert_handler {
+            alert_handler(&reason);
+        }
+    }
+
+    /// Operator action that clears a trip and the failure streak.
+    pub fn reset(&self, operator: &str) -> std::io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(path) = &self.config.state_path {
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+        }
+        state.persisted = false;
+        if let Some(tripped) = state.tripped.take() {
+            info!(target: AUDIT_TARGET, "Circuit breaker reset by {} (was: {})", operator, tripped.reason);
+        }
+        state.consecutive_failures = 0;
+        Ok(())
+    }
+
+    /// Records a mined transaction with its quote-currency `pnl` and `gas_cost` in wei.
+    pub fn record_transaction(&self, success: bool, gas_cost: U256, pnl: f64) {
+        self.record_transaction_at(success, gas_cost, pnl, Utc::now());
+    }
+
+    pub fn record_transaction_at(&self, success: bool, gas_cost: U256, pnl: f64, now: DateTime<Utc>) {
+        let reason = {
+            let mut state = self.state.lock().unwrap();
+
+            if state.day != Some(now.date_naive()) {
+                state.day = Some(now.date_naive());
+                state.daily_pnl = 0.0;
+            }
+            state.daily_pnl += pnl;
+            state.consecutive_failures = if success { 0 } else { state.consecutive_failures + 1 };
+
+            state.gas_window.push_back((now, gas_cost));
+            while state.gas_window.front().is_some_and(|(at, _)| *at <= now - Duration::hours(1)) {
+                state.gas_window.pop_front();
+            }
+            let gas_spent = state.gas_window.iter().fold(U256::zero(), |total, (_, gas)| total + gas);
+
+            if -state.daily_pnl > self.config.max_daily_loss {
+                Some(BreakerReason::DailyLoss { loss: -state.daily_pnl, limit: self.config.max_daily_loss })
+            } else if state.consecutive_failures >= self.config.max_consecutive_failures {
+                Some(BreakerReason::ConsecutiveFailures { count: state.consecutive_failures, limit: self.config.max_consecutive_failures })
+            } else if gas_spent > self.config.max_gas_per_hour {
+                Some(BreakerReason::HourlyGas { gas_spent, limit: self.config.max_gas_per_hour })
+            } else {
+                None
+            }
+        };
+
+        if let Some(reason) = reason {
+            self.trip_at(reason, now);
+        }
+    }
+
+    /// A transaction that failed to send or confirm, with no gas known to be spent.
+    pub fn record_failure(&self) {
+        self.record_transaction(false, U256::zero(), 0.0);
+    }
+}
+
+/// Reads a persisted trip. A state file that exists but cannot be read keeps
+/// the breaker tripped.
+pub fn load_tripped_state(path: &Path) -> Option<TrippedState> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&contents) {
+        Ok(tripped) => Some(tripped),
+        Err(e) => {
+            error!("Unreadable circuit breaker state at {}: {}", path.display(), e);
+            Some(TrippedState {
+                reason: BreakerReason::KillSwitch(format!("unreadable state file {}", path.display())),
+                tripped_at: Utc::now(),
+            })
+        }
+    }
+}
+
+fn save_tripped_state(path: &Path, tripped: &TrippedState) -> std::io::Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(tripped)?)
+}
+
+/// Trips `breaker` when the process receives SIGUSR1.
+#[cfg(unix)]
+pub async fn watch_kill_signal(breaker: std::sync::Arc<CircuitBreaker>) -> std::io::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut signals = signal(SignalKind::user_defined1())?;
+    while signals.recv().await.is_some() {
+        breaker.trip(BreakerReason::KillSwitch("SIGUSR1".to_string()));
+    }
+    Ok(())
+}
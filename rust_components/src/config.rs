@@ -0,0 +1,560 @@
+use crate::circuit_breaker::CircuitBreakerConfig;
+use crate::control_api::{ControlState, Thresholds};
+use crate::risk_manager::RiskManager;
+use ethers::types::{Address, U256};
+use serde::{Deserialize, Deserializer};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+use log::{info, warn};
+use thiserror::Error;
+
+/// Environment variables starting with this override config values, e.g.
+/// `ARB__THRESHOLDS__MIN_PROFIT=0.5` or `ARB__CHAINS__MAINNET__RPC_URLS='["https://..."]'`.
+/// Array entries are addressed by their `name`.
+pub const ENV_PREFIX: &str = "ARB__";
+
+/// The only chain whose wrapped native token may be left out of the config.
+const MAINNET_CHAIN_ID: u64 = 1;
+const MAINNET_WETH: &str = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2";
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read config {path}: {source}")]
+    Io { path: PathBuf, source: std::io::Error },
+    #[error("Failed to parse config {origin}: {message}")]
+    Parse { origin: String, message: String },
+    #[error("Invalid override {var}: {message}")]
+    Env { var: String, message: String },
+    #[error("Invalid config:{}", .0.iter().map(|problem| format!("\n  - {}", problem)).collect::<String>())]
+    Invalid(Vec<String>),
+    #[error("Reload changes {} which need a restart", .0.join(", "))]
+    Structural(Vec<&'static str>),
+}
+
+/// Accepts wei as a TOML integer or, for amounts beyond `i64`, a decimal string.
+fn deserialize_wei<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Wei {
+        Integer(u64),
+        Decimal(String),
+    }
+    match Wei::deserialize(deserializer)? {
+        Wei::Integer(value) => Ok(U256::from(value)),
+        Wei::Decimal(value) => U256::from_dec_str(&value).map_err(|e| serde::de::Error::custom(format!("invalid wei amount {}: {}", value, e))),
+    }
+}
+
+fn deserialize_optional_wei<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<U256>, D::Error> {
+    deserialize_wei(deserializer).map(Some)
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BotConfig {
+    /// How often pool prices are polled.
+    pub poll_interval_ms: u64,
+    /// Stop after this long; runs until interrupted when unset.
+    pub run_secs: Option<u64>,
+    /// Where each chain's pending transactions are kept, so a restart picks
+    /// them up again.
+    pub state_dir: Option<PathBuf>,
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        BotConfig { poll_interval_ms: 10_000, run_secs: None, state_dir: None }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RiskConfig {
+    pub stop_loss_threshold: f64,
+    pub max_price_impact_bps: f64,
+    /// Realized loss per UTC day before the circuit breaker trips.
+    pub max_daily_loss: f64,
+    pub max_consecutive_failures: u32,
+    #[serde(deserialize_with = "deserialize_wei")]
+    pub max_gas_per_hour: U256,
+    pub kill_switch_path: Option<PathBuf>,
+    /// Each chain's breaker keeps its trip in a file named after the chain,
+    /// e.g. `breaker-mainnet.json` for `breaker.json`.
+    pub state_path: Option<PathBuf>,
+    /// Oldest Chainlink answer trusted by the oracle guard.
+    pub max_oracle_age_secs: u64,
+    /// How far a pool price may stray from the oracle price before its trade is rejected.
+    pub max_oracle_deviation_bps: f64,
+}
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        let breaker = CircuitBreakerConfig::default();
+        RiskConfig {
+            stop_loss_threshold: 0.1,
+            max_price_impact_bps: 100.0,
+            max_daily_loss: breaker.max_daily_loss,
+            max_consecutive_failures: breaker.max_consecutive_failures,
+            max_gas_per_hour: breaker.max_gas_per_hour,
+            kill_switch_path: None,
+            state_path: None,
+            max_oracle_age_secs: 3_600,
+            max_oracle_deviation_bps: 200.0,
+        }
+    }
+}
+
+impl RiskConfig {
+    /// Risk manager for `chain`, with each token's `max_position`; positions in
+    /// tokens without one are bounded only by balance, price impact and exposure.
+    pub fn risk_manager(&self, chain: &ChainConfig) -> RiskManager {
+        let mut risk_manager = RiskManager::new(U256::MAX, self.stop_loss_threshold);
+        risk_manager.set_max_price_impact_bps(self.max_price_impact_bps);
+        for token in &chain.tokens {
+            if let Some(max_position_size) = token.max_position_size() {
+                risk_manager.set_max_position_size(token.address, max_position_size);
+            }
+        }
+        risk_manager
+    }
+
+    /// Limits of `chain`'s breaker; the chain's own loss and gas limits take
+    /// precedence, since both are in its currencies.
+    pub fn circuit_breaker_config(&self, chain: &ChainConfig) -> CircuitBreakerConfig {
+        CircuitBreakerConfig {
+            max_daily_loss: chain.max_daily_loss.unwrap_or(self.max_daily_loss),
+            max_consecutive_failures: self.max_consecutive_failures,
+            max_gas_per_hour: chain.max_gas_per_hour.unwrap_or(self.max_gas_per_hour),
+            kill_switch_path: self.kill_switch_path.clone(),
+            state_path: self.state_path.as_ref().map(|path| chain_state_path(path, &chain.name)),
+        }
+    }
+}
+
+/// `path` with `chain` appended to its file stem.
+fn chain_state_path(path: &Path, chain: &str) -> PathBuf {
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    let name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, chain, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, chain),
+    };
+    path.with_file_name(name)
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenConfig {
+    pub symbol: String,
+    pub address: Address,
+    #[serde(default = "default_decimals")]
+    pub decimals: u8,
+    /// Chainlink USD feed of this token. Once any token of a chain has one,
+    /// every traded pair needs both, and pool prices are checked against them.
+    pub price_feed: Option<Address>,
+    /// Largest single position in this token, in whole tokens.
+    pub max_position: Option<f64>,
+}
+
+fn default_decimals() -> u8 {
+    18
+}
+
+impl TokenConfig {
+    /// `max_position` in the token's smallest unit.
+    pub fn max_position_size(&self) -> Option<U256> {
+        let max_position = self.max_position?;
+        let amount = format!("{:.*}", self.decimals as usize, max_position);
+        Some(ethers::utils::parse_units(amount, self.decimals as u32).map(Into::into).unwrap_or(U256::MAX))
+    }
+}
+
+/// A Uniswap V2 style pair watched by the price monitor.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PoolConfig {
+    pub dex: String,
+    pub address: Address,
+    /// e.g. `"WETH/USDC"`; both symbols must be listed in the chain's tokens.
+    pub pair: Option<String>,
+    /// Router of this pool's DEX; defaults to the chain's `uniswap_router`.
+    pub router: Option<Address>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChainConfig {
+    pub name: String,
+    pub chain_id: u64,
+    /// Pooled; requests go to the fastest healthy endpoint and fail over to
+    /// the others.
+    pub rpc_urls: Vec<String>,
+    /// Endpoints that must agree on pool reserves before a trade is sent;
+    /// unset trusts whichever endpoint answers.
+    pub read_quorum: Option<usize>,
+    /// Per endpoint, shared by everything on this chain; unset is unlimited.
+    pub rpc_requests_per_sec: Option<f64>,
+    pub uniswap_router: Option<Address>,
+    pub flash_loan_provider: Option<Address>,
+    /// Deployed `ArbitrageBot` to route trades through.
+    pub arbitrage_bot: Option<Address>,
+    /// Token gas costs are weighed against, e.g. WETH; required off mainnet.
+    pub wrapped_native: Option<Address>,
+    /// Average block time; the chain is never scanned more often than this.
+    pub block_time_ms: Option<u64>,
+    /// Realized loss per UTC day, in the quote token, before this chain's
+    /// breaker trips; unset uses `risk.max_daily_loss`.
+    pub max_daily_loss: Option<f64>,
+    /// Gas per hour, in wei of this chain's currency, before its breaker trips;
+    /// unset uses `risk.max_gas_per_hour`.
+    #[serde(default, deserialize_with = "deserialize_optional_wei")]
+    pub max_gas_per_hour: Option<U256>,
+    #[serde(default)]
+    pub tokens: Vec<TokenConfig>,
+    #[serde(default)]
+    pub pools: Vec<PoolConfig>,
+}
+
+impl ChainConfig {
+    pub fn rpc_url(&self) -> &str {
+        &self.rpc_urls[0]
+    }
+
+    pub fn wrapped_native(&self) -> Option<Address> {
+        match self.wrapped_native {
+            Some(token) => Some(token),
+            None if self.chain_id == MAINNET_CHAIN_ID => MAINNET_WETH.parse().ok(),
+            None => None,
+        }
+    }
+
+    /// `poll_interval_ms`, but no shorter than a block.
+    pub fn poll_interval(&self, bot: &BotConfig) -> Duration {
+        Duration::from_millis(bot.poll_interval_ms.max(self.block_time_ms.unwrap_or_default()))
+    }
+
+    /// Pool addresses by DEX, as `PriceMonitor::new` takes them.
+    pub fn pool_addresses(&self) -> HashMap<String, Address> {
+        self.pools.iter().map(|pool| (pool.dex.clone(), pool.address)).collect()
+    }
+
+    /// Router that trades on the pool of `dex`.
+    pub fn router(&self, dex: &str) -> Option<Address> {
+        let pool = self.pools.iter().find(|pool| pool.dex == dex)?;
+        pool.router.or(self.uniswap_router)
+    }
+
+    pub fn token(&self, symbol: &str) -> Option<&TokenConfig> {
+        self.tokens.iter().find(|token| token.symbol == symbol)
+    }
+
+    /// Token the chain's PnL, exposure and loss limits are valued in: the quote
+    /// token of its first pool's pair.
+    pub fn quote_token(&self) -> Option<&TokenConfig> {
+        let pair = self.pools.iter().find_map(|pool| pool.pair.as_deref())?;
+        Some(self.pair_tokens(pair)?.1)
+    }
+
+    /// Base and quote tokens of a `BASE/QUOTE` pair.
+    pub fn pair_tokens(&self, pair: &str) -> Option<(&TokenConfig, &TokenConfig)> {
+        let (base, quote) = pair.split_once('/')?;
+        Some((self.token(base)?, self.token(quote)?))
+    }
+}
+
+/// The bot's settings, layered as defaults, then the TOML file, then `ARB__`
+/// environment overrides. Only `[thresholds]` can change while running; the
+/// other sections are structural and need a restart.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub bot: BotConfig,
+    pub thresholds: Thresholds,
+    pub risk: RiskConfig,
+    pub chains: Vec<ChainConfig>,
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pools: usize = self.chains.iter().map(|chain| chain.pools.len()).sum();
+        write!(
+            f,
+            "{} chain(s), {} pool(s), min profit {}, poll every {}ms",
+            self.chains.len(),
+            pools,
+            self.thresholds.min_profit,
+            self.bot.poll_interval_ms
+        )
+    }
+}
+
+/// Whether `value` is in `(above, up_to]`; false for NaN.
+fn in_range(value: f64, above: f64, up_to: f64) -> bool {
+    value > above && value <= up_to
+}
+
+/// Parses an override value as a TOML value, falling back to a plain string.
+fn parse_env_value(raw: &str) -> toml::Value {
+    match format!("value = {}", raw).parse::<toml::Table>() {
+        Ok(mut table) => table.remove("value").unwrap_or_else(|| toml::Value::String(raw.to_string())),
+        Err(_) => toml::Value::String(raw.to_string()),
+    }
+}
+
+fn apply_override(root: &mut toml::Table, var: &str, raw: &str) -> Result<(), ConfigError> {
+    let error = |message: String| ConfigError::Env { var: var.to_string(), message };
+    let path: Vec<String> = var[ENV_PREFIX.len()..].split("__").map(str::to_lowercase).collect();
+    if path.iter().any(String::is_empty) {
+        return Err(error("empty path segment".to_string()));
+    }
+
+    let (key, parents) = path.split_last().expect("At least one segment");
+    let mut table = root;
+    let mut segments = parents.iter();
+    while let Some(segment) = segments.next() {
+        let entry = table.entry(segment.clone()).or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        table = match entry {
+            toml::Value::Table(table) => table,
+            toml::Value::Array(entries) => {
+                let name = segments.next().ok_or_else(|| error(format!("{} needs an entry name", segment)))?;
+                entries
+                    .iter_mut()
+                    .filter_map(toml::Value::as_table_mut)
+                    .find(|entry| entry.get("name").and_then(toml::Value::as_str).is_some_and(|entry_name| entry_name.eq_ignore_ascii_case(name)))
+                    .ok_or_else(|| error(format!("no {} entry named {}", segment, name)))?
+            }
+            _ => return Err(error(format!("{} is not a section", segment))),
+        };
+    }
+    table.insert(key.clone(), parse_env_value(raw));
+    Ok(())
+}
+
+impl Config {
+    /// Reads `path` with overrides from the process environment.
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        Config::load_with_env(path, std::env::vars())
+    }
+
+    pub fn load_with_env<I: IntoIterator<Item = (String, String)>>(path: &Path, env: I) -> Result<Config, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Io { path: path.to_path_buf(), source })?;
+        Config::parse(&text, &path.display().to_string(), env)
+    }
+
+    /// Parses TOML text, applies the `ARB__` entries of `env` and validates.
+    pub fn parse<I: IntoIterator<Item = (String, String)>>(text: &str, origin: &str, env: I) -> Result<Config, ConfigError> {
+        let parse_error = |message: String| ConfigError::Parse { origin: origin.to_string(), message };
+        let mut root: toml::Table = text.parse().map_err(|e: toml::de::Error| parse_error(e.to_string()))?;
+
+        let mut overrides: Vec<(String, String)> = env.into_iter().filter(|(var, _)| var.starts_with(ENV_PREFIX)).collect();
+        overrides.sort();
+        for (var, value) in &overrides {
+            apply_override(&mut root, var, value)?;
+            info!("Config override from {}", var);
+        }
+
+        let config = Config::deserialize(toml::Value::Table(root)).map_err(|e| parse_error(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Collects every problem rather than stopping at the first.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        if self.bot.poll_interval_ms == 0 {
+            problems.push("bot.poll_interval_ms must be positive".to_string());
+        }
+        if !self.thresholds.min_profit.is_finite() || self.thresholds.min_profit < 0.0 {
+            problems.push("thresholds.min_profit must be a non-negative number".to_string());
+        }
+        for (chain, min_profit) in &self.thresholds.chain_min_profit {
+            if !self.chains.iter().any(|configured| configured.name == *chain) {
+                problems.push(format!("thresholds.chain_min_profit names unknown chain {}", chain));
+            }
+            if !min_profit.is_finite() || *min_profit < 0.0 {
+                problems.push(format!("thresholds.chain_min_profit.{} must be a non-negative number", chain));
+            }
+        }
+        if !in_range(self.risk.stop_loss_threshold, 0.0, 1.0) {
+            problems.push("risk.stop_loss_threshold must be in (0, 1]".to_string());
+        }
+        if !in_range(self.risk.max_price_impact_bps, 0.0, 10_000.0) {
+            problems.push("risk.max_price_impact_bps must be in (0, 10000]".to_string());
+        }
+        if !in_range(self.risk.max_daily_loss, 0.0, f64::INFINITY) {
+            problems.push("risk.max_daily_loss must be positive".to_string());
+        }
+        if !in_range(self.risk.max_oracle_deviation_bps, 0.0, 10_000.0) {
+            problems.push("risk.max_oracle_deviation_bps must be in (0, 10000]".to_string());
+        }
+
+        if self.chains.is_empty() {
+            problems.push("at least one [[chains]] entry is required".to_string());
+        }
+        let mut chain_names = HashSet::new();
+        let mut chain_ids = HashSet::new();
+        for chain in &self.chains {
+            let name = &chain.name;
+            if !chain_names.insert(name.as_str()) {
+                problems.push(format!("chain {} is defined twice", name));
+            }
+            if !chain_ids.insert(chain.chain_id) {
+                problems.push(format!("chain {}: chain_id {} is used by another chain", name, chain.chain_id));
+            }
+            if chain.rpc_urls.is_empty() {
+                problems.push(format!("chain {}: rpc_urls is empty", name));
+            }
+            for url in &chain.rpc_urls {
+                if !["http://", "https://"].iter().any(|scheme| url.starts_with(scheme)) {
+                    problems.push(format!("chain {}: rpc url {} must start with http(s)://", name, url));
+                }
+            }
+            if chain.rpc_requests_per_sec.is_some_and(|rate| !in_range(rate, 0.0, f64::INFINITY)) {
+                problems.push(format!("chain {}: rpc_requests_per_sec must be positive", name));
+            }
+            if chain.block_time_ms == Some(0) {
+                problems.push(format!("chain {}: block_time_ms must be positive", name));
+            }
+            if chain.max_daily_loss.is_some_and(|loss| !in_range(loss, 0.0, f64::INFINITY)) {
+                problems.push(format!("chain {}: max_daily_loss must be positive", name));
+            }
+            if chain.uniswap_router.is_some() && chain.wrapped_native().is_none() {
+                problems.push(format!("chain {}: wrapped_native is required to trade off mainnet", name));
+            }
+            if let Some(quorum) = chain.read_quorum {
+                if quorum == 0 || quorum > chain.rpc_urls.len() {
+                    problems.push(format!("chain {}: read_quorum {} must be between 1 and the {} rpc url(s)", name, quorum, chain.rpc_urls.len()));
+                }
+            }
+
+            let mut symbols = HashSet::new();
+            for token in &chain.tokens {
+                if !symbols.insert(token.symbol.as_str()) {
+                    problems.push(format!("chain {}: token {} is defined twice", name, token.symbol));
+                }
+                if token.decimals > 36 {
+                    problems.push(format!("chain {}: token {} has {} decimals", name, token.symbol, token.decimals));
+                }
+                if token.max_position.is_some_and(|max_position| !in_range(max_position, 0.0, f64::INFINITY)) {
+                    problems.push(format!("chain {}: token {} max_position must be positive", name, token.symbol));
+                }
+            }
+
+            let mut dexes = HashSet::new();
+            for pool in &chain.pools {
+                if !dexes.insert(pool.dex.as_str()) {
+                    problems.push(format!("chain {}: pool for {} is defined twice", name, pool.dex));
+                }
+                if let Some(pair) = &pool.pair {
+                    match pair.split_once('/') {
+                        Some((base, quote)) => {
+                            for symbol in [base, quote] {
+                                if !symbols.contains(symbol) {
+                                    problems.push(format!("chain {}: pool {} trades unknown token {}", name, pool.dex, symbol));
+                                }
+                            }
+                        }
+                        None => problems.push(format!("chain {}: pool {} pair {} is not BASE/QUOTE", name, pool.dex, pair)),
+                    }
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
+    pub fn chain(&self, name: &str) -> Option<&ChainConfig> {
+        self.chains.iter().find(|chain| chain.name == name)
+    }
+
+    /// Sections that differ from `other` and cannot be changed while running.
+    pub fn structural_changes(&self, other: &Config) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        if self.bot != other.bot {
+            changed.push("bot");
+        }
+        if self.risk != other.risk {
+            changed.push("risk");
+        }
+        if self.chains != other.chains {
+            changed.push("chains");
+        }
+        changed
+    }
+
+    /// Pushes the reloadable settings into a running bot.
+    pub fn apply_to_control(&self, control: &ControlState) {
+        if control.thresholds() != self.thresholds {
+            if let Err(e) = control.update_thresholds(self.thresholds.clone().into()) {
+                warn!("Failed to apply thresholds: {}", e);
+            }
+        }
+    }
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Reloads the config file when it changes, accepting only non-structural edits.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    current: Config,
+    modified: Option<SystemTime>,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: &Path, current: Config) -> Self {
+        ConfigWatcher { path: path.to_path_buf(), modified: modified_at(path), current }
+    }
+
+    pub fn current(&self) -> &Config {
+        &self.current
+    }
+
+    /// Returns the new config if the file changed since the last check. A
+    /// file that fails validation or touches structural sections is rejected
+    /// and the current config kept.
+    pub fn check(&mut self) -> Result<Option<&Config>, ConfigError> {
+        let modified = modified_at(&self.path);
+        if modified == self.modified {
+            return Ok(None);
+        }
+        self.modified = modified;
+
+        let reloaded = Config::load(&self.path)?;
+        let structural = self.current.structural_changes(&reloaded);
+        if !structural.is_empty() {
+            return Err(ConfigError::Structural(structural));
+        }
+        if reloaded == self.current {
+            return Ok(None);
+        }
+        info!("Reloaded config {}", self.path.display());
+        self.current = reloaded;
+        Ok(Some(&self.current))
+    }
+
+    /// Checks every `interval` on a background thread and hands accepted
+    /// configs to `on_reload`.
+    pub fn watch<F>(mut self, interval: Duration, on_reload: F) -> JoinHandle<()>
+    where
+        F: Fn(&Config) + Send + 'static,
+    {
+        std::thread::spawn(move || loop {
+            std::thread::sleep(interval);
+            match self.check() {
+                Ok(Some(config)) => on_reload(config),
+                Ok(None) => {}
+                Err(e) => warn!("Ignoring config change: {}", e),
+            }
+        })
+    }
+}
@@ -0,0 +1,355 @@
+use crate::arbitrage_finder::ArbitrageOpportunity;
+use crate::circuit_breaker::{BreakerReason, CircuitBreaker};
+use crate::inventory::Inventory;
+use crate::logger::AUDIT_TARGET;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use log::{info, warn};
+
+const RECENT_OPPORTUNITIES: usize = 100;
+
+/// Limits the operator can change while the bot runs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Thresholds {
+    /// Opportunities below this profit are ignored.
+    pub min_profit: f64,
+    /// Minimum profit per chain, in that chain's quote token; chains not
+    /// listed use `min_profit`.
+    pub chain_min_profit: BTreeMap<String, f64>,
+    /// Books and pool reads older than this are not traded against.
+    pub max_book_age_secs: u64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds { min_profit: 0.0, chain_min_profit: BTreeMap::new(), max_book_age_secs: 30 }
+    }
+}
+
+impl Thresholds {
+    pub fn min_profit_for(&self, chain: &str) -> f64 {
+        self.chain_min_profit.get(chain).copied().unwrap_or(self.min_profit)
+    }
+}
+
+/// Partial update accepted by `PUT /thresholds`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThresholdUpdate {
+    pub min_profit: Option<f64>,
+    /// Replaces every per-chain minimum.
+    pub chain_min_profit: Option<BTreeMap<String, f64>>,
+    pub max_book_age_secs: Option<u64>,
+}
+
+impl From<Thresholds> for ThresholdUpdate {
+    fn from(thresholds: Thresholds) -> Self {
+        ThresholdUpdate {
+            min_profit: Some(thresholds.min_profit),
+            chain_min_profit: Some(thresholds.chain_min_profit),
+            max_book_age_secs: Some(thresholds.max_book_age_secs),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BookStatus {
+    pub venue: String,
+    pub best_bid: Option<f64>,
+    pub best_ask: Option<f64>,
+    pub updated_at: DateTime<Utc>,
+    pub staleness_secs: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PoolStatus {
+    pub dex: String,
+    pub price: f64,
+    pub updated_at: DateTime<Utc>,
+    pub staleness_secs: f64,
+}
+
+fn staleness_secs(updated_at: DateTime<Utc>) -> f64 {
+    (Utc::now() - updated_at).num_milliseconds().max(0) as f64 / 1000.0
+}
+
+/// State shared between the running bot and the control API.
+#[derive(Default)]
+pub struct ControlState {
+    paused: AtomicBool,
+    thresholds: Mutex<Thresholds>,
+    books: Mutex<HashMap<String, BookStatus>>,
+    pools: Mutex<HashMap<String, (f64, DateTime<Utc>)>>,
+    opportunities: Mutex<VecDeque<ArbitrageOpportunity>>,
+    circuit_breakers: Vec<(String, Arc<CircuitBreaker>)>,
+    inventories: Mutex<Vec<(String, Arc<Mutex<Inventory>>)>>,
+}
+
+impl ControlState {
+    pub fn new() -> Self {
+        ControlState::default()
+    }
+
+    /// Adds `chain`'s breaker; `POST /kill` trips all of them.
+    pub fn add_circuit_breaker(&mut self, chain: &str, circuit_breaker: Arc<CircuitBreaker>) {
+        self.circuit_breakers.push((chain.to_string(), circuit_breaker));
+    }
+
+    /// Adds `chain`'s inventory, whose value and PnL `GET /status` reports.
+    pub fn add_inventory(&self, chain: &str, inventory: Arc<Mutex<Inventory>>) {
+        self.inventories.lock().unwrap().push((chain.to_string(), inventory));
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub fn pause(&self, operator: &str) {
+        self.paused.store(true, Ordering::SeqCst);
+        warn!(target: AUDIT_TARGET, "Execution paused by {}", operator);
+    }
+
+    pub fn resume(&self, operator: &str) {
+        self.paused.store(false, Ordering::SeqCst);
+        info!(target: AUDIT_TARGET, "Execution resumed by {}", operator);
+    }
+
+    /// Pauses and trips every circuit breaker, which only `breaker reset` clears.
+    pub fn kill(&self, operator: &str) {
+        self.pause(operator);
+        for (_, circuit_breaker) in &self.circuit_breakers {
+            circuit_breaker.trip(BreakerReason::KillSwitch(operator.to_string()));
+        }
+    }
+
+    pub fn thresholds(&self) -> Thresholds {
+        self.thresholds.lock().unwrap().clone()
+    }
+
+    pub fn update_thresholds(&self, update: ThresholdUpdate) -> Result<Thresholds, String> {
+        let invalid = |min_profit: f64| !min_profit.is_finite() || min_profit < 0.0;
+        if update.min_profit.is_some_and(invalid) {
+            return Err("min_profit must be a non-negative number".to_string());
+        }
+        if let Some((chain, _)) = update.chain_min_profit.iter().flatten().find(|(_, min_profit)| invalid(**min_profit)) {
+            return Err(format!("chain_min_profit of {} must be a non-negative number", chain));
+        }
+        let mut thresholds = self.thresholds.lock().unwrap();
+        if let Some(min_profit) = update.min_profit {
+            thresholds.min_profit = min_profit;
+        }
+        if let Some(chain_min_profit) = update.chain_min_profit {
+            thresholds.chain_min_profit = chain_min_profit;
+        }
+        if let Some(max_book_age_secs) = update.max_book_age_secs {
+            thresholds.max_book_age_secs = max_book_age_secs;
+        }
+        info!(target: AUDIT_TARGET, "Thresholds changed to {:?}", *thresholds);
+        Ok(thresholds.clone())
+    }
+
+    pub fn record_book(&self, venue: &str, best_bid: Option<f64>, best_ask: Option<f64>) {
+        let book = BookStatus { venue: venue.to_string(), best_bid, best_ask, updated_at: Utc::now(), staleness_secs: 0.0 };
+        self.books.lock().unwrap().insert(venue.to_string(), book);
+    }
+
+    pub fn record_pool_price(&self, dex: &str, price: f64) {
+        self.pools.lock().unwrap().insert(dex.to_string(), (price, Utc::now()));
+    }
+
+    /// Whether `venue`'s book was updated within `max_book_age_secs`.
+    pub fn is_book_fresh(&self, venue: &str) -> bool {
+        let max_age = self.thresholds().max_book_age_secs as f64;
+        self.books.lock().unwrap().get(venue).is_some_and(|book| staleness_secs(book.updated_at) <= max_age)
+    }
+
+    pub fn books(&self) -> Vec<BookStatus> {
+        let mut books: Vec<BookStatus> = self
+            .books
+            .lock()
+            .unwrap()
+            .values()
+            .map(|book| BookStatus { staleness_secs: staleness_secs(book.updated_at), ..book.clone() })
+            .collect();
+        books.sort_by(|a, b| a.venue.cmp(&b.venue));
+        books
+    }
+
+    pub fn pools(&self) -> Vec<PoolStatus> {
+        let mut pools: Vec<PoolStatus> = self
+            .pools
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(dex, (price, updated_at))| PoolStatus {
+                dex: dex.clone(),
+                price: *price,
+                updated_at: *updated_at,
+                staleness_secs: staleness_secs(*updated_at),
+            })
+            .collect();
+        pools.sort_by(|a, b| a.dex.cmp(&b.dex));
+        pools
+    }
+
+    pub fn record_opportunity(&self, opportunity: &ArbitrageOpportunity) {
+        let mut opportunities = self.opportunities.lock().unwrap();
+        if opportunities.len() == RECENT_OPPORTUNITIES {
+            opportunities.pop_front();
+        }
+        opportunities.push_back(opportunity.clone());
+    }
+
+    /// Up to `limit` opportunities, newest first.
+    pub fn recent_opportunities(&self, limit: usize) -> Vec<ArbitrageOpportunity> {
+        self.opportunities.lock().unwrap().iter().rev().take(limit).cloned().collect()
+    }
+
+    fn status(&self) -> Value {
+        let circuit_breakers: serde_json::Map<String, Value> = self
+            .circuit_breakers
+            .iter()
+            .map(|(chain, breaker)| {
+                let tripped = breaker.tripped().map(|tripped| json!({ "reason": tripped.reason.to_string(), "tripped_at": tripped.tripped_at }));
+                (chain.clone(), json!(tripped))
+            })
+            .collect();
+        let inventories: serde_json::Map<String, Value> = self
+            .inventories
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(chain, inventory)| {
+                let inventory = inventory.lock().unwrap();
+                // Unpriced until the chain's first scan
+                let exposure = inventory.exposure().ok().map(|exposure| {
+                    exposure.into_iter().map(|(token, value)| (format!("{:?}", token), json!(value))).collect::<serde_json::Map<String, Value>>()
+                });
+                let status = json!({
+                    "exposure": exposure,
+                    "total_value": inventory.total_value().ok(),
+                    "realized_pnl": inventory.realized_pnl(),
+                });
+                (chain.clone(), status)
+            })
+            .collect();
+        json!({
+            "paused": self.is_paused(),
+            "circuit_breakers": circuit_breakers,
+            "inventories": inventories,
+            "thresholds": self.thresholds(),
+        })
+    }
+}
+
+/// Compares in time independent of where the tokens differ.
+fn token_matches(expected: &str, given: &str) -> bool {
+    expected.len() == given.len() && expected.bytes().zip(given.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+fn error(status: u16, message: &str) -> (u16, Value) {
+    (status, json!({ "error": message }))
+}
+
+/// Routes an authenticated request. Endpoints:
+///
+/// - `GET /status`: pause state, each chain's circuit breaker, inventory value
+///   and PnL, and thresholds
+/// - `GET /books`: order books and pools with staleness
+/// - `GET /opportunities?limit=N`: recent opportunities, newest first
+/// - `POST /pause`, `POST /resume`: stop or restart execution
+/// - `GET /thresholds`, `PUT /thresholds`: read or partially update thresholds
+/// - `POST /kill`: pause and trip every circuit breaker
+pub fn route(state: &ControlState, method: &str, url: &str, body: &str) -> (u16, Value) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    match (method, path) {
+        ("GET", "/status") => (200, state.status()),
+        ("GET", "/books") => (200, json!({ "books": state.books(), "pools": state.pools() })),
+        ("GET", "/opportunities") => {
+            let limit = query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("limit="))
+                .map(str::parse)
+                .unwrap_or(Ok(20));
+            match limit {
+                Ok(limit) => (200, json!(state.recent_opportunities(limit))),
+                Err(_) => error(400, "limit must be a number"),
+            }
+        }
+        ("POST", "/pause") => {
+            state.pause("control API");
+            (200, state.status())
+        }
+        ("POST", "/resume") => {
+            state.resume("control API");
+            (200, state.status())
+        }
+        ("GET", "/thresholds") => (200, json!(state.thresholds())),
+        ("PUT", "/thresholds") => match serde_json::from_str::<ThresholdUpdate>(body) {
+            Ok(update) => match state.update_thresholds(update) {
+                Ok(thresholds) => (200, json!(thresholds)),
+                Err(e) => error(400, &e),
+            },
+            Err(e) => error(400, &format!("Invalid thresholds: {}", e)),
+        },
+        ("POST", "/kill") => {
+            state.kill("control API");
+            (200, state.status())
+        }
+        (_, "/status" | "/books" | "/opportunities" | "/pause" | "/resume" | "/thresholds" | "/kill") => error(405, "Method not allowed"),
+        _ => error(404, "Not found"),
+    }
+}
+
+/// A running control API.
+pub struct ControlServer {
+    pub addr: SocketAddr,
+    pub handle: JoinHandle<()>,
+}
+
+/// Serves the control API on `addr`, e.g. `127.0.0.1:9899`, from a background
+/// thread. Every request needs `Authorization: Bearer <token>`.
+pub fn serve(addr: &str, token: &str, state: Arc<ControlState>) -> Result<ControlServer, Box<dyn std::error::Error + Send + Sync>> {
+    if token.is_empty() {
+        return Err("The control API needs a non-empty bearer token".into());
+    }
+    let server = tiny_http::Server::http(addr)?;
+    let addr = server.server_addr().to_ip().ok_or("Control API is not listening on an IP address")?;
+    let expected = format!("Bearer {}", token);
+    info!("Serving control API on http://{}", addr);
+
+    let handle = std::thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let authorized = request
+                .headers()
+                .iter()
+                .find(|header| header.field.equiv("Authorization"))
+                .is_some_and(|header| token_matches(&expected, header.value.as_str()));
+
+            let (status, body) = if !authorized {
+                warn!("Rejected unauthenticated control request {} {}", request.method(), request.url());
+                error(401, "Unauthorized")
+            } else {
+                let mut body = String::new();
+                match request.as_reader().read_to_string(&mut body) {
+                    Ok(_) => route(&state, request.method().as_str(), request.url(), &body),
+                    Err(e) => error(400, &format!("Unreadable body: {}", e)),
+                }
+            };
+
+            let header = tiny_http::Header::from_bytes("Content-Type", "application/json").expect("Valid header");
+            let response = tiny_http::Response::from_string(body.to_string()).with_status_code(status).with_header(header);
+            if let Err(e) = request.respond(response) {
+                warn!("Failed to answer control request: {}", e);
+            }
+        }
+    });
+    Ok(ControlServer { addr, handle })
+}
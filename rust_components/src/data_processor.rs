@@ -1,3 +1,4 @@
+use crate::metrics;
 use crate::order_book::OrderBook;
 use crate::order::Order;
 use std::collections::HashMap;
@@ -20,6 +21,7 @@ impl DataProcessor {
                 eprintln!("Error adding order to {}: {:?}", exchange, e);
             }
         }
+        metrics::record_book_update(exchange);
     }
 
     pub fn get_order_book(&self, exchange: &str) -> Option<&OrderBook> {
@@ -0,0 +1,37 @@
+//! C entry points of the `cdylib`.
+
+use std::ffi::{c_char, CStr};
+
+/// An order book as `check_arbitrage` takes it: `[price, amount]` levels.
+#[derive(serde::Deserialize)]
+struct FfiOrderBook {
+    bids: Vec<(f64, f64)>,
+    asks: Vec<(f64, f64)>,
+}
+
+impl FfiOrderBook {
+    unsafe fn parse(json: *const c_char) -> Option<(Vec<crate::order::Order>, Vec<crate::order::Order>)> {
+        if json.is_null() {
+            return None;
+        }
+        let book: FfiOrderBook = serde_json::from_str(CStr::from_ptr(json).to_str().ok()?).ok()?;
+        let orders = |levels: Vec<(f64, f64)>| levels.into_iter().map(|(price, amount)| crate::order::Order::new(price, amount).ok()).collect::<Option<Vec<_>>>();
+        Some((orders(book.bids)?, orders(book.asks)?))
+    }
+}
+
+/// Whether buying on `exchange2` and selling on `exchange1` is profitable.
+/// Both are JSON order books, e.g. `{"bids": [[100.0, 1.0]], "asks": [[101.0, 2.0]]}`;
+/// null or malformed books have no arbitrage.
+///
+/// # Safety
+///
+/// Both pointers must be null or point to NUL-terminated strings that stay
+/// valid for the duration of the call.
+#[no_mangle]
+pub unsafe extern "C" fn check_arbitrage(exchange1: *const c_char, exchange2: *const c_char) -> bool {
+    let (Some((bids1, asks1)), Some((bids2, asks2))) = (FfiOrderBook::parse(exchange1), FfiOrderBook::parse(exchange2)) else {
+        return false;
+    };
+    matches!(crate::arbitrage_engine::ArbitrageEngine::new().calculate_arbitrage(&bids1, &asks1, &bids2, &asks2), Ok(Some(_)))
+}
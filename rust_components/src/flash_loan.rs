@@ -0,0 +1,93 @@
+use ethers::abi::{self, ParamType, Token};
+use ethers::prelude::*;
+use thiserror::Error;
+
+abigen!(
+    AavePool,
+    r#"[
+        function flashLoan(address receiverAddress, address[] assets, uint256[] amounts, uint256[] interestRateModes, address onBehalfOf, bytes params, uint16 referralCode) external
+        function FLASHLOAN_PREMIUM_TOTAL() external view returns (uint128)
+    ]"#
+);
+
+/// Aave expresses the flash-loan premium in basis points.
+const PERCENTAGE_FACTOR: u64 = 10_000;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum FlashLoanError {
+    #[error("Invalid flash loan params: {0}")]
+    InvalidParams(String),
+    #[error("Flash loan path must start and end with the borrowed asset {asset:?}")]
+    NotRoundTrip { asset: Address },
+    #[error("Expected profit {expected_profit} does not cover premium {premium} plus costs {costs}")]
+    Unprofitable { expected_profit: U256, premium: U256, costs: U256 },
+}
+
+/// Callback params for `executeOperation`, which decodes them as
+/// `abi.decode(params, (address[], uint256, uint256))`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlashLoanParams {
+    pub path: Vec<Address>,
+    pub min_amount_out: U256,
+    pub deadline: U256,
+}
+
+impl FlashLoanParams {
+    pub fn new(path: Vec<Address>, min_amount_out: U256, deadline: U256) -> Self {
+        FlashLoanParams {
+            path,
+            min_amount_out,
+            deadline,
+        }
+    }
+
+    pub fn encode(&self) -> Bytes {
+        abi::encode(&[
+            Token::Array(self.path.iter().copied().map(Token::Address).collect()),
+            Token::Uint(self.min_amount_out),
+            Token::Uint(self.deadline),
+        ])
+        .into()
+    }
+
+    /// `executeOperation` swaps the borrowed `asset` along `path` and repays the
+    /// loan out of the output, so the path must start and end with it.
+    pub fn check_round_trip(&self, asset: Address) -> Result<(), FlashLoanError> {
+        if self.path.len() < 2 || self.path.first() != Some(&asset) || self.path.last() != Some(&asset) {
+            return Err(FlashLoanError::NotRoundTrip { asset });
+        }
+        Ok(())
+    }
+
+    pub fn decode(data: &Bytes) -> Result<Self, FlashLoanError> {
+        let types = [ParamType::Array(Box::new(ParamType::Address)), ParamType::Uint(256), ParamType::Uint(256)];
+        let tokens = abi::decode(&types, data).map_err(|e| FlashLoanError::InvalidParams(e.to_string()))?;
+
+        match tokens.as_slice() {
+            [Token::Array(path), Token::Uint(min_amount_out), Token::Uint(deadline)] => {
+                let path = path
+                    .iter()
+                    .map(|token| token.clone().into_address())
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| FlashLoanError::InvalidParams("path must contain addresses".to_string()))?;
+                Ok(FlashLoanParams::new(path, *min_amount_out, *deadline))
+            }
+            _ => Err(FlashLoanError::InvalidParams("unexpected token layout".to_string())),
+        }
+    }
+}
+
+/// Premium the pool charges on `amount`, rounded half up like Aave's `percentMul`.
+pub fn flash_loan_premium(amount: U256, premium_bps: U256) -> U256 {
+    (amount * premium_bps + U256::from(PERCENTAGE_FACTOR / 2)) / U256::from(PERCENTAGE_FACTOR)
+}
+
+/// Net profit of repaying `amount + premium` out of `amount_out`. Fails unless it
+/// also covers `costs`, all in units of the borrowed token.
+pub fn check_flash_loan_profit(amount: U256, amount_out: U256, premium: U256, costs: U256) -> Result<U256, FlashLoanError> {
+    let expected_profit = amount_out.saturating_sub(amount).saturating_sub(premium);
+    if expected_profit <= costs {
+        return Err(FlashLoanError::Unprofitable { expected_profit, premium, costs });
+    }
+    Ok(expected_profit - costs)
+}
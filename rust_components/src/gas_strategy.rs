@@ -0,0 +1,178 @@
+use crate::provider_pool::PooledProvider;
+use ethers::prelude::*;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+const BASE_FEE_MULTIPLIER: u64 = 2;
+const BPS_DENOMINATOR: u64 = 10_000;
+
+#[derive(Error, Debug)]
+pub enum GasStrategyError {
+    #[error("Gas cost {gas_cost} exceeds budget {budget}")]
+    OverBudget { gas_cost: U256, budget: U256 },
+    #[error("Provider error: {0}")]
+    Provider(#[from] ProviderError),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GasFees {
+    Eip1559 {
+        base_fee_per_gas: U256,
+        max_fee_per_gas: U256,
+        max_priority_fee_per_gas: U256,
+    },
+    Legacy {
+        gas_price: U256,
+    },
+}
+
+impl GasFees {
+    /// Highest price per gas the transaction may pay.
+    pub fn max_gas_price(&self) -> U256 {
+        match self {
+            GasFees::Eip1559 { max_fee_per_gas, .. } => *max_fee_per_gas,
+            GasFees::Legacy { gas_price } => *gas_price,
+        }
+    }
+
+    /// Price per gas the transaction is expected to pay: the base fee plus the
+    /// tip, or the legacy gas price.
+    pub fn expected_gas_price(&self) -> U256 {
+        match self {
+            GasFees::Eip1559 { base_fee_per_gas, max_fee_per_gas, max_priority_fee_per_gas } => {
+                (*base_fee_per_gas + *max_priority_fee_per_gas).min(*max_fee_per_gas)
+            }
+            GasFees::Legacy { gas_price } => *gas_price,
+        }
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        matches!(self, GasFees::Legacy { .. })
+    }
+
+    /// Writes the fees into `tx`, converting it to a legacy transaction if needed.
+    pub fn apply(&self, tx: &mut TypedTransaction) {
+        match self {
+            GasFees::Eip1559 { max_fee_per_gas, max_priority_fee_per_gas, .. } => {
+                if let TypedTransaction::Eip1559(inner) = tx {
+                    inner.max_fee_per_gas = Some(*max_fee_per_gas);
+                    inner.max_priority_fee_per_gas = Some(*max_priority_fee_per_gas);
+                } else {
+                    tx.set_gas_price(*max_fee_per_gas);
+                }
+            }
+            GasFees::Legacy { gas_price } => {
+                if let TypedTransaction::Eip1559(inner) = tx {
+                    *tx = TypedTransaction::Legacy(inner.clone().into());
+                }
+                tx.set_gas_price(*gas_price);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GasStrategy {
+    fee_history_blocks: u64,
+    reward_percentile: f64,
+    max_profit_fraction: f64,
+    gas_limit_margin: f64,
+}
+
+impl Default for GasStrategy {
+    fn default() -> Self {
+        GasStrategy::new(10, 50.0, 0.5, 0.2)
+    }
+}
+
+impl GasStrategy {
+    /// `max_profit_fraction` caps total gas spend relative to expected net profit;
+    /// `gas_limit_margin` is added on top of `eth_estimateGas` (0.2 = +20%).
+    pub fn new(fee_history_blocks: u64, reward_percentile: f64, max_profit_fraction: f64, gas_limit_margin: f64) -> Self {
+        GasStrategy {
+            fee_history_blocks,
+            reward_percentile,
+            max_profit_fraction,
+            gas_limit_margin,
+        }
+    }
+
+    /// Estimates EIP-1559 fees from `eth_feeHistory`, falling back to a legacy
+    /// gas price on chains that do not support it.
+    pub async fn estimate_fees(&self, provider: &PooledProvider) -> Result<GasFees, GasStrategyError> {
+        match provider
+            .fee_history(self.fee_history_blocks, BlockNumber::Latest, &[self.reward_percentile])
+            .await
+        {
+            Ok(history) => {
+                if let Some(fees) = self.fees_from_history(&history) {
+                    info!("Estimated EIP-1559 fees: {:?}", fees);
+                    return Ok(fees);
+                }
+                debug!("Fee history has no base fee, using legacy gas price");
+            }
+            Err(e) => warn!("eth_feeHistory unavailable, using legacy gas price: {}", e),
+        }
+
+        let gas_price = provider.get_gas_price().await?;
+        info!("Estimated legacy gas price: {}", gas_price);
+        Ok(GasFees::Legacy { gas_price })
+    }
+
+    /// Derives fees from a fee history: the next block's base fee and the median of
+    /// the sampled priority fees. Returns `None` when the chain reports no base fee.
+    pub fn fees_from_history(&self, history: &FeeHistory) -> Option<GasFees> {
+        let base_fee_per_gas = history.base_fee_per_gas.last().copied()?;
+        if base_fee_per_gas.is_zero() {
+            return None;
+        }
+
+        let mut rewards: Vec<U256> = history
+            .reward
+            .iter()
+            .filter_map(|block| block.first().copied())
+            .collect();
+        rewards.sort();
+        let max_priority_fee_per_gas = rewards.get(rewards.len() / 2).copied().unwrap_or_default();
+
+        Some(GasFees::Eip1559 {
+            base_fee_per_gas,
+            max_fee_per_gas: base_fee_per_gas * BASE_FEE_MULTIPLIER + max_priority_fee_per_gas,
+            max_priority_fee_per_gas,
+        })
+    }
+
+    /// Holds gas to the configured fraction of `expected_profit`, in wei, for
+    /// either kind of fees: errors if `gas_limit` at the expected gas price is
+    /// over that budget, and otherwise lowers the most the transaction may pay
+    /// per gas to the budget.
+    pub fn cap_to_profit(&self, fees: GasFees, gas_limit: U256, expected_profit: U256) -> Result<GasFees, GasStrategyError> {
+        let fraction_bps = U256::from((self.max_profit_fraction * BPS_DENOMINATOR as f64) as u64);
+        let budget = expected_profit * fraction_bps / BPS_DENOMINATOR;
+        let gas_cost = fees.expected_gas_price().saturating_mul(gas_limit);
+        if gas_cost > budget {
+            warn!("Gas cost {} exceeds budget {}", gas_cost, budget);
+            return Err(GasStrategyError::OverBudget { gas_cost, budget });
+        }
+
+        match fees {
+            GasFees::Eip1559 { base_fee_per_gas, max_fee_per_gas, max_priority_fee_per_gas } if max_fee_per_gas.saturating_mul(gas_limit) > budget => {
+                let capped = GasFees::Eip1559 {
+                    base_fee_per_gas,
+                    max_fee_per_gas: budget / gas_limit,
+                    max_priority_fee_per_gas,
+                };
+                info!("Capped gas fees to profit budget {}: {:?}", budget, capped);
+                Ok(capped)
+            }
+            _ => Ok(fees),
+        }
+    }
+
+    pub fn gas_limit_with_margin(&self, estimate: U256) -> U256 {
+        let margin_bps = U256::from((self.gas_limit_margin * BPS_DENOMINATOR as f64) as u64);
+        estimate + estimate * margin_bps / BPS_DENOMINATOR
+    }
+}
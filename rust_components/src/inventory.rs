@@ -0,0 +1,308 @@
+use crate::paper_executor::PaperFill;
+use crate::provider_pool::PooledProvider;
+use crate::scanner::to_units;
+use ethers::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use log::{error, info, warn};
+use thiserror::Error;
+
+abigen!(
+    ERC20,
+    r#"[
+        function balanceOf(address account) external view returns (uint256)
+        event Transfer(address indexed from, address indexed to, uint256 value)
+    ]"#
+);
+
+const DEFAULT_DECIMALS: u8 = 18;
+
+/// Stands in for the chain's own currency, e.g. ETH, which pays for gas and has
+/// no `balanceOf`.
+pub const NATIVE_CURRENCY: Address = H160([0xee; 20]);
+
+#[derive(Error, Debug, PartialEq)]
+pub enum InventoryError {
+    #[error("Insufficient {token:?} at {location}: available {available}, required {required}")]
+    Insufficient { location: Location, token: Address, available: U256, required: U256 },
+    #[error("No price for token {0:?}")]
+    MissingPrice(Address),
+    #[error("Balance query failed: {0}")]
+    Provider(String),
+}
+
+/// Where a balance is held: an on-chain wallet or contract, or an exchange account.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Location {
+    Wallet(Address),
+    Venue(String),
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::Wallet(address) => write!(f, "wallet {:?}", address),
+            Location::Venue(venue) => write!(f, "venue {}", venue),
+        }
+    }
+}
+
+/// Difference between the tracked balance and what reconciliation observed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Discrepancy {
+    pub location: Location,
+    pub token: Address,
+    pub tracked: U256,
+    pub observed: U256,
+}
+
+/// Balances per (location, token), marked to a quote currency for exposure and PnL.
+#[derive(Debug)]
+pub struct Inventory {
+    balances: HashMap<(Location, Address), U256>,
+    wallets: HashSet<Address>,
+    prices: HashMap<Address, f64>,
+    decimals: HashMap<Address, u8>,
+    native_token: Address,
+    realized_pnl: f64,
+}
+
+impl Inventory {
+    /// `native_token` wraps the chain's currency, e.g. WETH on mainnet. Gas is
+    /// debited from the `NATIVE_CURRENCY` balance and valued at its price.
+    pub fn new(native_token: Address) -> Self {
+        Inventory {
+            balances: HashMap::new(),
+            wallets: HashSet::new(),
+            prices: HashMap::new(),
+            decimals: HashMap::new(),
+            native_token,
+            realized_pnl: 0.0,
+        }
+    }
+
+    /// Wallets whose transfers and gas are picked up from receipts.
+    pub fn track_wallet(&mut self, wallet: Address) {
+        self.wallets.insert(wallet);
+    }
+
+    pub fn set_decimals(&mut self, token: Address, decimals: u8) {
+        self.decimals.insert(token, decimals);
+    }
+
+    /// Price of one whole `token` in the quote currency.
+    pub fn set_price(&mut self, token: Address, price: f64) {
+        self.prices.insert(token, price);
+    }
+
+    pub fn balance(&self, location: &Location, token: Address) -> U256 {
+        self.balances.get(&(location.clone(), token)).copied().unwrap_or_default()
+    }
+
+    pub fn set_balance(&mut self, location: Location, token: Address, amount: U256) {
+        self.balances.insert((location, token), amount);
+    }
+
+    pub fn credit(&mut self, location: &Location, token: Address, amount: U256) {
+        *self.balances.entry((location.clone(), token)).or_default() += amount;
+    }
+
+    pub fn debit(&mut self, location: &Location, token: Address, amount: U256) -> Result<(), InventoryError> {
+        let available = self.balance(location, token);
+        if available < amount {
+            return Err(InventoryError::Insufficient { location: location.clone(), token, available, required: amount });
+        }
+        self.balances.insert((location.clone(), token), available - amount);
+        Ok(())
+    }
+
+    /// Balance of `token` at `location` available for sizing a trade.
+    pub fn available(&self, location: &Location, token: Address) -> U256 {
+        self.balance(location, token)
+    }
+
+    /// Quote-currency value of `amount` of `token`.
+    pub fn value(&self, token: Address, amount: U256) -> Result<f64, InventoryError> {
+        let priced = if token == NATIVE_CURRENCY && !self.prices.contains_key(&token) { self.native_token } else { token };
+        let price = self.prices.get(&priced).ok_or(InventoryError::MissingPrice(token))?;
+        let decimals = self.decimals.get(&token).copied().unwrap_or(DEFAULT_DECIMALS);
+        Ok(to_units(amount, decimals) * price)
+    }
+
+    /// Quote-currency value held per token across all locations.
+    pub fn exposure(&self) -> Result<HashMap<Address, f64>, InventoryError> {
+        let mut exposure = HashMap::new();
+        for ((_, token), amount) in &self.balances {
+            *exposure.entry(*token).or_insert(0.0) += self.value(*token, *amount)?;
+        }
+        Ok(exposure)
+    }
+
+    pub fn total_value(&self) -> Result<f64, InventoryError> {
+        Ok(self.exposure()?.values().sum())
+    }
+
+    /// PnL of applied fills, valued at the prices in effect when each was applied.
+    pub fn realized_pnl(&self) -> f64 {
+        self.realized_pnl
+    }
+
+    /// Applies a swap: `amount_in` of `token_in` leaves `location` and `amount_out`
+    /// of `token_out` arrives, with `gas_cost` paid in the native currency.
+    /// Nothing changes unless `location` covers both.
+    pub fn apply_fill(&mut self, location: &Location, token_in: Address, amount_in: U256, token_out: Address, amount_out: U256, gas_cost: U256) -> Result<(), InventoryError> {
+        let mut required = HashMap::from([(token_in, amount_in)]);
+        let gas = required.entry(NATIVE_CURRENCY).or_default();
+        *gas = gas.saturating_add(gas_cost);
+        for (token, amount) in &required {
+            let available = self.balance(location, *token);
+            if available < *amount {
+                return Err(InventoryError::Insufficient { location: location.clone(), token: *token, available, required: *amount });
+            }
+        }
+        for (token, amount) in required {
+            self.debit(location, token, amount)?;
+        }
+        self.credit(location, token_out, amount_out);
+
+        match (self.value(token_out, amount_out), self.value(token_in, amount_in), self.value(NATIVE_CURRENCY, gas_cost)) {
+            (Ok(value_out), Ok(value_in), Ok(gas_value)) => self.realized_pnl += value_out - value_in - gas_value,
+            _ => warn!("Fill applied without PnL: missing price for {:?}, {:?} or gas token", token_in, token_out),
+        }
+        Ok(())
+    }
+
+    /// Applies a `PaperTradeExecutor` fill to `wallet`.
+    pub fn apply_paper_fill(&mut self, wallet: Address, fill: &PaperFill) -> Result<(), InventoryError> {
+        let (Some(token_in), Some(token_out)) = (fill.path.first(), fill.path.last()) else {
+            return Ok(());
+        };
+        let location = Location::Wallet(wallet);
+        if fill.success {
+            self.apply_fill(&location, *token_in, fill.amount_in, *token_out, fill.amount_out, fill.gas_cost())
+        } else {
+            self.apply_fill(&location, *token_in, U256::zero(), *token_out, U256::zero(), fill.gas_cost())
+        }
+    }
+
+    /// Applies the ERC20 `Transfer` logs and gas of a mined transaction to every
+    /// tracked wallet it touches.
+    pub fn apply_receipt(&mut self, receipt: &TransactionReceipt) {
+        let before = self.tracked_value();
+
+        for log in &receipt.logs {
+            let Ok(transfer) = parse_log::<TransferFilter>(log.clone()) else {
+                continue;
+            };
+            if self.wallets.contains(&transfer.from) {
+                let location = Location::Wallet(transfer.from);
+                if let Err(e) = self.debit(&location, log.address, transfer.value) {
+                    warn!("Receipt {:?} overdraws tracked balance: {}", receipt.transaction_hash, e);
+                    self.set_balance(location, log.address, U256::zero());
+                }
+            }
+            if self.wallets.contains(&transfer.to) {
+                self.credit(&Location::Wallet(transfer.to), log.address, transfer.value);
+            }
+        }
+
+        if self.wallets.contains(&receipt.from) {
+            let gas_cost = receipt.gas_used.unwrap_or_default() * receipt.effective_gas_price.unwrap_or_default();
+            let location = Location::Wallet(receipt.from);
+            if self.debit(&location, NATIVE_CURRENCY, gas_cost).is_err() {
+                self.set_balance(location, NATIVE_CURRENCY, U256::zero());
+            }
+        }
+
+        if let (Some(before), Some(after)) = (before, self.tracked_value()) {
+            self.realized_pnl += after - before;
+        }
+        info!("Applied receipt {:?} to inventory", receipt.transaction_hash);
+    }
+
+    fn tracked_value(&self) -> Option<f64> {
+        self.total_value().ok()
+    }
+
+    /// Overwrites tracked balances at `location` with an observed snapshot, e.g.
+    /// an exchange balance response, returning what differed.
+    pub fn reconcile_snapshot(&mut self, location: &Location, observed: &HashMap<Address, U256>) -> Vec<Discrepancy> {
+        let mut discrepancies = Vec::new();
+        for (token, amount) in observed {
+            let tracked = self.balance(location, *token);
+            if tracked != *amount {
+                warn!("Inventory mismatch at {} for {:?}: tracked {}, observed {}", location, token, tracked, amount);
+                discrepancies.push(Discrepancy { location: location.clone(), token: *token, tracked, observed: *amount });
+                self.set_balance(location.clone(), *token, *amount);
+            }
+        }
+        discrepancies
+    }
+}
+
+/// What the sender of a mined round trip made in the one token it started and
+/// ended in, from the receipt's `Transfer` logs, with gas netted when that
+/// token is `native_token` (e.g. WETH, worth gas one to one). `None` unless
+/// exactly one token moved in or out of the sender.
+pub fn round_trip_pnl(receipt: &TransactionReceipt, native_token: Address) -> Option<(Address, I256)> {
+    let mut flows: HashMap<Address, I256> = HashMap::new();
+    for log in &receipt.logs {
+        let Ok(transfer) = parse_log::<TransferFilter>(log.clone()) else {
+            continue;
+        };
+        let value = I256::try_from(transfer.value).ok()?;
+        if transfer.from == receipt.from {
+            *flows.entry(log.address).or_default() -= value;
+        }
+        if transfer.to == receipt.from {
+            *flows.entry(log.address).or_default() += value;
+        }
+    }
+    if flows.len() != 1 {
+        return None;
+    }
+    let (token, mut pnl) = flows.into_iter().next()?;
+    if token == native_token {
+        let gas_cost = receipt.gas_used.unwrap_or_default() * receipt.effective_gas_price.unwrap_or_default();
+        pnl -= I256::try_from(gas_cost).ok()?;
+    }
+    Some((token, pnl))
+}
+
+/// Reads `balanceOf` for each token held by `wallet`, and the account balance
+/// for `NATIVE_CURRENCY`.
+pub async fn fetch_wallet_balances(provider: Arc<PooledProvider>, wallet: Address, tokens: &[Address]) -> Result<HashMap<Address, U256>, InventoryError> {
+    let mut balances = HashMap::new();
+    for token in tokens {
+        let balance = if *token == NATIVE_CURRENCY {
+            provider.get_balance(wallet, None).await.map_err(|e| InventoryError::Provider(e.to_string()))?
+        } else {
+            ERC20::new(*token, provider.clone())
+                .balance_of(wallet)
+                .call()
+                .await
+                .map_err(|e| InventoryError::Provider(e.to_string()))?
+        };
+        balances.insert(*token, balance);
+    }
+    Ok(balances)
+}
+
+/// Reconciles every tracked wallet against on-chain `balanceOf` every `interval`.
+pub async fn run_reconciliation(inventory: Arc<Mutex<Inventory>>, provider: Arc<PooledProvider>, tokens: Vec<Address>, interval: Duration) {
+    loop {
+        let wallets: Vec<Address> = inventory.lock().unwrap().wallets.iter().copied().collect();
+        for wallet in wallets {
+            match fetch_wallet_balances(provider.clone(), wallet, &tokens).await {
+                Ok(observed) => {
+                    let discrepancies = inventory.lock().unwrap().reconcile_snapshot(&Location::Wallet(wallet), &observed);
+                    info!("Reconciled wallet {:?}: {} discrepancies", wallet, discrepancies.len());
+                }
+                Err(e) => error!("Failed to reconcile wallet {:?}: {}", wallet, e),
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
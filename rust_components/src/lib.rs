@@ -5,9 +5,32 @@ pub mod arbitrage_engine;
 pub mod errors;
 pub mod data_processor;
 pub mod main_module;
+pub mod trade_executor;
+pub mod paper_executor;
+pub mod gas_strategy;
+pub mod transaction_manager;
+pub mod signer;
+pub mod logger;
+pub mod arbitrage_bot_contract;
+pub mod flash_loan;
+pub mod bot_admin;
+pub mod oracle;
+pub mod price_history;
+pub mod rpc_retry;
+pub mod provider_pool;
+pub mod price_monitor;
+pub mod arbitrage_finder;
+pub mod risk_manager;
+pub mod inventory;
+pub mod circuit_breaker;
+pub mod opportunity_tracker;
+pub mod trade_journal;
+pub mod metrics;
+pub mod spans;
+pub mod alerts;
+pub mod control_api;
+pub mod config;
+pub mod scanner;
+pub mod ffi;
 
-
-#[no_mangle]
-pub extern "C" fn check_arbitrage(exchange1: *const c_char, exchange2: *const c_char) -> bool {
-    // Your Rust implementation here
-}
+pub use ffi::check_arbitrage;
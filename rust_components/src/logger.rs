@@ -1,46 +1,354 @@
-use log::{Level, LevelFilter, Metadata, Record, SetLoggerError};
+use crate::spans::{self, SpanContext};
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
 use chrono::Local;
-use std::sync::Once;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+static SECRETS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Log target for trade-critical events. Records with this target always reach
+/// the audit log, whatever the level filters say, e.g.
+/// `info!(target: AUDIT_TARGET, "Transaction {:?} confirmed", hash)`.
+pub const AUDIT_TARGET: &str = "audit";
+
+/// Registers a value, such as a private key, that must never appear in log
+/// output. Hex values are matched in any case, with or without a `0x` prefix.
+pub fn register_secret(secret: &str) {
+    let secret = secret.strip_prefix("0x").or_else(|| secret.strip_prefix("0X")).unwrap_or(secret).to_ascii_lowercase();
+    if secret.is_empty() {
+        return;
+    }
+    let mut secrets = SECRETS.lock().unwrap();
+    if !secrets.contains(&secret) {
+        secrets.push(secret);
+    }
+}
+
+/// Replaces every registered secret in `message` with `[REDACTED]`.
+pub fn redact(message: &str) -> String {
+    let secrets = SECRETS.lock().unwrap();
+    secrets.iter().fold(message.to_string(), |message, secret| redact_secret(&message, secret))
+}
+
+/// Case-insensitive replace of a lowercase `secret`, taking a `0x` in front of
+/// it along. ASCII lowercasing keeps byte offsets, so matches map back to `message`.
+fn redact_secret(message: &str, secret: &str) -> String {
+    let lower = message.to_ascii_lowercase();
+    let mut redacted = String::with_capacity(message.len());
+    let mut last = 0;
+    for (found, _) in lower.match_indices(secret) {
+        let start = if found >= last + 2 && lower[..found].ends_with("0x") { found - 2 } else { found };
+        redacted.push_str(&message[last..start]);
+        redacted.push_str("[REDACTED]");
+        last = found + secret.len();
+    }
+    redacted.push_str(&message[last..]);
+    redacted
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("Unknown log format {}, expected text or json", s)),
+        }
+    }
+}
+
+/// A default level plus per-module overrides, parsed from specs like
+/// `"info,rust_components::trade_executor=debug"`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelFilters {
+    pub default: LevelFilter,
+    pub modules: Vec<(String, LevelFilter)>,
+}
+
+impl LevelFilters {
+    pub fn new(default: LevelFilter) -> Self {
+        LevelFilters { default, modules: Vec::new() }
+    }
+
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut filters = LevelFilters::new(LevelFilter::Info);
+        for directive in spec.split(',').map(str::trim).filter(|directive| !directive.is_empty()) {
+            match directive.split_once('=') {
+                Some((module, level)) => {
+                    let level = level.parse().map_err(|_| format!("Invalid level in {}", directive))?;
+                    filters.modules.push((module.to_string(), level));
+                }
+                None => filters.default = directive.parse().map_err(|_| format!("Invalid level {}", directive))?,
+            }
+        }
+        Ok(filters)
+    }
+
+    /// Level for `target`, taken from the longest matching module prefix.
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .filter(|(module, _)| target == module || target.starts_with(&format!("{}::", module)))
+            .max_by_key(|(module, _)| module.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.modules.iter().map(|(_, level)| *level).fold(self.default, Ord::max)
+    }
+}
+
+/// When a log file is rotated to `<path>.1`, shifting older files up to `keep`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rotation {
+    pub max_bytes: Option<u64>,
+    pub max_age: Option<Duration>,
+    pub keep: usize,
+}
+
+impl Default for Rotation {
+    fn default() -> Self {
+        Rotation {
+            max_bytes: Some(10 * 1024 * 1024),
+            max_age: Some(Duration::from_secs(24 * 60 * 60)),
+            keep: 7,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileSink {
+    pub path: PathBuf,
+    pub rotation: Rotation,
+}
+
+impl FileSink {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        FileSink { path: path.into(), rotation: Rotation::default() }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LoggerConfig {
+    pub filters: LevelFilters,
+    pub format: LogFormat,
+    pub stdout: bool,
+    pub file: Option<FileSink>,
+    /// Receives every `AUDIT_TARGET` record as a JSON line.
+    pub audit_file: Option<FileSink>,
+}
+
+impl Default for LoggerConfig {
+    fn default() -> Self {
+        LoggerConfig {
+            filters: LevelFilters::new(LevelFilter::Info),
+            format: LogFormat::Text,
+            stdout: true,
+            file: None,
+            audit_file: None,
+        }
+    }
+}
+
+struct RotatingFile {
+    path: PathBuf,
+    rotation: Rotation,
+    file: File,
+    size: u64,
+    opened_at: SystemTime,
+}
+
+impl RotatingFile {
+    fn open(sink: &FileSink) -> io::Result<Self> {
+        if let Some(parent) = sink.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&sink.path)?;
+        let metadata = file.metadata()?;
+        Ok(RotatingFile {
+            path: sink.path.clone(),
+            rotation: sink.rotation.clone(),
+            size: metadata.len(),
+            opened_at: metadata.created().unwrap_or_else(|_| SystemTime::now()),
+            file,
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let too_big = self.rotation.max_bytes.is_some_and(|max_bytes| self.size > 0 && self.size + line.len() as u64 + 1 > max_bytes);
+        let too_old = self.rotation.max_age.is_some_and(|max_age| self.opened_at.elapsed().is_ok_and(|age| age >= max_age));
+        if too_big || too_old {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.file.flush()?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        for index in (1..self.rotation.keep).rev() {
+            let from = rotated_path(&self.path, index);
+            if from.exists() {
+                std::fs::rename(&from, rotated_path(&self.path, index + 1))?;
+            }
+        }
+        if self.rotation.keep > 0 {
+            std::fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        } else {
+            std::fs::remove_file(&self.path)?;
+        }
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        self.opened_at = SystemTime::now();
+        Ok(())
+    }
+}
+
+/// `<path>.<index>`, where rotated files are kept.
+pub fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{}", index));
+    PathBuf::from(rotated)
+}
 
 pub struct Logger {
-    level: Level,
+    config: LoggerConfig,
+    file: Option<Mutex<RotatingFile>>,
+    audit_file: Option<Mutex<RotatingFile>>,
 }
 
 impl Logger {
     pub fn new(level: Level) -> Self {
-        Logger { level }
+        let config = LoggerConfig {
+            filters: LevelFilters::new(level.to_level_filter()),
+            ..Default::default()
+        };
+        Logger { config, file: None, audit_file: None }
+    }
+
+    /// Opens the configured log files, creating their directories.
+    pub fn with_config(config: LoggerConfig) -> io::Result<Self> {
+        let file = config.file.as_ref().map(RotatingFile::open).transpose()?.map(Mutex::new);
+        let audit_file = config.audit_file.as_ref().map(RotatingFile::open).transpose()?.map(Mutex::new);
+        Ok(Logger { config, file, audit_file })
+    }
+
+    /// Lowest level any filter lets through; audit records need at least `Info`.
+    pub fn max_level(&self) -> LevelFilter {
+        let max_level = self.config.filters.max_level();
+        if self.audit_file.is_some() {
+            max_level.max(LevelFilter::Info)
+        } else {
+            max_level
+        }
+    }
+
+    /// Lines logged inside an opportunity's spans carry its id and block number.
+    fn format(&self, record: &Record, format: LogFormat) -> String {
+        let message = redact(&record.args().to_string());
+        let context = spans::current_context().filter(|context| context.opportunity_id.is_some());
+        match format {
+            LogFormat::Text => {
+                let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S%.3f");
+                let line = format!("{} - {} - {} - {}", timestamp, record.level(), record.target(), message);
+                match context {
+                    Some(SpanContext { stage, opportunity_id: Some(opportunity_id), block_number }) => {
+                        let block = block_number.map(|block| format!(" block={}", block)).unwrap_or_default();
+                        format!("{} [{} opportunity={}{}]", line, stage, opportunity_id, block)
+                    }
+                    _ => line,
+                }
+            }
+            LogFormat::Json => {
+                let mut line = serde_json::json!({
+                    "timestamp": Local::now().to_rfc3339(),
+                    "level": record.level().as_str(),
+                    "target": record.target(),
+                    "message": message,
+                });
+                if let Some(context) = context {
+                    line["stage"] = context.stage.into();
+                    line["opportunity_id"] = context.opportunity_id.into();
+                    line["block_number"] = context.block_number.into();
+                }
+                line.to_string()
+            }
+        }
     }
 }
 
-impl log::Log for Logger {
+fn write_or_report(file: &Mutex<RotatingFile>, line: &str) {
+    let mut file = file.lock().unwrap();
+    if let Err(e) = file.write_line(line) {
+        eprintln!("Failed to write log file {}: {}; dropped: {}", file.path.display(), e, line);
+    }
+}
+
+impl Log for Logger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= self.level
+        metadata.level() <= self.config.filters.level_for(metadata.target())
     }
 
     fn log(&self, record: &Record) {
-        if self.enabled(record.metadata()) {
-            let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S%.3f");
-            println!("{} - {} - {} - {}", timestamp, record.level(), record.target(), record.args());
+        if record.target() == AUDIT_TARGET {
+            if let Some(audit_file) = &self.audit_file {
+                write_or_report(audit_file, &self.format(record, LogFormat::Json));
+            }
+        }
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let line = self.format(record, self.config.format);
+        if self.config.stdout {
+            println!("{}", line);
+        }
+        if let Some(file) = &self.file {
+            write_or_report(file, &line);
         }
     }
 
-    fn flush(&self) {}
+    fn flush(&self) {
+        for file in [&self.file, &self.audit_file].into_iter().flatten() {
+            let _ = file.lock().unwrap().file.flush();
+        }
+    }
 }
 
-static INIT: Once = Once::new();
-static mut LOGGER: Option<Logger> = None;
+static LOGGER: OnceLock<Logger> = OnceLock::new();
 
+/// Installs a stdout logger at `level`. Later calls keep the first logger.
 pub fn init(level: Level) -> Result<(), SetLoggerError> {
-    unsafe {
-        INIT.call_once(|| {
-            LOGGER = Some(Logger::new(level));
-        });
-
-        if let Some(logger) = &LOGGER {
-            log::set_logger(logger)
-                .map(|()| log::set_max_level(LevelFilter::Trace))
-        } else {
-            Err(SetLoggerError::CannotSetLogger)
-        }
+    install(Logger::new(level))
+}
+
+/// Installs a logger built from `config`. Later calls keep the first logger.
+pub fn init_with_config(config: LoggerConfig) -> Result<(), Box<dyn std::error::Error>> {
+    Ok(install(Logger::with_config(config)?)?)
+}
+
+fn install(logger: Logger) -> Result<(), SetLoggerError> {
+    let mut installed = false;
+    let logger = LOGGER.get_or_init(|| {
+        installed = true;
+        logger
+    });
+    if !installed {
+        return Ok(());
     }
+    log::set_logger(logger).map(|()| log::set_max_level(logger.max_level()))
 }
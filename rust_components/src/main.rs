@@ -1,60 +1,893 @@
-use rust_components::arbitrage_engine::ArbitrageEngine;
-use rust_components::data_processor::DataProcessor;
-use rust_components::order::Order;
+use rust_components::alerts::{AlertDispatcher, Severity, StdoutSink, TelegramSink, WebhookSink};
+use rust_components::bot_admin::{AdminAction, BotAdmin};
+use rust_components::circuit_breaker::{load_tripped_state, CircuitBreaker};
+use rust_components::config::{ChainConfig, Config, ConfigWatcher};
+use rust_components::control_api::{self, ControlState};
+use rust_components::flash_loan::AavePool;
+use rust_components::inventory::{self, Inventory, Location, NATIVE_CURRENCY};
+use rust_components::logger::{self, FileSink, LevelFilters, LogFormat, LoggerConfig};
+use rust_components::opportunity_tracker::OpportunityTracker;
+use rust_components::oracle::OracleGuard;
+use rust_components::paper_executor::PaperTradeExecutor;
+use rust_components::provider_pool::{PooledProvider, ProviderPool};
+use rust_components::scanner::{self, Candidate, MarketSnapshot, Scanner, SessionReport, SnapshotRecorder};
+use rust_components::signer::{LocalSigner, SignerPool, TransactionSigner};
+use rust_components::spans::{self, SpanExporter};
+use rust_components::trade_executor::{Executor, TradeExecutor};
+use rust_components::trade_journal::TradeJournal;
+use rust_components::transaction_manager::TransactionManagerConfig;
 
-use std::time::Duration;
-use log::{info, error};
-use env_logger;
+use clap::{Args, Parser, Subcommand};
+use ethers::providers::Middleware;
+use futures::future::join_all;
+use ethers::types::{Address, U256};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use log::{info, warn, error};
 
+/// How often a running bot looks for config changes.
+const CONFIG_RELOAD_INTERVAL: Duration = Duration::from_secs(5);
+/// How often RPC endpoints are checked for latency and block lag.
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+/// How long settled opportunities stay queryable in the tracker.
+const TRACKER_RETENTION: chrono::Duration = chrono::Duration::hours(1);
+/// How often the trading wallet's tracked balances are checked against the chain.
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Parser)]
+#[command(name = "rust_components", about = "Arbitrage bot")]
+struct Cli {
+    /// TOML config file; ARB__SECTION__KEY environment variables override it
+    #[arg(long, env = "ARB_CONFIG", default_value = "config.toml", global = true)]
+    config: PathBuf,
+    /// Chains from the config to use, e.g. mainnet,arbitrum; run and paper
+    /// default to all of them, the other commands take one and default to the first
+    #[arg(long, env = "ARB_CHAIN", value_delimiter = ',', global = true)]
+    chain: Vec<String>,
+    /// Level filters, e.g. "info,rust_components::trade_executor=debug"
+    #[arg(long, env = "RUST_LOG", default_value = "info", global = true)]
+    log_level: String,
+    /// text or json
+    #[arg(long, env = "LOG_FORMAT", default_value = "text", global = true)]
+    log_format: LogFormat,
+    /// Also write logs to this file, rotated by size and age
+    #[arg(long, env = "LOG_FILE", global = true)]
+    log_file: Option<PathBuf>,
+    /// Write trade-critical events to this file regardless of level filters
+    #[arg(long, env = "AUDIT_LOG", global = true)]
+    audit_log: Option<PathBuf>,
+    /// Export per-stage timing spans as JSON lines to this file
+    #[arg(long, env = "SPAN_FILE", global = true)]
+    span_file: Option<PathBuf>,
+    /// Serve Prometheus metrics on this address, e.g. 127.0.0.1:9898
+    #[cfg(feature = "metrics")]
+    #[arg(long, env = "METRICS_ADDR", global = true)]
+    metrics_addr: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Trade live: scan each chain's pools every poll interval and send the best round trip
+    Run(RunArgs),
+    /// Like run, but fill trades in a local ledger instead of sending them
+    Paper(LiveArgs),
+    /// Feed recorded snapshots through detection and paper execution, printing each step
+    Replay(ReplayArgs),
+    /// Paper trade recorded snapshots at one or more minimum profits and print the totals
+    Backtest(BacktestArgs),
+    /// Print the current opportunities and exit
+    ScanOnce {
+        #[arg(long)]
+        json: bool,
+    },
+    /// Validate the config and exit
+    CheckConfig,
+    /// Quote selling SIZE base tokens of PAIR (e.g. WETH/USDC) on every pool
+    Quote {
+        pair: String,
+        size: String,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Owner operations on the deployed ArbitrageBot contract
+    Admin(AdminArgs),
+    /// Inspect or clear a tripped circuit breaker
+    Breaker(BreakerArgs),
+}
+
+#[derive(Args)]
+struct LiveArgs {
+    /// Append every snapshot to this file for replay and backtest
+    #[arg(long)]
+    record: Option<PathBuf>,
+    /// SQLite trade journal
+    #[arg(long, env = "TRADE_JOURNAL")]
+    journal: Option<PathBuf>,
+    /// Serve the control API on this address; the bearer token is read from CONTROL_API_TOKEN
+    #[arg(long, env = "CONTROL_ADDR")]
+    control_addr: Option<String>,
+    /// Post alerts as JSON to this URL
+    #[arg(long, env = "ALERT_WEBHOOK")]
+    alert_webhook: Option<String>,
+    /// Send alerts to this Telegram chat; the bot token is read from TELEGRAM_BOT_TOKEN
+    #[arg(long, env = "TELEGRAM_CHAT_ID")]
+    telegram_chat_id: Option<String>,
+}
+
+#[derive(Args)]
+struct RunArgs {
+    #[command(flatten)]
+    live: LiveArgs,
+    /// Environment variable holding the trading key; without it the node signs
+    #[arg(long)]
+    key_env: Option<String>,
+    /// Route trades through this deployed ArbitrageBot instead of the chain's
+    /// arbitrage_bot; only with a single chain
+    #[arg(long, env = "ARBITRAGE_BOT_ADDRESS")]
+    bot: Option<Address>,
+    /// Alert on wins of at least this much, in units of the chain's quote token
+    #[arg(long, default_value_t = 100.0)]
+    large_win: f64,
+}
+
+#[derive(Args)]
+struct ReplayArgs {
+    /// Snapshots recorded with --record
+    file: PathBuf,
+    /// Wait out the recorded gaps between snapshots
+    #[arg(long)]
+    realtime: bool,
+    #[arg(long, default_value_t = 30)]
+    gas_price_gwei: u64,
+}
+
+#[derive(Args)]
+struct BacktestArgs {
+    /// Snapshots recorded with --record
+    #[arg(long)]
+    data: PathBuf,
+    /// Minimum profits to compare, e.g. 0,0.5,1; defaults to the config's
+    #[arg(long, value_delimiter = ',')]
+    min_profit: Vec<f64>,
+    #[arg(long, default_value_t = 30)]
+    gas_price_gwei: u64,
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args)]
+struct BreakerArgs {
+    /// Circuit breaker state file of one chain of the running bot, e.g.
+    /// breaker-mainnet.json for risk.state_path = "breaker.json"
+    #[arg(long)]
+    state_path: PathBuf,
+    #[command(subcommand)]
+    command: BreakerCommand,
+}
+
+#[derive(Subcommand)]
+enum BreakerCommand {
+    /// Print why the breaker tripped, if it has
+    Status,
+    /// Resume trading by clearing the trip
+    Reset {
+        /// Skip the confirmation prompt
+        #[arg(long, short)]
+        yes: bool,
+    },
+}
+
+#[derive(Args)]
+struct AdminArgs {
+    #[arg(long, env = "RPC_URL", default_value = "http://127.0.0.1:8545")]
+    rpc_url: String,
+    /// Address of the deployed ArbitrageBot
+    #[arg(long, env = "ARBITRAGE_BOT_ADDRESS")]
+    bot: Address,
+    /// Environment variable holding the owner's private key; without it the node signs
+    #[arg(long)]
+    key_env: Option<String>,
+    /// Simulate the call and exit without sending
+    #[arg(long)]
+    dry_run: bool,
+    /// Skip the confirmation prompt
+    #[arg(long, short)]
+    yes: bool,
+    #[command(subcommand)]
+    command: AdminCommand,
+}
+
+#[derive(Subcommand)]
+enum AdminCommand {
+    /// Call addSupportedToken
+    AddToken { token: Address },
+    /// Call removeSupportedToken
+    RemoveToken { token: Address },
+    /// Call addPriceFeed
+    AddPriceFeed { token: Address, price_feed: Address },
+    /// Call withdrawToken; the amount is in the token's smallest unit
+    Withdraw {
+        token: Address,
+        #[arg(value_parser = parse_amount)]
+        amount: U256,
+    },
+    /// Print the owner and the supportedTokens/priceFeeds entries for a token
+    Show { token: Address },
+}
+
+fn parse_amount(value: &str) -> Result<U256, String> {
+    U256::from_dec_str(value).map_err(|e| format!("invalid amount {}: {}", value, e))
+}
+
+fn confirm(prompt: &str) -> io::Result<bool> {
+    print!("{} [y/N] ", prompt);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+async fn run_admin(args: AdminArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut admin = BotAdmin::new(&args.rpc_url, args.bot)?;
+
+    let action = match args.command {
+        AdminCommand::Show { token } => {
+            println!("owner: {:?}", admin.owner().await?);
+            println!("{}", admin.token_state(token).await?);
+            return Ok(());
+        }
+        AdminCommand::AddToken { token } => AdminAction::AddSupportedToken { token },
+        AdminCommand::RemoveToken { token } => AdminAction::RemoveSupportedToken { token },
+        AdminCommand::AddPriceFeed { token, price_feed } => AdminAction::AddPriceFeed { token, price_feed },
+        AdminCommand::Withdraw { token, amount } => AdminAction::WithdrawToken { token, amount },
+    };
+
+    let from = match &args.key_env {
+        Some(var) => {
+            let chain_id = admin.provider().get_chainid().await?.as_u64();
+            let signer = LocalSigner::from_env(var, chain_id)?;
+            let from = signer.address();
+            let mut signers = SignerPool::new();
+            signers.add(Arc::new(signer));
+            admin.set_signers(Arc::new(signers));
+            from
+        }
+        None => admin.owner().await?,
+    };
+
+    println!("before: {}", admin.token_state(action.token()).await?);
+    admin.simulate(&action, from).await?;
+    println!("Simulation of {} from {:?} succeeded", action, from);
+    if args.dry_run {
+        return Ok(());
+    }
+    if !args.yes && !confirm(&format!("Send {} to {:?}?", action, args.bot))? {
+        println!("Aborted");
+        return Ok(());
+    }
+
+    let receipt = admin.execute(&action, from).await?;
+    println!("Confirmed in transaction {:?}", receipt.transaction_hash);
+    println!("after: {}", admin.token_state(action.token()).await?);
+    Ok(())
+}
+
+fn run_breaker(args: BreakerArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(tripped) = load_tripped_state(&args.state_path) else {
+        println!("Circuit breaker is not tripped");
+        return Ok(());
+    };
+    println!("Circuit breaker tripped at {}: {}", tripped.tripped_at, tripped.reason);
+
+    if let BreakerCommand::Reset { yes } = args.command {
+        if !yes && !confirm("Resume trading?")? {
+            println!("Aborted");
+            return Ok(());
+        }
+        std::fs::remove_file(&args.state_path)?;
+        println!("Circuit breaker reset; the bot resumes on its next check");
+    }
+    Ok(())
+}
+
+fn init_logging(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let config = LoggerConfig {
+        filters: LevelFilters::parse(&cli.log_level)?,
+        format: cli.log_format,
+        file: cli.log_file.clone().map(FileSink::new),
+        audit_file: cli.audit_log.clone().map(FileSink::new),
+        ..Default::default()
+    };
+    logger::init_with_config(config)?;
+    if let Some(span_file) = &cli.span_file {
+        spans::init_exporter(SpanExporter::to_file(span_file)?)?;
+    }
+    Ok(())
+}
+
+fn find_chain(config: &Config, name: &str) -> Result<ChainConfig, Box<dyn std::error::Error>> {
+    Ok(config.chain(name).ok_or_else(|| format!("Chain {} is not in the config", name))?.clone())
+}
+
+fn select_chain(config: &Config, names: &[String]) -> Result<ChainConfig, Box<dyn std::error::Error>> {
+    match names {
+        [] => Ok(config.chains.first().ok_or("The config defines no chains")?.clone()),
+        [name] => find_chain(config, name),
+        _ => Err("This command takes a single --chain".into()),
+    }
+}
+
+/// The named chains, or every configured one.
+fn select_chains(config: &Config, names: &[String]) -> Result<Vec<ChainConfig>, Box<dyn std::error::Error>> {
+    if names.is_empty() {
+        if config.chains.is_empty() {
+            return Err("The config defines no chains".into());
+        }
+        return Ok(config.chains.clone());
+    }
+    names.iter().map(|name| find_chain(config, name)).collect()
+}
+
+/// Pools every rpc url of `chain` under its rate limit, checking their health
+/// in the background.
+fn connect(chain: &ChainConfig) -> Result<Arc<PooledProvider>, Box<dyn std::error::Error>> {
+    let pool = ProviderPool::new(&chain.rpc_urls)?;
+    if let Some(rate) = chain.rpc_requests_per_sec {
+        pool.set_rate_limit(rate, rate.ceil() as u32);
+    }
+    if chain.rpc_urls.len() > 1 {
+        pool.spawn_health_checks(HEALTH_CHECK_INTERVAL);
+    }
+    let mut provider = pool.into_provider();
+    if let Some(block_time_ms) = chain.block_time_ms {
+        provider = provider.interval(Duration::from_millis(block_time_ms));
+    }
+    Ok(Arc::new(provider))
+}
+
+fn gwei(gwei: u64) -> U256 {
+    U256::from(gwei) * U256::exp10(9)
+}
+
+fn describe(candidate: &Candidate, chain: &ChainConfig) -> String {
+    let opportunity = &candidate.opportunity;
+    let pair = opportunity.pair.as_deref().unwrap_or_default();
+    let decimals = chain.pair_tokens(pair).map_or(18, |(_, quote)| quote.decimals);
+    let clamp = candidate.position.clamped_by.as_ref().map(|reason| format!(" (clamped by {:?})", reason)).unwrap_or_default();
+    format!(
+        "{} buy on {} sell on {}: size {:.6}, profit {:.6}{}",
+        pair,
+        opportunity.buy_dex,
+        opportunity.sell_dex,
+        scanner::to_units(candidate.position.size, decimals),
+        opportunity.profit,
+        clamp
+    )
+}
+
+fn build_alerts(args: &LiveArgs) -> Option<Arc<AlertDispatcher>> {
+    if args.alert_webhook.is_none() && args.telegram_chat_id.is_none() {
+        return None;
+    }
+    let mut alerts = AlertDispatcher::new();
+    alerts.add_sink(Box::new(StdoutSink), Severity::Warning);
+    if let Some(url) = &args.alert_webhook {
+        alerts.add_sink(Box::new(WebhookSink::new(url)), Severity::Info);
+    }
+    if let Some(chat_id) = &args.telegram_chat_id {
+        match std::env::var("TELEGRAM_BOT_TOKEN") {
+            Ok(token) => alerts.add_sink(Box::new(TelegramSink::new(&token, chat_id)), Severity::Warning),
+            Err(_) => warn!("--telegram-chat-id is set but TELEGRAM_BOT_TOKEN is not; Telegram alerts are off"),
+        }
+    }
+    Some(Arc::new(alerts))
+}
+
+/// Guard over the Chainlink feeds of `chain`'s tokens, if any has one.
+fn oracle_guard(config: &Config, chain: &ChainConfig, provider: Arc<PooledProvider>) -> Option<OracleGuard> {
+    let feeds: Vec<_> = chain.tokens.iter().filter_map(|token| Some((token.address, token.price_feed?))).collect();
+    if feeds.is_empty() {
+        return None;
+    }
+    let mut guard = OracleGuard::with_provider(provider, config.risk.max_oracle_age_secs, config.risk.max_oracle_deviation_bps);
+    for (token, feed) in feeds {
+        guard.add_feed(token, feed);
+    }
+    Some(guard)
+}
+
+/// Pieces of `run` and `paper` shared by every chain.
+struct LiveSession {
+    control: Arc<ControlState>,
+    tracker: Arc<OpportunityTracker>,
+    recorder: Option<SnapshotRecorder>,
+    journal: Option<Arc<TradeJournal>>,
+}
+
+impl LiveSession {
+    fn scanner(&self, config: &Config, chain: ChainConfig) -> Scanner {
+        let (risk_manager, min_profit) = (config.risk.risk_manager(&chain), config.thresholds.min_profit_for(&chain.name));
+        let mut scanner = Scanner::new(chain, risk_manager, min_profit);
+        scanner.set_tracker(self.tracker.clone());
+        scanner.set_control(self.control.clone());
+        scanner
+    }
+}
+
+fn start_session(config_path: &Path, config: &Config, args: &LiveArgs, circuit_breakers: &[(String, Arc<CircuitBreaker>)]) -> Result<LiveSession, Box<dyn std::error::Error>> {
+    let mut control = ControlState::new();
+    for (chain, circuit_breaker) in circuit_breakers {
+        control.add_circuit_breaker(chain, circuit_breaker.clone());
+    }
+    let control = Arc::new(control);
+    config.apply_to_control(&control);
+    if let Some(addr) = &args.control_addr {
+        let token = std::env::var("CONTROL_API_TOKEN").map_err(|_| "--control-addr needs CONTROL_API_TOKEN")?;
+        logger::register_secret(&token);
+        control_api::serve(addr, &token, control.clone()).map_err(|e| format!("Failed to serve control API on {}: {}", addr, e))?;
+    }
+    if config_path.exists() {
+        let control = control.clone();
+        ConfigWatcher::new(config_path, config.clone()).watch(CONFIG_RELOAD_INTERVAL, move |config| config.apply_to_control(&control));
+    }
+
+    let journal = match &args.journal {
+        Some(path) => Some(Arc::new(TradeJournal::open(path)?)),
+        None => None,
+    };
+    let mut tracker = OpportunityTracker::new();
+    if let Some(journal) = &journal {
+        tracker.set_journal(journal.clone());
+    }
+
+    let recorder = match &args.record {
+        Some(path) => Some(SnapshotRecorder::open(path)?),
+        None => None,
+    };
+    Ok(LiveSession { control, tracker: Arc::new(tracker), recorder, journal })
+}
+
+/// Re-reads the pools with the chain's `read_quorum`, if set, and re-sizes
+/// `best` against them.
+async fn confirm_with_quorum(scanner: &Scanner, provider: Arc<PooledProvider>, best: &Candidate) -> Option<Candidate> {
+    let chain = scanner.chain();
+    let Some(quorum) = chain.read_quorum else {
+        return Some(best.clone());
+    };
+    match scanner::fetch_snapshot_quorum(provider, chain, quorum).await {
+        Ok(snapshot) => {
+            let confirmed = scanner.confirm(best, &snapshot);
+            if confirmed.is_none() {
+                info!("{} is gone at quorum block {:?}", describe(best, chain), snapshot.block_number);
+            }
+            confirmed
+        }
+        Err(e) => {
+            warn!("Not trading {} without quorum: {}", describe(best, chain), e);
+            None
+        }
+    }
+}
+
+/// Scans `scanner`'s chain every poll interval and executes the best candidate
+/// until `run_secs` pass or Ctrl-C is pressed.
+async fn live_loop(
+    session: &LiveSession,
+    scanner: &Scanner,
+    config: &Config,
+    provider: Arc<PooledProvider>,
+    executor: &dyn Executor,
+    paper: Option<&PaperTradeExecutor>,
+    account: Address,
+) -> SessionReport {
+    let mut report = SessionReport::new(scanner.min_profit());
+    let started = Instant::now();
+    let chain = scanner.chain();
+    let interval = chain.poll_interval(&config.bot);
+
+    loop {
+        match scanner::fetch_snapshot(provider.clone(), chain).await {
+            Ok(snapshot) => {
+                if let Some(recorder) = &session.recorder {
+                    if let Err(e) = recorder.record(&snapshot) {
+                        warn!("Failed to record snapshot: {}", e);
+                    }
+                }
+                let candidates = scanner.scan(&snapshot);
+                report.record_snapshot(&candidates);
+                let best = match candidates.first() {
+                    Some(_) if session.control.is_paused() => {
+                        info!("Execution paused by operator, not trading");
+                        None
+                    }
+                    Some(best) => confirm_with_quorum(scanner, provider.clone(), best).await,
+                    None => None,
+                };
+                if let Some(best) = best {
+                    if let Some(paper) = paper {
+                        match provider.get_gas_price().await {
+                            Ok(gas_price) => paper.set_gas_price(gas_price),
+                            Err(e) => warn!("Keeping the previous paper gas price: {}", e),
+                        }
+                        best.set_paper_reserves(paper);
+                    }
+                    match scanner.execute(executor, &best, account).await {
+                        Ok(receipt) => info!("Executed {} in {:?}", describe(&best, chain), receipt.transaction_hash),
+                        Err(e) => warn!("Did not execute {}: {}", describe(&best, chain), e),
+                    }
+                }
+            }
+            Err(e) => error!("Failed to read pools on {}: {}", chain.name, e),
+        }
+        session.tracker.prune(chrono::Utc::now() - TRACKER_RETENTION);
+
+        if config.bot.run_secs.is_some_and(|run_secs| started.elapsed() >= Duration::from_secs(run_secs)) {
+            break;
+        }
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {
+                info!("Interrupted, stopping");
+                break;
+            }
+            _ = tokio::time::sleep(interval) => {}
+        }
+    }
+    report
+}
+
+/// A chain's scanner and the executor trading on it.
+struct ChainTrader {
+    scanner: Scanner,
+    provider: Arc<PooledProvider>,
+    executor: TradeExecutor,
+    account: Address,
+}
+
+async fn chain_trader(
+    session: &LiveSession,
+    config: &Config,
+    chain: ChainConfig,
+    args: &RunArgs,
+    circuit_breaker: Arc<CircuitBreaker>,
+    alerts: Option<Arc<AlertDispatcher>>,
+) -> Result<ChainTrader, Box<dyn std::error::Error>> {
+    let router = chain.uniswap_router.ok_or_else(|| format!("Chain {} needs uniswap_router to trade", chain.name))?;
+    let flash_loan_provider = chain.flash_loan_provider.ok_or_else(|| format!("Chain {} needs flash_loan_provider to trade", chain.name))?;
+    let native_token = chain.wrapped_native().ok_or_else(|| format!("Chain {} needs wrapped_native to trade", chain.name))?;
+    let provider = connect(&chain)?;
+    let chain_id = provider.get_chainid().await?.as_u64();
+    if chain_id != chain.chain_id {
+        return Err(format!("RPC for {} reports chain id {}, the config says {}", chain.name, chain_id, chain.chain_id).into());
+    }
+
+    let mut executor = TradeExecutor::with_provider(provider.clone(), router, flash_loan_provider);
+    executor.set_native_token(native_token);
+    if let Some(quote) = chain.quote_token() {
+        executor.set_quote_token(quote.address);
+    }
+    for token in &chain.tokens {
+        executor.set_token_decimals(token.address, token.decimals);
+    }
+    let mut transaction_config = TransactionManagerConfig {
+        persistence_path: config.bot.state_dir.as_ref().map(|dir| dir.join(format!("{}-pending.json", chain.name))),
+        ..Default::default()
+    };
+    if let Some(block_time_ms) = chain.block_time_ms {
+        transaction_config.poll_interval = Duration::from_millis(block_time_ms);
+    }
+    executor.set_transaction_manager_config(transaction_config)?;
+    let account = match &args.key_env {
+        Some(var) => {
+            let signer = LocalSigner::from_env(var, chain_id)?;
+            let account = signer.address();
+            let mut signers = SignerPool::new();
+            signers.add(Arc::new(signer));
+            executor.set_signers(Arc::new(signers));
+            account
+        }
+        None => *provider.get_accounts().await?.first().ok_or_else(|| format!("The node for {} has no accounts; pass --key-env", chain.name))?,
+    };
+    // Buying on one DEX and selling on another takes the bot's routed swap
+    let bot = args.bot.or(chain.arbitrage_bot).ok_or_else(|| format!("Chain {} needs arbitrage_bot to trade across DEXes", chain.name))?;
+    executor.set_arbitrage_bot(bot);
+    executor.set_circuit_breaker(circuit_breaker.clone());
+    executor.set_control(session.control.clone());
+    if let Some(journal) = &session.journal {
+        executor.set_journal(journal.clone());
+    }
+    if let Some(alerts) = alerts {
+        executor.set_alerts(alerts, args.large_win);
+    }
+
+    executor.resume_pending().await;
+
+    // Balances are read after restored transactions settle so they count once
+    let mut wallet = Inventory::new(native_token);
+    wallet.track_wallet(account);
+    let mut tokens = vec![NATIVE_CURRENCY];
+    for token in &chain.tokens {
+        wallet.set_decimals(token.address, token.decimals);
+        tokens.push(token.address);
+    }
+    let balances = inventory::fetch_wallet_balances(provider.clone(), account, &tokens).await?;
+    wallet.reconcile_snapshot(&Location::Wallet(account), &balances);
+    let wallet = Arc::new(Mutex::new(wallet));
+    tokio::spawn(inventory::run_reconciliation(wallet.clone(), provider.clone(), tokens, RECONCILE_INTERVAL));
+    executor.set_inventory(wallet.clone());
+    session.control.add_inventory(&chain.name, wallet.clone());
+
+    info!("Trading live on {} from {:?}", chain.name, account);
+    let mut scanner = session.scanner(config, chain);
+    scanner.set_inventory(wallet, Location::Wallet(account));
+    scanner.set_circuit_breaker(circuit_breaker);
+    if let Some(guard) = oracle_guard(config, scanner.chain(), provider.clone()) {
+        scanner.set_oracle_guard(guard);
+    }
+    Ok(ChainTrader { scanner, provider, executor, account })
+}
+
+/// A breaker per chain, since losses and gas are in each chain's own
+/// currencies, each tripped by SIGUSR1. Unless `persist`, trips end with the
+/// process and never halt a live bot sharing the state file.
+fn circuit_breakers(config: &Config, chains: &[ChainConfig], alerts: Option<&Arc<AlertDispatcher>>, persist: bool) -> Vec<(String, Arc<CircuitBreaker>)> {
+    let mut circuit_breakers = Vec::new();
+    for chain in chains {
+        let mut breaker_config = config.risk.circuit_breaker_config(chain);
+        if !persist {
+            breaker_config.state_path = None;
+        }
+        let mut circuit_breaker = CircuitBreaker::new(breaker_config);
+        if let Some(alerts) = alerts {
+            circuit_breaker.set_alert_handler(alerts.breaker_alert_handler(&chain.name));
+        }
+        let circuit_breaker = Arc::new(circuit_breaker);
+        #[cfg(unix)]
+        {
+            let circuit_breaker = circuit_breaker.clone();
+            tokio::spawn(async move {
+                if let Err(e) = rust_components::circuit_breaker::watch_kill_signal(circuit_breaker).await {
+                    warn!("Cannot watch for SIGUSR1, the kill signal is disabled: {}", e);
+                }
+            });
+        }
+        circuit_breakers.push((chain.name.clone(), circuit_breaker));
+    }
+    circuit_breakers
+}
+
+async fn run_live(config_path: &Path, config: Config, chains: Vec<ChainConfig>, args: RunArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if args.bot.is_some() && chains.len() > 1 {
+        return Err("--bot needs a single --chain; set arbitrage_bot per chain instead".into());
+    }
+    let alerts = build_alerts(&args.live);
+    let circuit_breakers = circuit_breakers(&config, &chains, alerts.as_ref(), true);
+    let session = start_session(config_path, &config, &args.live, &circuit_breakers)?;
+
+    let mut traders = Vec::new();
+    for (chain, (_, circuit_breaker)) in chains.into_iter().zip(&circuit_breakers) {
+        traders.push(chain_trader(&session, &config, chain, &args, circuit_breaker.clone(), alerts.clone()).await?);
+    }
+
+    info!("Trading live on {} chain(s): {}", traders.len(), config);
+    let reports = join_all(traders.iter().map(|trader| live_loop(&session, &trader.scanner, &config, trader.provider.clone(), &trader.executor, None, trader.account))).await;
+    for (trader, report) in traders.iter().zip(reports) {
+        println!("{}: {} snapshot(s), {} opportunity(ies)", trader.scanner.chain().name, report.snapshots, report.opportunities);
+    }
+    Ok(())
+}
+
+async fn run_paper(config_path: &Path, config: Config, chains: Vec<ChainConfig>, args: LiveArgs) -> Result<(), Box<dyn std::error::Error>> {
+    // POST /kill and SIGUSR1 halt paper trading like live trading
+    let circuit_breakers = circuit_breakers(&config, &chains, build_alerts(&args).as_ref(), false);
+    let session = start_session(config_path, &config, &args, &circuit_breakers)?;
+    let mut traders = Vec::new();
+    for (chain, (_, circuit_breaker)) in chains.into_iter().zip(&circuit_breakers) {
+        let provider = connect(&chain)?;
+        let mut paper = PaperTradeExecutor::new(provider.get_gas_price().await?);
+        if let Some(native_token) = chain.wrapped_native() {
+            paper.set_native_token(native_token);
+        }
+        if let Some(pool) = chain.flash_loan_provider {
+            match AavePool::new(pool, provider.clone()).flashloan_premium_total().call().await {
+                Ok(premium_bps) => paper.set_flash_loan_premium_bps(U256::from(premium_bps)),
+                Err(e) => warn!("Could not read the flash loan premium of {:?}, assuming Aave V3's: {}", pool, e),
+            }
+        }
+        let mut scanner = session.scanner(&config, chain);
+        scanner.set_circuit_breaker(circuit_breaker.clone());
+        if let Some(guard) = oracle_guard(&config, scanner.chain(), provider.clone()) {
+            scanner.set_oracle_guard(guard);
+        }
+        traders.push((scanner, provider, paper));
+    }
+
+    info!("Paper trading on {} chain(s): {}", traders.len(), config);
+    let reports = join_all(traders.iter().map(|(scanner, provider, paper)| live_loop(&session, scanner, &config, provider.clone(), paper, Some(paper), Address::zero()))).await;
+    for ((scanner, _, paper), mut report) in traders.iter().zip(reports) {
+        report.record_ledger(&paper.ledger(), scanner.chain());
+        println!("{}: {}", scanner.chain().name, report);
+    }
+    Ok(())
+}
+
+/// Recorded snapshots of `chain`; a recording may cover several chains.
+fn read_chain_snapshots(path: &Path, chain: &ChainConfig) -> Result<Vec<MarketSnapshot>, Box<dyn std::error::Error>> {
+    let mut snapshots = scanner::read_snapshots(path)?;
+    snapshots.retain(|snapshot| snapshot.chain == chain.name);
+    if snapshots.is_empty() {
+        return Err(format!("{} has no snapshots of {}", path.display(), chain.name).into());
+    }
+    Ok(snapshots)
+}
+
+/// Paper trades `snapshots` in order; `on_step` sees each snapshot's candidates.
+async fn paper_trade<F>(scanner: &Scanner, snapshots: &[MarketSnapshot], gas_price: U256, realtime: bool, mut on_step: F) -> SessionReport
+where
+    F: FnMut(&MarketSnapshot, &[Candidate]),
+{
+    let mut paper = PaperTradeExecutor::new(gas_price);
+    if let Some(native_token) = scanner.chain().wrapped_native() {
+        paper.set_native_token(native_token);
+    }
+    let mut report = SessionReport::new(scanner.min_profit());
+    let mut previous: Option<&MarketSnapshot> = None;
+    for snapshot in snapshots {
+        if let (true, Some(previous)) = (realtime, previous) {
+            if let Ok(gap) = (snapshot.at - previous.at).to_std() {
+                tokio::time::sleep(gap).await;
+            }
+        }
+        previous = Some(snapshot);
+
+        let candidates = scanner.scan(snapshot);
+        report.record_snapshot(&candidates);
+        on_step(snapshot, &candidates);
+        if let Some(best) = candidates.first() {
+            best.set_paper_reserves(&paper);
+            if let Err(e) = scanner.execute(&paper, best, Address::zero()).await {
+                warn!("Paper trade failed: {}", e);
+            }
+        }
+    }
+    report.record_ledger(&paper.ledger(), scanner.chain());
+    report
+}
+
+async fn run_replay(config: Config, chain: ChainConfig, args: ReplayArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let snapshots = read_chain_snapshots(&args.file, &chain)?;
+    let scanner = Scanner::new(chain.clone(), config.risk.risk_manager(&chain), config.thresholds.min_profit_for(&chain.name));
+
+    let report = paper_trade(&scanner, &snapshots, gwei(args.gas_price_gwei), args.realtime, |snapshot, candidates| {
+        let block = snapshot.block_number.map(|block| block.to_string()).unwrap_or_else(|| "-".to_string());
+        println!("{} block {}: {} opportunity(ies)", snapshot.at, block, candidates.len());
+        for candidate in candidates {
+            println!("  {}", describe(candidate, &chain));
+        }
+    })
+    .await;
+    println!("{}", report);
+    Ok(())
+}
+
+async fn run_backtest(config: Config, chain: ChainConfig, args: BacktestArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let snapshots = read_chain_snapshots(&args.data, &chain)?;
+    let min_profits = if args.min_profit.is_empty() { vec![config.thresholds.min_profit_for(&chain.name)] } else { args.min_profit };
+
+    let mut reports = Vec::new();
+    for min_profit in min_profits {
+        let scanner = Scanner::new(chain.clone(), config.risk.risk_manager(&chain), min_profit);
+        reports.push(paper_trade(&scanner, &snapshots, gwei(args.gas_price_gwei), false, |_, _| {}).await);
+    }
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        for report in reports {
+            println!("{}", report);
+        }
+    }
+    Ok(())
+}
+
+async fn run_scan_once(config: Config, chain: ChainConfig, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let snapshot = scanner::fetch_snapshot(connect(&chain)?, &chain).await?;
+    let scanner = Scanner::new(chain.clone(), config.risk.risk_manager(&chain), config.thresholds.min_profit_for(&chain.name));
+    let candidates = scanner.scan(&snapshot);
+
+    if json {
+        let opportunities: Vec<_> = candidates.iter().map(|candidate| &candidate.opportunity).collect();
+        println!("{}", serde_json::to_string_pretty(&opportunities)?);
+    } else if candidates.is_empty() {
+        println!("No opportunities on {} at block {}", chain.name, snapshot.block_number.unwrap_or_default());
+    } else {
+        for candidate in &candidates {
+            println!("{}", describe(candidate, &chain));
+        }
+    }
+    Ok(())
+}
+
+async fn run_quote(config: Config, chain: ChainConfig, pair: &str, size: &str, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let (base, quote) = chain.pair_tokens(pair).ok_or_else(|| format!("Pair {} is not configured on {}", pair, chain.name))?;
+    let (base_symbol, quote_symbol) = (base.symbol.clone(), quote.symbol.clone());
+    let amount_in: U256 = ethers::utils::parse_units(size, base.decimals as u32)?.into();
+
+    let snapshot = scanner::fetch_snapshot(connect(&chain)?, &chain).await?;
+    let scanner = Scanner::new(chain.clone(), config.risk.risk_manager(&chain), config.thresholds.min_profit_for(&chain.name));
+    let quotes = scanner.quote(&snapshot, pair, amount_in)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&quotes)?);
+        return Ok(());
+    }
+    for quote in quotes {
+        println!(
+            "{}: {} {} -> {:.6} {} at {:.6}, slippage {:.1} bps",
+            quote.dex, size, base_symbol, quote.amount_out, quote_symbol, quote.price, quote.slippage_bps
+        );
+    }
+    Ok(())
+}
+
+fn check_config(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::load(path)?;
+    println!("{} is valid: {}", path.display(), config);
+    for chain in &config.chains {
+        println!("  {} (chain id {}): {} rpc url(s), {} token(s), {} pool(s)", chain.name, chain.chain_id, chain.rpc_urls.len(), chain.tokens.len(), chain.pools.len());
+        if let Some(native) = chain.wrapped_native() {
+            println!("    wrapped native {:?}, polled every {:?}", native, chain.poll_interval(&config.bot));
+        }
+    }
+    Ok(())
+}
+
+async fn run_command(command: Command, config_path: &Path, chains: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let config = match command {
+        Command::Admin(args) => return run_admin(args).await,
+        _ => Config::load(config_path)?,
+    };
+    let chains = match command {
+        Command::Run(_) | Command::Paper(_) => select_chains(&config, chains)?,
+        _ => vec![select_chain(&config, chains)?],
+    };
+    let chain = chains[0].clone();
+    match command {
+        Command::Run(args) => run_live(config_path, config, chains, args).await,
+        Command::Paper(args) => run_paper(config_path, config, chains, args).await,
+        Command::Replay(args) => run_replay(config, chain, args).await,
+        Command::Backtest(args) => run_backtest(config, chain, args).await,
+        Command::ScanOnce { json } => run_scan_once(config, chain, json).await,
+        Command::Quote { pair, size, json } => run_quote(config, chain, &pair, &size, json).await,
+        Command::CheckConfig | Command::Admin(_) | Command::Breaker(_) => unreachable!("Handled without a runtime"),
+    }
+}
 
 fn main() {
-    env_logger::init();
-
-    info!("Starting the arbitrage bot");
-
-    let mut data_processor = DataProcessor::new();
-    let arbitrage_engine = ArbitrageEngine::new();
-
-    // Simulate order book updates
-    let exchange1_bids = vec![
-        Order::new(100.0, 1.0).expect("Failed to create order"),
-        Order::new(99.0, 2.0).expect("Failed to create order")
-    ];
-    let exchange1_asks = vec![
-        Order::new(101.0, 1.0).expect("Failed to create order"),
-        Order::new(102.0, 2.0).expect("Failed to create order")
-    ];
-    let exchange2_bids = vec![
-        Order::new(99.5, 1.0).expect("Failed to create order"),
-        Order::new(98.5, 2.0).expect("Failed to create order")
-    ];
-    let exchange2_asks = vec![
-        Order::new(100.5, 1.0).expect("Failed to create order"),
-        Order::new(101.5, 2.0).expect("Failed to create order")
-    ];
-
-    info!("Processing order book updates");
-    data_processor.process_order_book_update("Exchange1", exchange1_bids.clone(), true);
-    data_processor.process_order_book_update("Exchange1", exchange1_asks.clone(), false);
-    data_processor.process_order_book_update("Exchange2", exchange2_bids.clone(), true);
-    data_processor.process_order_book_update("Exchange2", exchange2_asks.clone(), false);
-
-    // Run arbitrage check
-    info!("Checking for arbitrage opportunities");
-    match arbitrage_engine.calculate_arbitrage(
-        &exchange1_bids,
-        &exchange1_asks,
-        &exchange2_bids,
-        &exchange2_asks
-    ) {
-        Ok(Some(profit)) => info!("Arbitrage opportunity found! Potential profit: {}", profit),
-        Ok(None) => info!("No arbitrage opportunity at this time."),
-        Err(e) => error!("Error checking arbitrage: {}", e),
-    }
-
-    // Simulate continuous operation
-    let duration = Duration::from_secs(10);
-    info!("Running arbitrage bot for {} seconds", duration.as_secs());
-    std::thread::sleep(duration);
-    info!("Arbitrage bot finished running");
+    let cli = Cli::parse();
+    if let Err(e) = init_logging(&cli) {
+        eprintln!("Failed to initialize logging: {}", e);
+        std::process::exit(1);
+    }
+
+    #[cfg(feature = "metrics")]
+    if let Some(addr) = &cli.metrics_addr {
+        if let Err(e) = rust_components::metrics::serve(addr) {
+            error!("Failed to serve metrics on {}: {}", addr, e);
+        }
+    }
+
+    let result = match cli.command {
+        Command::CheckConfig => check_config(&cli.config),
+        Command::Breaker(args) => run_breaker(args),
+        command => {
+            let runtime = tokio::runtime::Runtime::new().expect("Failed to start tokio runtime");
+            runtime.block_on(run_command(command, &cli.config, &cli.chain))
+        }
+    };
+    if let Err(e) = result {
+        error!("{}", e);
+        std::process::exit(1);
+    }
 }
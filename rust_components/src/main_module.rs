@@ -2,13 +2,21 @@ use std::collections::HashMap;
 use crate::order::Order;
 use crate::data_processor::DataProcessor;
 use crate::arbitrage_engine::ArbitrageEngine;
+use crate::arbitrage_finder::ArbitrageOpportunity;
+use crate::control_api::ControlState;
+use crate::metrics;
+use crate::trade_journal::TradeJournal;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use std::thread;
+use log::{error, info, warn};
 
 pub struct ArbitrageBot {
     data_processor: DataProcessor,
     arbitrage_engine: ArbitrageEngine,
     order_books: HashMap<String, (Vec<Order>, Vec<Order>)>,
+    journal: Option<Arc<TradeJournal>>,
+    control: Option<Arc<ControlState>>,
 }
 
 impl ArbitrageBot {
@@ -17,9 +25,22 @@ impl ArbitrageBot {
             data_processor: DataProcessor::new(),
             arbitrage_engine: ArbitrageEngine::new(),
             order_books: HashMap::new(),
+            journal: None,
+            control: None,
         }
     }
 
+    /// Keeps the opportunities found by `run` in `journal`.
+    pub fn set_journal(&mut self, journal: Arc<TradeJournal>) {
+        self.journal = Some(journal);
+    }
+
+    /// Exposes books and opportunities to the control API and obeys its pause
+    /// and thresholds in `run`.
+    pub fn set_control(&mut self, control: Arc<ControlState>) {
+        self.control = Some(control);
+    }
+
     pub fn process_order_books(
         &mut self,
         exchange1: &str,
@@ -37,6 +58,11 @@ impl ArbitrageBot {
     
 
     pub fn update_order_books(&mut self, exchange: &str, bids: Vec<Order>, asks: Vec<Order>) {
+        if let Some(control) = &self.control {
+            let best_bid = bids.iter().map(|order| order.price).reduce(f64::max);
+            let best_ask = asks.iter().map(|order| order.price).reduce(f64::min);
+            control.record_book(exchange, best_bid, best_ask);
+        }
         self.order_books.insert(exchange.to_string(), (bids.clone(), asks.clone()));
         self.data_processor.process_order_book_update(exchange, bids, true);
         self.data_processor.process_order_book_update(exchange, asks, false);
@@ -80,14 +106,50 @@ impl ArbitrageBot {
         
         let start_time = Instant::now();
         while start_time.elapsed() < duration {
-            match self.check_arbitrage(exchange1, exchange2) {
-                Ok(Some(profit)) => println!("Arbitrage opportunity found! Potential profit: {}", profit),
-                Ok(None) => println!("No arbitrage opportunity at this time."),
-                Err(e) => println!("Error checking arbitrage: {}", e),
+            if self.can_check(exchange1, exchange2) {
+                self.check_and_record(exchange1, exchange2);
             }
             thread::sleep(Duration::from_secs(1));
         }
         
         println!("Arbitrage check completed.");
     }
+
+    fn can_check(&self, exchange1: &str, exchange2: &str) -> bool {
+        let Some(control) = &self.control else {
+            return true;
+        };
+        if control.is_paused() {
+            info!("Execution paused by operator, not checking");
+            return false;
+        }
+        if !control.is_book_fresh(exchange1) || !control.is_book_fresh(exchange2) {
+            warn!("Skipping stale order books for {} and {}", exchange1, exchange2);
+            return false;
+        }
+        true
+    }
+
+    fn check_and_record(&self, exchange1: &str, exchange2: &str) {
+        let min_profit = self.control.as_ref().map_or(0.0, |control| control.thresholds().min_profit);
+        match self.check_arbitrage(exchange1, exchange2) {
+            Ok(Some(profit)) if profit >= min_profit => {
+                println!("Arbitrage opportunity found! Potential profit: {}", profit);
+                metrics::opportunity_found();
+                // `check_arbitrage` buys on the second exchange and sells on the first.
+                let opportunity = ArbitrageOpportunity::new(exchange2, exchange1, profit);
+                if let Some(control) = &self.control {
+                    control.record_opportunity(&opportunity);
+                }
+                if let Some(journal) = &self.journal {
+                    if let Err(e) = journal.record_opportunity(&opportunity) {
+                        error!("Failed to journal opportunity: {}", e);
+                    }
+                }
+            }
+            Ok(Some(profit)) => println!("Ignoring opportunity with profit {} below minimum {}.", profit, min_profit),
+            Ok(None) => println!("No arbitrage opportunity at this time."),
+            Err(e) => println!("Error checking arbitrage: {}", e),
+        }
+    }
 }
@@ -0,0 +1,170 @@
+//! Prometheus metrics, compiled in with the `metrics` feature. Without it every
+//! recording function is a no-op, so call sites need no feature gates.
+
+use ethers::types::U256;
+use std::time::Duration;
+
+#[cfg(feature = "metrics")]
+mod enabled {
+    use prometheus::{Counter, Encoder, GaugeVec, Histogram, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder};
+    use std::collections::HashMap;
+    use std::sync::{Mutex, OnceLock};
+    use std::time::Instant;
+
+    pub struct Metrics {
+        pub registry: Registry,
+        pub opportunities: IntCounterVec,
+        pub rpc_latency: HistogramVec,
+        pub detection_to_submit: Histogram,
+        pub book_staleness: GaugeVec,
+        pub gas_spent: Counter,
+        pub book_updates: Mutex<HashMap<String, Instant>>,
+    }
+
+    const LATENCY_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+    pub fn metrics() -> &'static Metrics {
+        static METRICS: OnceLock<Metrics> = OnceLock::new();
+        METRICS.get_or_init(|| {
+            let registry = Registry::new_custom(Some("arbitrage".to_string()), None).expect("Valid metrics prefix");
+            let opportunities = IntCounterVec::new(
+                Opts::new("opportunities_total", "Arbitrage opportunities by outcome"),
+                &["outcome"],
+            )
+            .expect("Valid opportunities metric");
+            let rpc_latency = HistogramVec::new(
+                HistogramOpts::new("rpc_latency_seconds", "Latency of RPC calls").buckets(LATENCY_BUCKETS.to_vec()),
+                &["method"],
+            )
+            .expect("Valid RPC latency metric");
+            let detection_to_submit = Histogram::with_opts(
+                HistogramOpts::new("detection_to_submit_seconds", "Time from detecting an opportunity to submitting its transaction")
+                    .buckets(LATENCY_BUCKETS.to_vec()),
+            )
+            .expect("Valid detection to submit metric");
+            let book_staleness = GaugeVec::new(
+                Opts::new("order_book_staleness_seconds", "Seconds since the venue's order book was last updated"),
+                &["venue"],
+            )
+            .expect("Valid staleness metric");
+            let gas_spent = Counter::new("gas_spent_wei_total", "Gas paid by confirmed transactions, in wei").expect("Valid gas metric");
+
+            registry.register(Box::new(opportunities.clone())).expect("Register opportunities metric");
+            registry.register(Box::new(rpc_latency.clone())).expect("Register RPC latency metric");
+            registry.register(Box::new(detection_to_submit.clone())).expect("Register detection to submit metric");
+            registry.register(Box::new(book_staleness.clone())).expect("Register staleness metric");
+            registry.register(Box::new(gas_spent.clone())).expect("Register gas metric");
+
+            Metrics {
+                registry,
+                opportunities,
+                rpc_latency,
+                detection_to_submit,
+                book_staleness,
+                gas_spent,
+                book_updates: Mutex::new(HashMap::new()),
+            }
+        })
+    }
+
+    pub fn render() -> String {
+        let metrics = metrics();
+        for (venue, updated) in metrics.book_updates.lock().unwrap().iter() {
+            metrics.book_staleness.with_label_values(&[venue]).set(updated.elapsed().as_secs_f64());
+        }
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&metrics.registry.gather(), &mut buffer).expect("Text encoding cannot fail");
+        String::from_utf8(buffer).expect("Metrics are UTF-8")
+    }
+}
+
+fn count_opportunity(outcome: &str) {
+    #[cfg(feature = "metrics")]
+    enabled::metrics().opportunities.with_label_values(&[outcome]).inc();
+    #[cfg(not(feature = "metrics"))]
+    let _ = outcome;
+}
+
+pub fn opportunity_found() {
+    count_opportunity("found");
+}
+
+pub fn opportunity_rejected() {
+    count_opportunity("rejected");
+}
+
+pub fn opportunity_executed() {
+    count_opportunity("executed");
+}
+
+pub fn observe_rpc_latency(method: &str, latency: Duration) {
+    #[cfg(feature = "metrics")]
+    enabled::metrics().rpc_latency.with_label_values(&[method]).observe(latency.as_secs_f64());
+    #[cfg(not(feature = "metrics"))]
+    let _ = (method, latency);
+}
+
+pub fn observe_detection_to_submit(latency: Duration) {
+    #[cfg(feature = "metrics")]
+    enabled::metrics().detection_to_submit.observe(latency.as_secs_f64());
+    #[cfg(not(feature = "metrics"))]
+    let _ = latency;
+}
+
+/// Marks `venue`'s order book as fresh; staleness is measured from here at scrape time.
+pub fn record_book_update(venue: &str) {
+    #[cfg(feature = "metrics")]
+    enabled::metrics().book_updates.lock().unwrap().insert(venue.to_string(), std::time::Instant::now());
+    #[cfg(not(feature = "metrics"))]
+    let _ = venue;
+}
+
+pub fn add_gas_spent(gas_cost: U256) {
+    #[cfg(feature = "metrics")]
+    enabled::metrics().gas_spent.inc_by(gas_cost.to_string().parse::<f64>().unwrap_or_default());
+    #[cfg(not(feature = "metrics"))]
+    let _ = gas_cost;
+}
+
+/// Current metrics in the Prometheus text format.
+#[cfg(feature = "metrics")]
+pub fn render() -> String {
+    enabled::render()
+}
+
+/// Empty without the `metrics` feature.
+#[cfg(not(feature = "metrics"))]
+pub fn render() -> String {
+    String::new()
+}
+
+/// A running metrics endpoint.
+#[cfg(feature = "metrics")]
+pub struct MetricsServer {
+    pub addr: std::net::SocketAddr,
+    pub handle: std::thread::JoinHandle<()>,
+}
+
+/// Serves `render()` at `GET /metrics` on `addr`, e.g. `127.0.0.1:9898`, from a
+/// background thread.
+#[cfg(feature = "metrics")]
+pub fn serve(addr: &str) -> Result<MetricsServer, Box<dyn std::error::Error + Send + Sync>> {
+    let server = tiny_http::Server::http(addr)?;
+    let addr = server.server_addr().to_ip().ok_or("Metrics are not served on an IP address")?;
+    log::info!("Serving metrics on http://{}/metrics", addr);
+
+    let handle = std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let response = if request.url() == "/metrics" {
+                let header = tiny_http::Header::from_bytes("Content-Type", "text/plain; version=0.0.4").expect("Valid header");
+                tiny_http::Response::from_string(render()).with_header(header)
+            } else {
+                tiny_http::Response::from_string("Not found").with_status_code(404)
+            };
+            if let Err(e) = request.respond(response) {
+                log::warn!("Failed to answer metrics request: {}", e);
+            }
+        }
+    });
+    Ok(MetricsServer { addr, handle })
+}
@@ -0,0 +1,240 @@
+use crate::arbitrage_finder::ArbitrageOpportunity;
+use crate::metrics;
+use crate::trade_journal::TradeJournal;
+use chrono::{DateTime, Duration, Utc};
+use ethers::types::H256;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use log::{debug, error, warn};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum LifecycleError {
+    #[error("Unknown opportunity {0}")]
+    UnknownOpportunity(String),
+    #[error("Invalid transition for {id} from {from} to {to}")]
+    InvalidTransition { id: String, from: String, to: String },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OpportunityState {
+    Detected,
+    RiskApproved,
+    RiskRejected(String),
+    Simulated,
+    SimulationFailed(String),
+    /// Approved, but not broadcast, e.g. over the gas budget.
+    SendFailed(String),
+    Submitted(H256),
+    Confirmed(H256),
+    Reverted(H256),
+    /// Broadcast, but given up on before it was mined.
+    Dropped(String),
+}
+
+impl OpportunityState {
+    /// State name without its payload, for grouping.
+    pub fn name(&self) -> &'static str {
+        match self {
+            OpportunityState::Detected => "detected",
+            OpportunityState::RiskApproved => "risk_approved",
+            OpportunityState::RiskRejected(_) => "risk_rejected",
+            OpportunityState::Simulated => "simulated",
+            OpportunityState::SimulationFailed(_) => "simulation_failed",
+            OpportunityState::SendFailed(_) => "send_failed",
+            OpportunityState::Submitted(_) => "submitted",
+            OpportunityState::Confirmed(_) => "confirmed",
+            OpportunityState::Reverted(_) => "reverted",
+            OpportunityState::Dropped(_) => "dropped",
+        }
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            OpportunityState::RiskRejected(_)
+                | OpportunityState::SimulationFailed(_)
+                | OpportunityState::SendFailed(_)
+                | OpportunityState::Confirmed(_)
+                | OpportunityState::Reverted(_)
+                | OpportunityState::Dropped(_)
+        )
+    }
+
+    /// Executors that do not simulate go straight from approval to submission,
+    /// and an executor may still refuse an approved trade, e.g. once paused.
+    fn can_transition_to(&self, next: &OpportunityState) -> bool {
+        use OpportunityState::*;
+        matches!(
+            (self, next),
+            (Detected, RiskApproved)
+                | (Detected, RiskRejected(_))
+                | (RiskApproved, RiskRejected(_))
+                | (RiskApproved, Simulated)
+                | (RiskApproved, SimulationFailed(_))
+                | (RiskApproved, Submitted(_))
+                | (RiskApproved, SendFailed(_))
+                | (Simulated, Submitted(_))
+                | (Simulated, SendFailed(_))
+                | (Submitted(_), Confirmed(_))
+                | (Submitted(_), Reverted(_))
+                | (Submitted(_), Dropped(_))
+        )
+    }
+}
+
+impl fmt::Display for OpportunityState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpportunityState::RiskRejected(reason)
+            | OpportunityState::SimulationFailed(reason)
+            | OpportunityState::SendFailed(reason)
+            | OpportunityState::Dropped(reason) => write!(f, "{} ({})", self.name(), reason),
+            OpportunityState::Submitted(hash) | OpportunityState::Confirmed(hash) | OpportunityState::Reverted(hash) => write!(f, "{} ({:?})", self.name(), hash),
+            _ => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transition {
+    pub state: OpportunityState,
+    pub at: DateTime<Utc>,
+}
+
+/// An opportunity with every state it has been through, oldest first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpportunityRecord {
+    pub opportunity: ArbitrageOpportunity,
+    pub transitions: Vec<Transition>,
+}
+
+impl OpportunityRecord {
+    pub fn state(&self) -> &OpportunityState {
+        &self.transitions.last().expect("Records start detected").state
+    }
+
+    /// When the opportunity first entered a state called `name`.
+    pub fn entered_at(&self, name: &str) -> Option<DateTime<Utc>> {
+        self.transitions.iter().find(|transition| transition.state.name() == name).map(|transition| transition.at)
+    }
+
+    pub fn detection_to_submit(&self) -> Option<Duration> {
+        Some(self.entered_at("submitted")? - self.opportunity.detected_at)
+    }
+}
+
+/// Timestamped lifecycle of every opportunity from detection to settlement.
+#[derive(Debug, Default)]
+pub struct OpportunityTracker {
+    records: Mutex<HashMap<String, OpportunityRecord>>,
+    journal: Option<Arc<TradeJournal>>,
+}
+
+impl OpportunityTracker {
+    pub fn new() -> Self {
+        OpportunityTracker::default()
+    }
+
+    /// Persists every detection and accepted transition to `journal`.
+    pub fn set_journal(&mut self, journal: Arc<TradeJournal>) {
+        self.journal = Some(journal);
+    }
+
+    pub fn detect(&self, opportunity: ArbitrageOpportunity) {
+        let record = OpportunityRecord {
+            transitions: vec![Transition { state: OpportunityState::Detected, at: opportunity.detected_at }],
+            opportunity,
+        };
+        debug!("Tracking opportunity {}", record.opportunity.id);
+        if let Some(journal) = &self.journal {
+            if let Err(e) = journal.record_opportunity(&record.opportunity) {
+                error!("Failed to journal opportunity {}: {}", record.opportunity.id, e);
+            }
+        }
+        self.records.lock().unwrap().insert(record.opportunity.id.clone(), record);
+    }
+
+    pub fn transition(&self, id: &str, state: OpportunityState) -> Result<(), LifecycleError> {
+        self.transition_at(id, state, Utc::now())
+    }
+
+    pub fn transition_at(&self, id: &str, state: OpportunityState, at: DateTime<Utc>) -> Result<(), LifecycleError> {
+        let mut records = self.records.lock().unwrap();
+        let record = records.get_mut(id).ok_or_else(|| LifecycleError::UnknownOpportunity(id.to_string()))?;
+
+        if !record.state().can_transition_to(&state) {
+            warn!("Rejected transition of {} from {} to {}", id, record.state(), state);
+            return Err(LifecycleError::InvalidTransition {
+                id: id.to_string(),
+                from: record.state().to_string(),
+                to: state.to_string(),
+            });
+        }
+        debug!("Opportunity {} is now {}", id, state);
+        if let Some(journal) = &self.journal {
+            if let Err(e) = journal.record_state(id, &state, at) {
+                error!("Failed to journal transition of {} to {}: {}", id, state, e);
+            }
+        }
+        match &state {
+            OpportunityState::RiskRejected(_) | OpportunityState::SimulationFailed(_) => metrics::opportunity_rejected(),
+            OpportunityState::Submitted(_) => {
+                if let Ok(latency) = (at - record.opportunity.detected_at).to_std() {
+                    metrics::observe_detection_to_submit(latency);
+                }
+            }
+            OpportunityState::Confirmed(_) => metrics::opportunity_executed(),
+            _ => {}
+        }
+        record.transitions.push(Transition { state, at });
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<OpportunityRecord> {
+        self.records.lock().unwrap().get(id).cloned()
+    }
+
+    /// Records currently in the state called `name`.
+    pub fn in_state(&self, name: &str) -> Vec<OpportunityRecord> {
+        self.records
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|record| record.state().name() == name)
+            .cloned()
+            .collect()
+    }
+
+    /// How many opportunities ended in each non-confirmed terminal state, keyed
+    /// by state and reason, e.g. `"risk_rejected (position too small)"`.
+    pub fn failure_reasons(&self) -> HashMap<String, usize> {
+        let mut reasons = HashMap::new();
+        for record in self.records.lock().unwrap().values() {
+            let state = record.state();
+            if state.is_terminal() && !matches!(state, OpportunityState::Confirmed(_)) {
+                let reason = match state {
+                    OpportunityState::Reverted(_) => state.name().to_string(),
+                    _ => state.to_string(),
+                };
+                *reasons.entry(reason).or_insert(0) += 1;
+            }
+        }
+        reasons
+    }
+
+    pub fn detection_to_submit_latencies(&self) -> Vec<Duration> {
+        self.records.lock().unwrap().values().filter_map(OpportunityRecord::detection_to_submit).collect()
+    }
+
+    /// Drops settled records that finished before `cutoff`, and detections from
+    /// before it that were never acted on. Records still in flight are kept.
+    pub fn prune(&self, cutoff: DateTime<Utc>) {
+        self.records.lock().unwrap().retain(|_, record| {
+            let last_at = record.transitions.last().map_or(record.opportunity.detected_at, |transition| transition.at);
+            let settled = record.state().is_terminal() || record.state() == &OpportunityState::Detected;
+            !settled || last_at >= cutoff
+        });
+    }
+}
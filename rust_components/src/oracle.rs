@@ -0,0 +1,147 @@
+use crate::provider_pool::{PooledProvider, ProviderPool};
+use ethers::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+use log::{debug, warn};
+use thiserror::Error;
+
+abigen!(
+    AggregatorV3,
+    r#"[
+        function latestRoundData() external view returns (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound)
+        function decimals() external view returns (uint8)
+    ]"#
+);
+
+#[derive(Error, Debug, PartialEq)]
+pub enum OracleError {
+    #[error("No price feed configured for token {0:?}")]
+    MissingFeed(Address),
+    #[error("Price feed {feed:?} returned non-positive answer {answer}")]
+    InvalidAnswer { feed: Address, answer: I256 },
+    #[error("Price feed {feed:?} answer {answer} is out of range")]
+    AnswerOutOfRange { feed: Address, answer: I256 },
+    #[error("Price feed {feed:?} round is incomplete")]
+    IncompleteRound { feed: Address },
+    #[error("Price feed {feed:?} is stale: updated {age_secs}s ago, max {max_age_secs}s")]
+    Stale { feed: Address, age_secs: u64, max_age_secs: u64 },
+    #[error("DEX price {dex_price} deviates {deviation_bps:.1} bps from oracle price {oracle_price} (max {max_deviation_bps} bps)")]
+    Deviation { dex_price: f64, oracle_price: f64, deviation_bps: f64, max_deviation_bps: f64 },
+    #[error("Oracle call failed: {0}")]
+    Provider(String),
+}
+
+/// A `latestRoundData` answer together with the feed's decimals.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OracleRound {
+    pub feed: Address,
+    pub round_id: u128,
+    pub answer: I256,
+    pub decimals: u8,
+    pub updated_at: u64,
+    pub answered_in_round: u128,
+}
+
+impl OracleRound {
+    /// Rejects non-positive answers, unfinished rounds and answers older than
+    /// `max_age_secs` at time `now`, returning the answer as a float price.
+    pub fn validate(&self, now: u64, max_age_secs: u64) -> Result<f64, OracleError> {
+        if self.answer <= I256::zero() {
+            return Err(OracleError::InvalidAnswer { feed: self.feed, answer: self.answer });
+        }
+        if self.updated_at == 0 || self.answered_in_round < self.round_id {
+            return Err(OracleError::IncompleteRound { feed: self.feed });
+        }
+        let age_secs = now.saturating_sub(self.updated_at);
+        if age_secs > max_age_secs {
+            return Err(OracleError::Stale { feed: self.feed, age_secs, max_age_secs });
+        }
+        let answer = u128::try_from(self.answer.into_raw()).map_err(|_| OracleError::AnswerOutOfRange { feed: self.feed, answer: self.answer })?;
+        Ok(answer as f64 / 10f64.powi(self.decimals as i32))
+    }
+}
+
+/// Relative difference between `dex_price` and `oracle_price` in basis points.
+pub fn deviation_bps(dex_price: f64, oracle_price: f64) -> f64 {
+    ((dex_price - oracle_price) / oracle_price).abs() * 10_000.0
+}
+
+pub fn check_deviation(dex_price: f64, oracle_price: f64, max_deviation_bps: f64) -> Result<(), OracleError> {
+    let deviation_bps = deviation_bps(dex_price, oracle_price);
+    if !deviation_bps.is_finite() || deviation_bps > max_deviation_bps {
+        return Err(OracleError::Deviation { dex_price, oracle_price, deviation_bps, max_deviation_bps });
+    }
+    Ok(())
+}
+
+/// Compares DEX-implied prices with Chainlink feeds, the same
+/// `AggregatorV3Interface` feeds registered on `ArbitrageBot.sol`.
+pub struct OracleGuard {
+    provider: Arc<PooledProvider>,
+    feeds: HashMap<Address, Address>,
+    max_age_secs: u64,
+    max_deviation_bps: f64,
+}
+
+impl OracleGuard {
+    pub fn new(provider_url: &str, max_age_secs: u64, max_deviation_bps: f64) -> Result<Self, OracleError> {
+        let pool = ProviderPool::new(&[provider_url.to_string()]).map_err(|e| OracleError::Provider(e.to_string()))?;
+        Ok(Self::with_provider(Arc::new(pool.into_provider()), max_age_secs, max_deviation_bps))
+    }
+
+    /// Reads the feeds through `provider`, e.g. the chain's pool of endpoints.
+    pub fn with_provider(provider: Arc<PooledProvider>, max_age_secs: u64, max_deviation_bps: f64) -> Self {
+        OracleGuard {
+            provider,
+            feeds: HashMap::new(),
+            max_age_secs,
+            max_deviation_bps,
+        }
+    }
+
+    /// Registers the feed pricing `token`; all feeds must share a quote currency (e.g. USD).
+    pub fn add_feed(&mut self, token: Address, feed: Address) {
+        self.feeds.insert(token, feed);
+    }
+
+    pub async fn latest_round(&self, token: Address) -> Result<OracleRound, OracleError> {
+        let feed = *self.feeds.get(&token).ok_or(OracleError::MissingFeed(token))?;
+        let aggregator = AggregatorV3::new(feed, self.provider.clone());
+
+        let (round_id, answer, _started_at, updated_at, answered_in_round) = aggregator
+            .latest_round_data()
+            .call()
+            .await
+            .map_err(|e| OracleError::Provider(e.to_string()))?;
+        let decimals = aggregator.decimals().call().await.map_err(|e| OracleError::Provider(e.to_string()))?;
+
+        Ok(OracleRound {
+            feed,
+            round_id,
+            answer,
+            decimals,
+            updated_at: updated_at.low_u64(),
+            answered_in_round,
+        })
+    }
+
+    /// Fresh oracle price of `token` in the feeds' quote currency.
+    pub async fn price(&self, token: Address) -> Result<f64, OracleError> {
+        let round = self.latest_round(token).await?;
+        round.validate(chrono::Utc::now().timestamp() as u64, self.max_age_secs)
+    }
+
+    /// Checks each of `dex_prices`, in units of `quote` per `base`, against the
+    /// ratio of the two tokens' oracle prices.
+    pub async fn check_prices(&self, base: Address, quote: Address, dex_prices: &[f64]) -> Result<(), OracleError> {
+        let oracle_price = self.price(base).await? / self.price(quote).await?;
+        for dex_price in dex_prices {
+            debug!("Oracle price for {:?}/{:?}: {}, DEX price: {}", base, quote, oracle_price, dex_price);
+            if let Err(e) = check_deviation(*dex_price, oracle_price, self.max_deviation_bps) {
+                warn!("Oracle guard rejected price: {}", e);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
@@ -59,6 +59,14 @@ impl OrderBook {
         self.asks.iter().next().map(|(price, amount)| (price.into_inner(), *amount))
     }
 
+    /// Midpoint of the best bid and ask, if both sides have orders.
+    pub fn get_mid_price(&self) -> Option<f64> {
+        match (self.get_best_bid(), self.get_best_ask()) {
+            (Some((bid, _)), Some((ask, _))) => Some((bid + ask) / 2.0),
+            _ => None,
+        }
+    }
+
     pub fn get_all_bids(&self) -> Vec<Order> {
         self.bids.iter().map(|(price, amount)| Order::new(price.into_inner(), *amount).unwrap()).collect()
     }
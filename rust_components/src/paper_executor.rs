@@ -0,0 +1,331 @@
+use crate::flash_loan::{flash_loan_premium, FlashLoanParams};
+use crate::trade_executor::{Executor, RoutedTrade, TradeObserver, WETH_ADDRESS};
+use async_trait::async_trait;
+use ethers::abi::{encode, Token};
+use ethers::prelude::*;
+use ethers::utils::keccak256;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+const SWAP_FEE_NUMERATOR: u64 = 997;
+const SWAP_FEE_DENOMINATOR: u64 = 1000;
+const SWAP_GAS_ESTIMATE: u64 = 150_000;
+const FLASH_LOAN_GAS_ESTIMATE: u64 = 350_000;
+/// Aave V3's flash loan premium, until the pool's own is set.
+const DEFAULT_FLASH_LOAN_PREMIUM_BPS: u64 = 5;
+
+#[derive(Error, Clone, Debug, PartialEq)]
+pub enum PaperTradeError {
+    #[error("Invalid path: at least two tokens are required")]
+    InvalidPath,
+    #[error("Path has {hops} hop(s) but {routers} router(s)")]
+    RouterMismatch { hops: usize, routers: usize },
+    #[error("No reserves known for pair {0:?}/{1:?}")]
+    UnknownPair(Address, Address),
+    #[error("Path from {start:?} to {end:?} is not a round trip, so it has no PnL")]
+    NotRoundTrip { start: Address, end: Address },
+    #[error("Flash loan of {asset:?} must trade a path from and back to it")]
+    FlashLoanPath { asset: Address },
+    #[error("Invalid flash loan params: {0}")]
+    FlashLoanParams(String),
+    #[error("No pool prices gas in {0:?}")]
+    NoGasPrice(Address),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PaperTradeKind {
+    Swap,
+    FlashLoan,
+}
+
+/// A trade that would have been sent, with the outcome the simulation predicted.
+#[derive(Clone, Debug)]
+pub struct PaperFill {
+    pub transaction_hash: H256,
+    pub kind: PaperTradeKind,
+    pub path: Vec<Address>,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    pub gas_used: U256,
+    pub gas_price: U256,
+    pub success: bool,
+    /// Output minus input, flash loan premium and gas, in units of the token
+    /// traded. A reverted trade loses its gas.
+    pub pnl: I256,
+}
+
+impl PaperFill {
+    pub fn gas_cost(&self) -> U256 {
+        self.gas_used * self.gas_price
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PaperLedger {
+    fills: Vec<PaperFill>,
+}
+
+impl PaperLedger {
+    pub fn new() -> Self {
+        PaperLedger::default()
+    }
+
+    pub fn record(&mut self, fill: PaperFill) {
+        self.fills.push(fill);
+    }
+
+    pub fn fills(&self) -> &[PaperFill] {
+        &self.fills
+    }
+
+    pub fn realized_pnl(&self) -> I256 {
+        self.fills.iter().fold(I256::zero(), |total, fill| total + fill.pnl)
+    }
+
+    pub fn total_gas_cost(&self) -> U256 {
+        self.fills
+            .iter()
+            .fold(U256::zero(), |total, fill| total + fill.gas_cost())
+    }
+}
+
+/// Router, input token and output token of one pool direction.
+type PoolKey = (Address, Address, Address);
+
+/// Executor that simulates trades against the latest known pool reserves and
+/// records the would-be receipts in a local ledger. Nothing is ever broadcast.
+///
+/// Pools are kept per router, like on chain: `execute_trade` swaps every hop on
+/// the executor's own router, whose pools `update_reserves` sets, and
+/// `execute_routed_trade` swaps each hop on the pool of its router. Only round
+/// trips are filled, and their gas is charged in the traded token at the price
+/// of a pool pairing it with the native token.
+pub struct PaperTradeExecutor {
+    reserves: Mutex<HashMap<PoolKey, (U256, U256)>>,
+    ledger: Mutex<PaperLedger>,
+    gas_price: Mutex<U256>,
+    native_token: Address,
+    flash_loan_premium_bps: U256,
+}
+
+impl PaperTradeExecutor {
+    pub fn new(gas_price: U256) -> Self {
+        PaperTradeExecutor {
+            reserves: Mutex::new(HashMap::new()),
+            ledger: Mutex::new(PaperLedger::new()),
+            gas_price: Mutex::new(gas_price),
+            native_token: WETH_ADDRESS.parse().expect("Invalid WETH address"),
+            flash_loan_premium_bps: U256::from(DEFAULT_FLASH_LOAN_PREMIUM_BPS),
+        }
+    }
+
+    /// Wrapped native token, worth gas one to one; mainnet WETH until set.
+    pub fn set_native_token(&mut self, native_token: Address) {
+        self.native_token = native_token;
+    }
+
+    /// `FLASHLOAN_PREMIUM_TOTAL` of the pool that flash loans borrow from.
+    pub fn set_flash_loan_premium_bps(&mut self, flash_loan_premium_bps: U256) {
+        self.flash_loan_premium_bps = flash_loan_premium_bps;
+    }
+
+    /// Reserves of the `token_a`/`token_b` pool on the executor's own router.
+    pub fn update_reserves(&self, token_a: Address, token_b: Address, reserve_a: U256, reserve_b: U256) {
+        self.set_pool_reserves(Address::zero(), token_a, token_b, reserve_a, reserve_b);
+    }
+
+    /// Reserves of the `token_a`/`token_b` pool that `router` trades on.
+    pub fn set_pool_reserves(&self, router: Address, token_a: Address, token_b: Address, reserve_a: U256, reserve_b: U256) {
+        let mut reserves = self.reserves.lock().unwrap();
+        reserves.insert((router, token_a, token_b), (reserve_a, reserve_b));
+        reserves.insert((router, token_b, token_a), (reserve_b, reserve_a));
+    }
+
+    pub fn set_gas_price(&self, gas_price: U256) {
+        *self.gas_price.lock().unwrap() = gas_price;
+    }
+
+    pub fn ledger(&self) -> PaperLedger {
+        self.ledger.lock().unwrap().clone()
+    }
+
+    /// Uniswap V2 `getAmountsOut` over the executor's own router's pools, 0.3% fee per hop.
+    pub fn get_amount_out(&self, amount_in: U256, path: &[Address]) -> Result<U256, PaperTradeError> {
+        let routers = vec![Address::zero(); path.len().saturating_sub(1)];
+        self.get_routed_amount_out(amount_in, &routers, path)
+    }
+
+    /// Output of swapping hop `i` of `path` on the pool of `routers[i]`.
+    pub fn get_routed_amount_out(&self, amount_in: U256, routers: &[Address], path: &[Address]) -> Result<U256, PaperTradeError> {
+        if path.len() < 2 {
+            return Err(PaperTradeError::InvalidPath);
+        }
+        if routers.len() != path.len() - 1 {
+            return Err(PaperTradeError::RouterMismatch { hops: path.len() - 1, routers: routers.len() });
+        }
+
+        let reserves = self.reserves.lock().unwrap();
+        let mut amount = amount_in;
+        for (router, hop) in routers.iter().zip(path.windows(2)) {
+            let (reserve_in, reserve_out) = reserves
+                .get(&(*router, hop[0], hop[1]))
+                .copied()
+                .ok_or(PaperTradeError::UnknownPair(hop[0], hop[1]))?;
+            if reserve_in.is_zero() || reserve_out.is_zero() {
+                return Err(PaperTradeError::UnknownPair(hop[0], hop[1]));
+            }
+
+            let amount_in_with_fee = amount * SWAP_FEE_NUMERATOR;
+            amount = amount_in_with_fee * reserve_out
+                / (reserve_in * SWAP_FEE_DENOMINATOR + amount_in_with_fee);
+        }
+        Ok(amount)
+    }
+
+    /// `gas_cost` in `token`, priced on the first of `routers` with a pool
+    /// pairing it with the native token, else on the deepest such pool.
+    fn gas_in(&self, token: Address, gas_cost: U256, routers: &[Address]) -> Result<U256, PaperTradeError> {
+        if gas_cost.is_zero() || token == self.native_token {
+            return Ok(gas_cost);
+        }
+        let reserves = self.reserves.lock().unwrap();
+        let pool = routers.iter().find_map(|router| reserves.get(&(*router, self.native_token, token))).or_else(|| {
+            reserves
+                .iter()
+                .filter(|((_, token_in, token_out), _)| *token_in == self.native_token && *token_out == token)
+                .map(|(_, pool)| pool)
+                .max_by_key(|(reserve_native, _)| *reserve_native)
+        });
+        match pool {
+            Some((reserve_native, reserve_token)) if !reserve_native.is_zero() => Ok(gas_cost * *reserve_token / *reserve_native),
+            _ => Err(PaperTradeError::NoGasPrice(token)),
+        }
+    }
+
+    fn record_fill(&self, mut fill: PaperFill, account: Address) -> TransactionReceipt {
+        let mut ledger = self.ledger.lock().unwrap();
+        let sequence = ledger.fills().len() as u64;
+        fill.transaction_hash = H256::from(keccak256(encode(&[
+            Token::Uint(U256::from(sequence)),
+            Token::Array(fill.path.iter().copied().map(Token::Address).collect()),
+            Token::Uint(fill.amount_in),
+        ])));
+
+        let receipt = TransactionReceipt {
+            transaction_hash: fill.transaction_hash,
+            from: account,
+            status: Some(U64::from(fill.success as u64)),
+            gas_used: Some(fill.gas_used),
+            effective_gas_price: Some(fill.gas_price),
+            ..Default::default()
+        };
+        ledger.record(fill);
+        receipt
+    }
+}
+
+#[async_trait]
+impl Executor for PaperTradeExecutor {
+    async fn execute_trade(&self, path: Vec<Address>, amount: U256, min_amount_out: U256, deadline: U256, account: Address) -> Result<TransactionReceipt, Box<dyn std::error::Error>> {
+        let routers = vec![Address::zero(); path.len().saturating_sub(1)];
+        let trade = RoutedTrade { routers, path, amount, min_amount_out, deadline, account };
+        self.execute_routed_trade(trade, &()).await
+    }
+
+    async fn execute_routed_trade(&self, trade: RoutedTrade, observer: &dyn TradeObserver) -> Result<TransactionReceipt, Box<dyn std::error::Error>> {
+        let RoutedTrade { routers, path, amount, min_amount_out, deadline, account } = trade;
+        info!("Paper trading: simulating trade {:?} via {:?}", path, routers);
+
+        let (Some(start), Some(end)) = (path.first().copied(), path.last().copied()) else {
+            return Err(Box::new(PaperTradeError::InvalidPath));
+        };
+        if start != end {
+            return Err(Box::new(PaperTradeError::NotRoundTrip { start, end }));
+        }
+        let amount_out = self.get_routed_amount_out(amount, &routers, &path)?;
+        let gas_price = *self.gas_price.lock().unwrap();
+        let gas_used = U256::from(SWAP_GAS_ESTIMATE);
+        let gas = I256::from_raw(self.gas_in(start, gas_used * gas_price, &routers)?);
+        observer.simulated();
+        let now = U256::from(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs());
+        let success = amount_out >= min_amount_out && now <= deadline;
+        if !success {
+            warn!(
+                "Paper trade would revert: amount_out={}, min_amount_out={}, deadline={}",
+                amount_out, min_amount_out, deadline
+            );
+        }
+
+        let pnl = if success {
+            I256::from_raw(amount_out) - I256::from_raw(amount) - gas
+        } else {
+            -gas
+        };
+        let fill = PaperFill {
+            transaction_hash: H256::zero(),
+            kind: PaperTradeKind::Swap,
+            path,
+            amount_in: amount,
+            amount_out: if success { amount_out } else { U256::zero() },
+            gas_used,
+            gas_price,
+            success,
+            pnl,
+        };
+
+        let receipt = self.record_fill(fill, account);
+        observer.submitted(receipt.transaction_hash);
+        info!("Paper trade recorded: {:?}", receipt.transaction_hash);
+        Ok(receipt)
+    }
+
+    /// Simulates the `ArbitrageBot` callback that `data` encodes as
+    /// `FlashLoanParams`: the path is swapped on the executor's own router and
+    /// the output must repay the loan and its premium.
+    async fn execute_flash_loan_trade(&self, token: Address, amount: U256, data: Bytes) -> Result<TransactionReceipt, Box<dyn std::error::Error>> {
+        info!("Paper trading: simulating flash loan for token: {:?}, amount: {:?}", token, amount);
+
+        let params = FlashLoanParams::decode(&data).map_err(|e| PaperTradeError::FlashLoanParams(e.to_string()))?;
+        if params.check_round_trip(token).is_err() {
+            return Err(Box::new(PaperTradeError::FlashLoanPath { asset: token }));
+        }
+        let amount_out = self.get_amount_out(amount, &params.path)?;
+        let premium = flash_loan_premium(amount, self.flash_loan_premium_bps);
+        let gas_price = *self.gas_price.lock().unwrap();
+        let gas_used = U256::from(FLASH_LOAN_GAS_ESTIMATE);
+        let gas = I256::from_raw(self.gas_in(token, gas_used * gas_price, &[Address::zero()])?);
+
+        let now = U256::from(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs());
+        let success = amount_out >= amount + premium && amount_out >= params.min_amount_out && now <= params.deadline;
+        if !success {
+            warn!(
+                "Paper flash loan would revert: amount_out={}, repayment={}, min_amount_out={}, deadline={}",
+                amount_out, amount + premium, params.min_amount_out, params.deadline
+            );
+        }
+
+        let pnl = if success {
+            I256::from_raw(amount_out) - I256::from_raw(amount + premium) - gas
+        } else {
+            -gas
+        };
+        let fill = PaperFill {
+            transaction_hash: H256::zero(),
+            kind: PaperTradeKind::FlashLoan,
+            path: params.path,
+            amount_in: amount,
+            amount_out: if success { amount_out } else { U256::zero() },
+            gas_used,
+            gas_price,
+            success,
+            pnl,
+        };
+
+        let receipt = self.record_fill(fill, Address::zero());
+        info!("Paper flash loan recorded: {:?}", receipt.transaction_hash);
+        Ok(receipt)
+    }
+}
@@ -0,0 +1,101 @@
+use crate::order_book::OrderBook;
+use std::collections::{HashMap, VecDeque};
+use log::debug;
+
+/// Simple returns between consecutive prices.
+pub fn simple_returns(prices: &[f64]) -> Vec<f64> {
+    prices.windows(2).map(|pair| (pair[1] - pair[0]) / pair[0]).collect()
+}
+
+/// Population standard deviation of simple returns, as in the Python
+/// `RiskAssessor._calculate_volatility` (`np.std(np.diff(p) / p[:-1])`).
+pub fn realized_volatility(prices: &[f64]) -> f64 {
+    let returns = simple_returns(prices);
+    if returns.is_empty() {
+        return 0.0;
+    }
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / returns.len() as f64;
+    variance.sqrt()
+}
+
+/// RiskMetrics-style exponentially weighted volatility with decay `lambda`,
+/// seeded with the first squared return.
+pub fn ewma_volatility(prices: &[f64], lambda: f64) -> f64 {
+    let returns = simple_returns(prices);
+    let mut iter = returns.iter();
+    let Some(first) = iter.next() else {
+        return 0.0;
+    };
+    let variance = iter.fold(first.powi(2), |variance, r| lambda * variance + (1.0 - lambda) * r.powi(2));
+    variance.sqrt()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PriceSample {
+    pub timestamp: u64,
+    pub price: f64,
+}
+
+/// Rolling price series per pair, keeping at most `max_samples` each.
+#[derive(Debug)]
+pub struct PriceHistory {
+    max_samples: usize,
+    series: HashMap<String, VecDeque<PriceSample>>,
+}
+
+impl PriceHistory {
+    pub fn new(max_samples: usize) -> Self {
+        PriceHistory {
+            max_samples,
+            series: HashMap::new(),
+        }
+    }
+
+    /// Appends a price; non-positive and non-finite prices are ignored.
+    pub fn record(&mut self, pair: &str, timestamp: u64, price: f64) {
+        if !price.is_finite() || price <= 0.0 {
+            debug!("Ignoring invalid price {} for {}", price, pair);
+            return;
+        }
+        let series = self.series.entry(pair.to_string()).or_default();
+        series.push_back(PriceSample { timestamp, price });
+        while series.len() > self.max_samples {
+            series.pop_front();
+        }
+    }
+
+    /// Records one price per key, e.g. per pair of a market snapshot.
+    pub fn record_snapshot(&mut self, prices: &HashMap<String, f64>, timestamp: u64) {
+        for (pair, price) in prices {
+            self.record(pair, timestamp, *price);
+        }
+    }
+
+    /// Records the mid price of an order book, if it has both sides.
+    pub fn record_order_book(&mut self, pair: &str, order_book: &OrderBook, timestamp: u64) {
+        if let Some(mid) = order_book.get_mid_price() {
+            self.record(pair, timestamp, mid);
+        }
+    }
+
+    pub fn samples(&self, pair: &str) -> Vec<PriceSample> {
+        self.series.get(pair).map(|series| series.iter().copied().collect()).unwrap_or_default()
+    }
+
+    /// The last `window` prices for `pair`, oldest first.
+    pub fn prices(&self, pair: &str, window: usize) -> Vec<f64> {
+        let Some(series) = self.series.get(pair) else {
+            return Vec::new();
+        };
+        series.iter().skip(series.len().saturating_sub(window)).map(|sample| sample.price).collect()
+    }
+
+    pub fn realized_volatility(&self, pair: &str, window: usize) -> f64 {
+        realized_volatility(&self.prices(pair, window))
+    }
+
+    pub fn ewma_volatility(&self, pair: &str, lambda: f64) -> f64 {
+        ewma_volatility(&self.prices(pair, self.max_samples), lambda)
+    }
+}
@@ -1,36 +1,103 @@
+use crate::config::ChainConfig;
+use crate::metrics;
+use crate::provider_pool::{PooledProvider, ProviderPool};
+use chrono::{DateTime, Utc};
 use ethers::prelude::*;
+use serde::Serialize;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use log::{info, warn, error, debug};
+use tracing::{info_span, instrument, Instrument};
+
+abigen!(
+    IUniswapV2Pair,
+    r#"[
+        function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast)
+    ]"#
+);
+
+/// How long a DEX's last good price is reported as stale before it goes missing.
+const DEFAULT_MAX_PRICE_AGE: Duration = Duration::from_secs(60);
+
+/// Outcome of one poll of a DEX.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum PriceReading {
+    Fresh(f64),
+    /// The read failed; `price` is the last good one, from `updated_at`.
+    Stale { price: f64, updated_at: DateTime<Utc>, error: String },
+    /// The read failed and there is no recent enough price to fall back on.
+    Missing { error: String },
+}
+
+impl PriceReading {
+    /// The price, only if it was read in this poll.
+    pub fn fresh(&self) -> Option<f64> {
+        match self {
+            PriceReading::Fresh(price) => Some(*price),
+            _ => None,
+        }
+    }
+}
 
 pub struct PriceMonitor {
-    provider: Arc<Provider<Http>>,
+    provider: Arc<PooledProvider>,
+    chain_id: Option<u64>,
     dex_addresses: HashMap<String, Address>,
-    pair_contracts: HashMap<String, Contract<Arc<Provider<Http>>>>,
+    pair_contracts: HashMap<String, Contract<Arc<PooledProvider>>>,
+    max_price_age: Duration,
+    last_prices: Mutex<HashMap<String, (f64, DateTime<Utc>)>>,
 }
 
 impl PriceMonitor {
     pub fn new(provider_url: &str, dex_addresses: HashMap<String, Address>) -> Self {
-        let provider = Arc::new(Provider::<Http>::try_from(provider_url).expect("Failed to create provider"));
+        let pool = ProviderPool::new(&[provider_url.to_string()]).expect("Failed to create provider");
+        Self::with_provider(Arc::new(pool.into_provider()), dex_addresses)
+    }
+
+    /// Reads through `provider`, e.g. a pool over several endpoints.
+    pub fn with_provider(provider: Arc<PooledProvider>, dex_addresses: HashMap<String, Address>) -> Self {
         PriceMonitor {
             provider,
+            chain_id: None,
             dex_addresses,
             pair_contracts: HashMap::new(),
+            max_price_age: DEFAULT_MAX_PRICE_AGE,
+            last_prices: Mutex::new(HashMap::new()),
         }
     }
 
+    /// Watches the pools configured for `chain`.
+    pub fn for_chain(provider: Arc<PooledProvider>, chain: &ChainConfig) -> Self {
+        let mut monitor = Self::with_provider(provider, chain.pool_addresses());
+        monitor.chain_id = Some(chain.chain_id);
+        monitor
+    }
+
+    pub fn chain_id(&self) -> Option<u64> {
+        self.chain_id
+    }
+
+    pub fn set_max_price_age(&mut self, max_price_age: Duration) {
+        self.max_price_age = max_price_age;
+    }
+
     pub async fn initialize_pairs(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         for (dex, pair_address) in &self.dex_addresses {
-            let pair_contract = Contract::new(*pair_address, IUniswapV2Pair::abi(), self.provider.clone());
+            let pair_contract = Contract::new(*pair_address, IUNISWAPV2PAIR_ABI.clone(), Arc::new(self.provider.clone()));
             self.pair_contracts.insert(dex.clone(), pair_contract);
         }
         info!("Pair contracts initialized successfully");
         Ok(())
     }
 
+    #[instrument(skip(self))]
     pub async fn get_price(&self, dex: &str) -> Result<f64, Box<dyn std::error::Error>> {
         let pair_contract = self.pair_contracts.get(dex).ok_or("Pair contract not found")?;
-        let reserves: (U256, U256, u32) = pair_contract.method("getReserves")?.call().await?;
+        let started = Instant::now();
+        let reserves = pair_contract.method::<_, (U256, U256, u32)>("getReserves", ())?.call().await;
+        metrics::observe_rpc_latency("getReserves", started.elapsed());
+        let reserves: (U256, U256, u32) = reserves?;
 
         if reserves.0.is_zero() || reserves.1.is_zero() {
             warn!("Zero reserves detected for {}", dex);
@@ -42,24 +109,46 @@ impl PriceMonitor {
         Ok(price)
     }
 
+    /// Polls every DEX once. A failed read falls back to the last good price
+    /// as `Stale` for up to `max_price_age`, then reports `Missing`.
+    pub async fn read_prices(&self) -> HashMap<String, PriceReading> {
+        let mut readings = HashMap::new();
+        for dex in self.dex_addresses.keys() {
+            let result = self.get_price(dex).await.map_err(|e| e.to_string());
+            let mut last_prices = self.last_prices.lock().unwrap();
+            let reading = match result {
+                Ok(price) => {
+                    last_prices.insert(dex.clone(), (price, Utc::now()));
+                    PriceReading::Fresh(price)
+                }
+                Err(error) => {
+                    let max_age = chrono::Duration::from_std(self.max_price_age).unwrap_or(chrono::Duration::MAX);
+                    match last_prices.get(dex) {
+                        Some((price, updated_at)) if Utc::now() - *updated_at <= max_age => {
+                            warn!("Error getting price for {}, last good price is from {}: {}", dex, updated_at, error);
+                            PriceReading::Stale { price: *price, updated_at: *updated_at, error }
+                        }
+                        _ => {
+                            error!("Error getting price for {}, no recent price: {}", dex, error);
+                            PriceReading::Missing { error }
+                        }
+                    }
+                }
+            };
+            readings.insert(dex.clone(), reading);
+        }
+        readings
+    }
+
     pub async fn monitor_prices<F>(&self, callback: F, interval: u64)
     where
-        F: Fn(HashMap<String, f64>) + Send + 'static,
+        F: Fn(HashMap<String, PriceReading>) + Send,
     {
         loop {
-            let mut prices = HashMap::new();
-            for dex in self.dex_addresses.keys() {
-                match self.get_price(dex).await {
-                    Ok(price) => {
-                        prices.insert(dex.clone(), price);
-                    }
-                    Err(e) => {
-                        error!("Error getting price for {}: {:?}", dex, e);
-                    }
-                }
-            }
-            callback(prices);
+            let span = info_span!("fetch_prices");
+            let readings = self.read_prices().instrument(span.clone()).await;
+            span.in_scope(|| callback(readings));
             tokio::time::sleep(tokio::time::Duration::from_millis(interval)).await;
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,460 @@
+use crate::rpc_retry::{classify, ErrorClass, RetryPolicy, TokenBucket};
+use async_trait::async_trait;
+use ethers::providers::{Http, HttpClientError, JsonRpcClient, JsonRpcError, Provider, ProviderError, RpcError};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{BlockId, Bytes, U64};
+use futures::future::join_all;
+use reqwest::Url;
+use log::{debug, info, warn};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::task::JoinHandle;
+
+/// Failed requests in a row before an endpoint is only used as a last resort.
+const DEFAULT_MAX_FAILURES: u32 = 3;
+/// Blocks an endpoint may trail the highest one seen before it counts as lagging.
+const DEFAULT_MAX_BLOCK_LAG: u64 = 3;
+/// How long one endpoint gets to answer before the next one is tried.
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Weight of the newest sample in the latency average.
+const LATENCY_SMOOTHING: f64 = 0.2;
+
+/// A provider that spreads requests over several RPC endpoints.
+pub type PooledProvider = Provider<ProviderPool>;
+
+#[derive(Error, Debug)]
+pub enum ProviderPoolError {
+    /// An endpoint answered with an error, e.g. a revert. Not retried elsewhere.
+    #[error(transparent)]
+    Endpoint(#[from] HttpClientError),
+    #[error("Invalid RPC url {0}")]
+    InvalidUrl(String),
+    #[error("No RPC endpoints configured")]
+    NoEndpoints,
+    #[error("{method} failed on all {tried} endpoint(s) in {rounds} round(s), last error: {last}")]
+    AllFailed { method: String, tried: usize, rounds: u32, last: HttpClientError },
+    #[error("{method} needs {needed} agreeing endpoint(s), only {agreeing} agree")]
+    NoQuorum { method: String, needed: usize, agreeing: usize },
+}
+
+impl RpcError for ProviderPoolError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            ProviderPoolError::Endpoint(e) => e.as_error_response(),
+            _ => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            ProviderPoolError::Endpoint(e) => e.as_serde_error(),
+            _ => None,
+        }
+    }
+}
+
+impl From<ProviderPoolError> for ProviderError {
+    fn from(e: ProviderPoolError) -> Self {
+        match e {
+            ProviderPoolError::Endpoint(e) => e.into(),
+            e => ProviderError::JsonRpcClientError(Box::new(e)),
+        }
+    }
+}
+
+/// Drops the url from transport errors, since it may carry an API key.
+fn scrub(e: HttpClientError) -> HttpClientError {
+    match e {
+        HttpClientError::ReqwestError(e) => HttpClientError::ReqwestError(e.without_url()),
+        e => e,
+    }
+}
+
+/// Health of one endpoint as seen by its last requests and checks.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct EndpointStatus {
+    /// `scheme://host[:port]` only; paths and queries may hold API keys.
+    pub endpoint: String,
+    pub healthy: bool,
+    pub latency_ms: Option<f64>,
+    pub consecutive_failures: u32,
+    pub block_number: Option<u64>,
+    pub lagging: bool,
+}
+
+#[derive(Debug, Default)]
+struct Health {
+    latency: Option<Duration>,
+    consecutive_failures: u32,
+    block_number: Option<u64>,
+    lagging: bool,
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    label: String,
+    client: Http,
+    limiter: TokenBucket,
+    health: Mutex<Health>,
+}
+
+impl Endpoint {
+    /// Sends once the rate limit allows, timing the request itself.
+    async fn request<T, R>(&self, method: &str, params: T) -> (Result<R, HttpClientError>, Duration)
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        self.limiter.acquire().await;
+        let started = Instant::now();
+        let result = self.client.request(method, params).await;
+        (result, started.elapsed())
+    }
+
+    /// Counts a retryable failure; a rate limited endpoint also gets no more
+    /// requests until its bucket refills.
+    fn record_error(&self, class: ErrorClass) {
+        if class == ErrorClass::RateLimited {
+            self.limiter.drain();
+        }
+        self.record_failure();
+    }
+
+    fn record_success(&self, elapsed: Duration) {
+        let mut health = self.health.lock().unwrap();
+        health.latency = Some(match health.latency {
+            Some(latency) => latency.mul_f64(1.0 - LATENCY_SMOOTHING) + elapsed.mul_f64(LATENCY_SMOOTHING),
+            None => elapsed,
+        });
+        if health.consecutive_failures > 0 {
+            info!("RPC endpoint {} recovered", self.label);
+        }
+        health.consecutive_failures = 0;
+    }
+
+    fn record_failure(&self) {
+        self.health.lock().unwrap().consecutive_failures += 1;
+    }
+}
+
+/// A value read at the same block from at least `agreeing` endpoints.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuorumRead {
+    pub block_number: u64,
+    pub value: Bytes,
+    pub agreeing: usize,
+}
+
+/// JSON-RPC client over several HTTP endpoints. Requests go to the fastest
+/// healthy endpoint and fail over to the next one on retryable errors;
+/// endpoints that keep failing or trail the chain head are tried last. When
+/// every endpoint fails, the round is retried after a backoff.
+/// Clones share endpoints, their health and their rate limits.
+#[derive(Clone, Debug)]
+pub struct ProviderPool {
+    endpoints: Arc<Vec<Endpoint>>,
+    max_failures: u32,
+    max_block_lag: u64,
+    retry: RetryPolicy,
+}
+
+impl ProviderPool {
+    pub fn new(urls: &[String]) -> Result<Self, ProviderPoolError> {
+        Self::with_timeout(urls, DEFAULT_REQUEST_TIMEOUT)
+    }
+
+    /// `timeout` bounds each request to one endpoint.
+    pub fn with_timeout(urls: &[String], timeout: Duration) -> Result<Self, ProviderPoolError> {
+        if urls.is_empty() {
+            return Err(ProviderPoolError::NoEndpoints);
+        }
+        let endpoints = urls
+            .iter()
+            .map(|url| {
+                let parsed = Url::parse(url).map_err(|_| ProviderPoolError::InvalidUrl(url.clone()))?;
+                let http = reqwest::Client::builder().timeout(timeout).build().map_err(|e| HttpClientError::ReqwestError(e.without_url()))?;
+                let label = match parsed.port() {
+                    Some(port) => format!("{}://{}:{}", parsed.scheme(), parsed.host_str().unwrap_or_default(), port),
+                    None => format!("{}://{}", parsed.scheme(), parsed.host_str().unwrap_or_default()),
+                };
+                let client = Http::new_with_client(parsed, http);
+                Ok(Endpoint { label, client, limiter: TokenBucket::unlimited(), health: Mutex::new(Health::default()) })
+            })
+            .collect::<Result<Vec<_>, ProviderPoolError>>()?;
+
+        Ok(ProviderPool {
+            endpoints: Arc::new(endpoints),
+            max_failures: DEFAULT_MAX_FAILURES,
+            max_block_lag: DEFAULT_MAX_BLOCK_LAG,
+            retry: RetryPolicy::default(),
+        })
+    }
+
+    /// Wraps the pool in a `Provider`.
+    pub fn into_provider(self) -> PooledProvider {
+        Provider::new(self)
+    }
+
+    pub fn set_max_failures(&mut self, max_failures: u32) {
+        self.max_failures = max_failures;
+    }
+
+    pub fn set_max_block_lag(&mut self, max_block_lag: u64) {
+        self.max_block_lag = max_block_lag;
+    }
+
+    /// Backoff between rounds over all endpoints.
+    pub fn set_retry_policy(&mut self, retry: RetryPolicy) {
+        self.retry = retry;
+    }
+
+    /// Limits each endpoint to `requests_per_sec`, in bursts of up to `burst`.
+    /// The limit is shared by every clone of the pool.
+    pub fn set_rate_limit(&self, requests_per_sec: f64, burst: u32) {
+        for endpoint in self.endpoints.iter() {
+            endpoint.limiter.set_rate(requests_per_sec, burst);
+        }
+    }
+
+    fn is_healthy(&self, health: &Health) -> bool {
+        health.consecutive_failures < self.max_failures && !health.lagging
+    }
+
+    /// Endpoint indices in the order to try them: healthy ones by latency,
+    /// then the rest. Unmeasured endpoints count as fastest so they get measured.
+    fn route(&self) -> Vec<usize> {
+        let mut order: Vec<(bool, Duration, usize)> = self
+            .endpoints
+            .iter()
+            .enumerate()
+            .map(|(index, endpoint)| {
+                let health = endpoint.health.lock().unwrap();
+                (!self.is_healthy(&health), health.latency.unwrap_or_default(), index)
+            })
+            .collect();
+        order.sort();
+        order.into_iter().map(|(_, _, index)| index).collect()
+    }
+
+    pub fn endpoint_status(&self) -> Vec<EndpointStatus> {
+        self.endpoints
+            .iter()
+            .map(|endpoint| {
+                let health = endpoint.health.lock().unwrap();
+                EndpointStatus {
+                    endpoint: endpoint.label.clone(),
+                    healthy: self.is_healthy(&health),
+                    latency_ms: health.latency.map(|latency| latency.as_secs_f64() * 1000.0),
+                    consecutive_failures: health.consecutive_failures,
+                    block_number: health.block_number,
+                    lagging: health.lagging,
+                }
+            })
+            .collect()
+    }
+
+    /// Asks every endpoint for its block number, updating latency, failures and
+    /// how far each one trails the highest block reported.
+    pub async fn check_health(&self) -> Vec<EndpointStatus> {
+        let results = join_all(self.endpoints.iter().map(|endpoint| endpoint.request::<_, U64>("eth_blockNumber", ()))).await;
+        let highest = results.iter().filter_map(|(result, _)| result.as_ref().ok()).max().map(|block| block.as_u64());
+
+        for (endpoint, (result, elapsed)) in self.endpoints.iter().zip(results) {
+            match result {
+                Ok(block) => {
+                    endpoint.record_success(elapsed);
+                    let block = block.as_u64();
+                    let lag = highest.unwrap_or(block).saturating_sub(block);
+                    let mut health = endpoint.health.lock().unwrap();
+                    if lag > self.max_block_lag && !health.lagging {
+                        warn!("RPC endpoint {} is {} blocks behind", endpoint.label, lag);
+                    }
+                    health.block_number = Some(block);
+                    health.lagging = lag > self.max_block_lag;
+                }
+                Err(e) => {
+                    let class = classify(&e);
+                    warn!("Health check of {} failed ({:?}): {}", endpoint.label, class, scrub(e));
+                    endpoint.record_error(class);
+                }
+            }
+        }
+        self.endpoint_status()
+    }
+
+    /// Runs `check_health` every `interval` in the background.
+    pub fn spawn_health_checks(&self, interval: Duration) -> JoinHandle<()> {
+        let pool = self.clone();
+        tokio::spawn(async move {
+            loop {
+                pool.check_health().await;
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+
+    /// Endpoints that have not been failing, for reads that ask all of them.
+    fn live_endpoints(&self) -> Vec<&Endpoint> {
+        self.endpoints
+            .iter()
+            .filter(|endpoint| endpoint.health.lock().unwrap().consecutive_failures < self.max_failures)
+            .collect()
+    }
+
+    /// Highest block that at least `quorum` endpoints have reached.
+    pub async fn quorum_block_number(&self, quorum: usize) -> Result<u64, ProviderPoolError> {
+        let endpoints = self.live_endpoints();
+        let results = join_all(endpoints.iter().map(|endpoint| endpoint.request::<_, U64>("eth_blockNumber", ()))).await;
+
+        let mut blocks = Vec::new();
+        for (endpoint, (result, elapsed)) in endpoints.iter().zip(results) {
+            match result {
+                Ok(block) => {
+                    endpoint.record_success(elapsed);
+                    blocks.push(block.as_u64());
+                }
+                Err(e) => {
+                    endpoint.record_error(classify(&e));
+                    debug!("eth_blockNumber failed on {}: {}", endpoint.label, scrub(e));
+                }
+            }
+        }
+        blocks.sort_unstable_by(|a, b| b.cmp(a));
+        match quorum.checked_sub(1).and_then(|index| blocks.get(index)) {
+            Some(block) => Ok(*block),
+            None => Err(ProviderPoolError::NoQuorum { method: "eth_blockNumber".to_string(), needed: quorum, agreeing: blocks.len() }),
+        }
+    }
+
+    /// `eth_call`s `tx` at the highest block `quorum` endpoints have reached.
+    pub async fn quorum_call(&self, tx: &TypedTransaction, quorum: usize) -> Result<QuorumRead, ProviderPoolError> {
+        let block_number = self.quorum_block_number(quorum).await?;
+        self.quorum_call_at(tx, block_number, quorum).await
+    }
+
+    /// `eth_call`s `tx` on every live endpoint at `block_number`, and succeeds
+    /// once `quorum` of them return the same bytes.
+    pub async fn quorum_call_at(&self, tx: &TypedTransaction, block_number: u64, quorum: usize) -> Result<QuorumRead, ProviderPoolError> {
+        let params = (tx, BlockId::from(block_number));
+        let endpoints = self.live_endpoints();
+        let results = join_all(endpoints.iter().map(|endpoint| endpoint.request::<_, Bytes>("eth_call", &params))).await;
+
+        let mut values: Vec<(Bytes, usize)> = Vec::new();
+        for (endpoint, (result, _)) in endpoints.iter().zip(results) {
+            match result {
+                Ok(value) => match values.iter_mut().find(|(seen, _)| *seen == value) {
+                    Some((_, count)) => *count += 1,
+                    None => values.push((value, 1)),
+                },
+                Err(e) => {
+                    debug!("eth_call at block {} failed on {}: {}", block_number, endpoint.label, scrub(e));
+                }
+            }
+        }
+        if values.len() > 1 {
+            warn!("Endpoints disagree on eth_call at block {}: {} distinct answers", block_number, values.len());
+        }
+
+        match values.into_iter().max_by_key(|(_, count)| *count) {
+            Some((value, agreeing)) if agreeing >= quorum => Ok(QuorumRead { block_number, value, agreeing }),
+            best => Err(ProviderPoolError::NoQuorum {
+                method: "eth_call".to_string(),
+                needed: quorum,
+                agreeing: best.map_or(0, |(_, count)| count),
+            }),
+        }
+    }
+}
+
+/// Methods that broadcast a transaction. Repeating one after a timeout could
+/// rebroadcast a transaction the node already accepted, so they are sent once.
+fn is_send(method: &str) -> bool {
+    matches!(method, "eth_sendRawTransaction" | "eth_sendTransaction")
+}
+
+impl ProviderPool {
+    /// Sends a non-idempotent request to the best endpoint only, without
+    /// retries or failover.
+    async fn send_once<T, R>(&self, method: &str, params: T) -> Result<R, ProviderPoolError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let index = *self.route().first().ok_or(ProviderPoolError::NoEndpoints)?;
+        let endpoint = &self.endpoints[index];
+        let (result, elapsed) = endpoint.request(method, &params).await;
+        match result {
+            Ok(result) => {
+                endpoint.record_success(elapsed);
+                Ok(result)
+            }
+            Err(e) => {
+                let class = classify(&e);
+                if !class.is_retryable() {
+                    endpoint.record_success(elapsed);
+                    return Err(e.into());
+                }
+                let e = scrub(e);
+                warn!("{} failed on {} ({:?}), not retrying a send: {}", method, endpoint.label, class, e);
+                endpoint.record_error(class);
+                Err(ProviderPoolError::AllFailed { method: method.to_string(), tried: 1, rounds: 1, last: e })
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for ProviderPool {
+    type Error = ProviderPoolError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, ProviderPoolError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        if is_send(method) {
+            return self.send_once(method, params).await;
+        }
+
+        let mut tried = 0;
+        let mut last_error = None;
+        for round in 0..=self.retry.max_retries {
+            if round > 0 {
+                let delay = self.retry.backoff(round - 1);
+                debug!("Retrying {} in {:?}, round {} of {}", method, delay, round + 1, self.retry.max_retries + 1);
+                tokio::time::sleep(delay).await;
+            }
+            let route = self.route();
+            tried = route.len();
+            for index in route {
+                let endpoint = &self.endpoints[index];
+                let (result, elapsed) = endpoint.request(method, &params).await;
+                let e = match result {
+                    Ok(result) => {
+                        endpoint.record_success(elapsed);
+                        return Ok(result);
+                    }
+                    Err(e) => e,
+                };
+                let class = classify(&e);
+                if !class.is_retryable() {
+                    // The node answered, so another endpoint would say the same
+                    endpoint.record_success(elapsed);
+                    return Err(e.into());
+                }
+                let e = scrub(e);
+                warn!("{} failed on {} ({:?}), failing over: {}", method, endpoint.label, class, e);
+                endpoint.record_error(class);
+                last_error = Some(e);
+            }
+        }
+        match last_error {
+            Some(last) => Err(ProviderPoolError::AllFailed { method: method.to_string(), tried, rounds: self.retry.max_retries + 1, last }),
+            None => Err(ProviderPoolError::NoEndpoints),
+        }
+    }
+}
@@ -1,39 +1,325 @@
+use crate::arbitrage_finder::ArbitrageOpportunity;
+use crate::circuit_breaker::{BreakerReason, CircuitBreaker};
+use crate::inventory::{Inventory, Location};
+use crate::oracle::{OracleError, OracleGuard};
+use crate::order_book::OrderBook;
+use crate::price_history::PriceHistory;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
 use ethers::prelude::*;
 use log::{info, warn};
+use tracing::instrument;
+
+/// Samples kept per pair; enough for the longest default window.
+const DEFAULT_HISTORY_SAMPLES: usize = 1000;
+
+/// Uniswap V2 swap fee, 0.3%.
+const SWAP_FEE_NUMERATOR: u64 = 997;
+const SWAP_FEE_DENOMINATOR: u64 = 1000;
+
+/// Constant-product reserves oriented along the trade: `reserve_in` is the token
+/// sold into the pool.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PoolReserves {
+    pub reserve_in: U256,
+    pub reserve_out: U256,
+}
+
+impl PoolReserves {
+    pub fn new(reserve_in: U256, reserve_out: U256) -> Self {
+        PoolReserves { reserve_in, reserve_out }
+    }
+
+    pub fn get_amount_out(&self, amount_in: U256) -> U256 {
+        if self.reserve_in.is_zero() || self.reserve_out.is_zero() {
+            return U256::zero();
+        }
+        let amount_in_with_fee = amount_in * SWAP_FEE_NUMERATOR;
+        amount_in_with_fee * self.reserve_out / (self.reserve_in * SWAP_FEE_DENOMINATOR + amount_in_with_fee)
+    }
+
+    /// Largest input whose execution price, fees aside, is at most
+    /// `max_price_impact_bps` worse than the spot price. The pool's spot price
+    /// itself moves by roughly twice that.
+    pub fn max_input_for_price_impact(&self, max_price_impact_bps: f64) -> U256 {
+        let impact = (max_price_impact_bps / 10_000.0).clamp(0.0, 0.9999);
+        U256::from((self.reserve_in.as_u128() as f64 * impact / (1.0 - impact)) as u128)
+    }
+}
+
+/// Profit in `token` of buying on `buy_pool` and selling the proceeds back on `sell_pool`.
+pub fn arbitrage_profit(amount_in: U256, buy_pool: &PoolReserves, sell_pool: &PoolReserves) -> U256 {
+    sell_pool.get_amount_out(buy_pool.get_amount_out(amount_in)).saturating_sub(amount_in)
+}
+
+/// Input that maximises `arbitrage_profit`, from the closed form for two chained
+/// constant-product pools. Zero when the round trip cannot be profitable.
+pub fn optimal_arbitrage_input(buy_pool: &PoolReserves, sell_pool: &PoolReserves) -> U256 {
+    let fee = SWAP_FEE_NUMERATOR as f64 / SWAP_FEE_DENOMINATOR as f64;
+    let (a_in, a_out) = (buy_pool.reserve_in.as_u128() as f64, buy_pool.reserve_out.as_u128() as f64);
+    let (b_in, b_out) = (sell_pool.reserve_in.as_u128() as f64, sell_pool.reserve_out.as_u128() as f64);
+
+    // Reserves of the single virtual pool equivalent to the two chained pools
+    let denominator = b_in + fee * a_out;
+    if denominator <= 0.0 {
+        return U256::zero();
+    }
+    let virtual_in = a_in * b_in / denominator;
+    let virtual_out = fee * a_out * b_out / denominator;
+    if fee * virtual_out <= virtual_in {
+        return U256::zero();
+    }
+
+    let optimal = ((virtual_in * virtual_out * fee).sqrt() - virtual_in) / fee;
+    U256::from(optimal.max(0.0) as u128)
+}
+
+/// Why a position was sized below the profit-maximising amount.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClampReason {
+    Unprofitable,
+    MaxPositionSize,
+    AvailableBalance,
+    PriceImpact,
+    TokenExposureCap(Address),
+    VenueExposureCap(String),
+}
+
+/// Market data for sizing an opportunity that starts and ends in `token`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SizingRequest {
+    pub token: Address,
+    pub buy_pool: PoolReserves,
+    pub sell_pool: PoolReserves,
+    pub available_balance: U256,
+}
+
+impl SizingRequest {
+    /// Takes the available balance of `token` at `location` from the inventory.
+    pub fn from_inventory(inventory: &Inventory, location: &Location, token: Address, buy_pool: PoolReserves, sell_pool: PoolReserves) -> Self {
+        SizingRequest {
+            token,
+            buy_pool,
+            sell_pool,
+            available_balance: inventory.available(location, token),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PositionSize {
+    pub size: U256,
+    pub optimal_size: U256,
+    pub expected_profit: U256,
+    pub clamped_by: Option<ClampReason>,
+}
 
 pub struct RiskManager {
     max_position_size: U256,
+    token_max_position_sizes: HashMap<Address, U256>,
     stop_loss_threshold: f64,
+    oracle_guard: Option<OracleGuard>,
+    price_history: Mutex<PriceHistory>,
+    volatility_windows: Vec<usize>,
+    ewma_lambda: f64,
+    max_price_impact_bps: f64,
+    token_exposure_caps: HashMap<Address, U256>,
+    venue_exposure_caps: HashMap<String, U256>,
+    token_exposure: Mutex<HashMap<Address, U256>>,
+    venue_exposure: Mutex<HashMap<String, U256>>,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
 }
 
 impl RiskManager {
     pub fn new(max_position_size: U256, stop_loss_threshold: f64) -> Self {
         RiskManager {
             max_position_size,
+            token_max_position_sizes: HashMap::new(),
             stop_loss_threshold,
+            oracle_guard: None,
+            price_history: Mutex::new(PriceHistory::new(DEFAULT_HISTORY_SAMPLES)),
+            volatility_windows: vec![20, 100],
+            ewma_lambda: 0.94,
+            max_price_impact_bps: 100.0,
+            token_exposure_caps: HashMap::new(),
+            venue_exposure_caps: HashMap::new(),
+            token_exposure: Mutex::new(HashMap::new()),
+            venue_exposure: Mutex::new(HashMap::new()),
+            circuit_breaker: None,
+        }
+    }
+
+    /// Largest price move a single position may cause on the buy pool.
+    pub fn set_max_price_impact_bps(&mut self, max_price_impact_bps: f64) {
+        self.max_price_impact_bps = max_price_impact_bps;
+    }
+
+    /// Largest position in `token`, in its smallest unit; overrides the limit
+    /// given to `new` for that token.
+    pub fn set_max_position_size(&mut self, token: Address, max_position_size: U256) {
+        self.token_max_position_sizes.insert(token, max_position_size);
+    }
+
+    pub fn set_token_exposure_cap(&mut self, token: Address, cap: U256) {
+        self.token_exposure_caps.insert(token, cap);
+    }
+
+    pub fn set_venue_exposure_cap(&mut self, venue: &str, cap: U256) {
+        self.venue_exposure_caps.insert(venue.to_string(), cap);
+    }
+
+    /// Books an open position against the token and both venues' caps, from
+    /// when its trade is submitted until it settles.
+    pub fn add_exposure(&self, opportunity: &ArbitrageOpportunity, token: Address, amount: U256) {
+        *self.token_exposure.lock().unwrap().entry(token).or_default() += amount;
+        let mut venue_exposure = self.venue_exposure.lock().unwrap();
+        for venue in [&opportunity.buy_dex, &opportunity.sell_dex] {
+            *venue_exposure.entry(venue.clone()).or_default() += amount;
+        }
+    }
+
+    pub fn release_exposure(&self, opportunity: &ArbitrageOpportunity, token: Address, amount: U256) {
+        if let Some(exposure) = self.token_exposure.lock().unwrap().get_mut(&token) {
+            *exposure = exposure.saturating_sub(amount);
+        }
+        let mut venue_exposure = self.venue_exposure.lock().unwrap();
+        for venue in [&opportunity.buy_dex, &opportunity.sell_dex] {
+            if let Some(exposure) = venue_exposure.get_mut(venue) {
+                *exposure = exposure.saturating_sub(amount);
+            }
+        }
+    }
+
+    fn remaining_token_capacity(&self, token: Address) -> Option<U256> {
+        let cap = self.token_exposure_caps.get(&token)?;
+        Some(cap.saturating_sub(self.token_exposure.lock().unwrap().get(&token).copied().unwrap_or_default()))
+    }
+
+    fn remaining_venue_capacity(&self, venue: &str) -> Option<U256> {
+        let cap = self.venue_exposure_caps.get(venue)?;
+        Some(cap.saturating_sub(self.venue_exposure.lock().unwrap().get(venue).copied().unwrap_or_default()))
+    }
+
+    /// Sample counts over which realized volatility is measured; the largest wins.
+    pub fn set_volatility_windows(&mut self, volatility_windows: Vec<usize>) {
+        self.volatility_windows = volatility_windows;
+    }
+
+    pub fn set_ewma_lambda(&mut self, ewma_lambda: f64) {
+        self.ewma_lambda = ewma_lambda;
+    }
+
+    pub fn price_history(&self) -> MutexGuard<'_, PriceHistory> {
+        self.price_history.lock().unwrap()
+    }
+
+    pub fn record_price(&self, pair: &str, timestamp: u64, price: f64) {
+        self.price_history().record(pair, timestamp, price);
+    }
+
+    /// Feeds one price per pair, e.g. from a market snapshot, into the price history.
+    pub fn record_price_snapshot(&self, prices: &HashMap<String, f64>, timestamp: u64) {
+        self.price_history().record_snapshot(prices, timestamp);
+    }
+
+    /// Feeds an order book mid into the price history.
+    pub fn record_order_book(&self, pair: &str, order_book: &OrderBook, timestamp: u64) {
+        self.price_history().record_order_book(pair, order_book, timestamp);
+    }
+
+    pub fn set_oracle_guard(&mut self, oracle_guard: OracleGuard) {
+        self.oracle_guard = Some(oracle_guard);
+    }
+
+    /// Blocks trades where any of the DEX prices for `base`/`quote` disagrees
+    /// with the Chainlink feeds or where a feed is stale. Passes when no guard is set.
+    pub async fn check_oracle_prices(&self, base: Address, quote: Address, dex_prices: &[f64]) -> Result<(), OracleError> {
+        match &self.oracle_guard {
+            Some(oracle_guard) => oracle_guard.check_prices(base, quote, dex_prices).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Sizes a position at the profit-maximising input for the two pools, then
+    /// clamps it by price impact, balance, the global limit and exposure caps.
+    #[instrument(name = "risk", skip_all, fields(opportunity_id = %opportunity.id, block_number = opportunity.block_number))]
+    pub fn calculate_position_size(&self, opportunity: &ArbitrageOpportunity, request: &SizingRequest) -> PositionSize {
+        let optimal_size = optimal_arbitrage_input(&request.buy_pool, &request.sell_pool);
+        if optimal_size.is_zero() {
+            info!("No profitable size for {} -> {}", opportunity.buy_dex, opportunity.sell_dex);
+            return PositionSize {
+                size: U256::zero(),
+                optimal_size,
+                expected_profit: U256::zero(),
+                clamped_by: Some(ClampReason::Unprofitable),
+            };
+        }
+
+        let mut limits = vec![
+            (self.token_max_position_sizes.get(&request.token).copied().unwrap_or(self.max_position_size), ClampReason::MaxPositionSize),
+            (request.available_balance, ClampReason::AvailableBalance),
+            (request.buy_pool.max_input_for_price_impact(self.max_price_impact_bps), ClampReason::PriceImpact),
+        ];
+        if let Some(capacity) = self.remaining_token_capacity(request.token) {
+            limits.push((capacity, ClampReason::TokenExposureCap(request.token)));
+        }
+        for venue in [&opportunity.buy_dex, &opportunity.sell_dex] {
+            if let Some(capacity) = self.remaining_venue_capacity(venue) {
+                limits.push((capacity, ClampReason::VenueExposureCap(venue.clone())));
+            }
+        }
+
+        let mut size = optimal_size;
+        let mut clamped_by = None;
+        for (limit, reason) in limits {
+            if limit < size {
+                size = limit;
+                clamped_by = Some(reason);
+            }
+        }
+
+        let expected_profit = arbitrage_profit(size, &request.buy_pool, &request.sell_pool);
+        match &clamped_by {
+            Some(reason) => info!("Position size {} clamped from {} by {:?}, expected profit {}", size, optimal_size, reason, expected_profit),
+            None => info!("Position size {} at optimum, expected profit {}", size, expected_profit),
+        }
+
+        PositionSize {
+            size,
+            optimal_size,
+            expected_profit,
+            clamped_by,
         }
     }
 
-    pub fn calculate_position_size(&self, opportunity: &ArbitrageOpportunity, market_liquidity: U256) -> U256 {
-        let position_size = market_liquidity.min(self.max_position_size);
-        info!("Calculated position size: {:?}", position_size);
-        position_size
+    /// Shared breaker that high volatility trips; the same one should be given
+    /// to `TradeExecutor` so a trip halts execution.
+    pub fn set_circuit_breaker(&mut self, circuit_breaker: Arc<CircuitBreaker>) {
+        self.circuit_breaker = Some(circuit_breaker);
     }
 
+    /// True when trading must stop, either because `volatility` is too high or a
+    /// stateful breaker has already tripped.
     pub fn check_circuit_breakers(&self, volatility: f64) -> bool {
         if volatility > self.stop_loss_threshold {
             warn!("Circuit breaker triggered due to high volatility: {}", volatility);
-            true
-        } else {
-            false
+            if let Some(circuit_breaker) = &self.circuit_breaker {
+                circuit_breaker.trip(BreakerReason::Volatility { volatility, limit: self.stop_loss_threshold });
+            }
+            return true;
         }
+        self.circuit_breaker.as_ref().is_some_and(|circuit_breaker| circuit_breaker.check().is_err())
     }
 
-    pub fn calculate_market_volatility(&self) -> f64 {
-        // This is a placeholder implementation. In a real-world scenario,
-        // you would calculate volatility based on historical price data.
-        let volatility = 0.05; // 5% volatility
-        info!("Calculated market volatility: {}", volatility);
+    /// Per-sample volatility of `pair`: the highest of the realized volatility
+    /// over each configured window and the EWMA volatility.
+    pub fn calculate_market_volatility(&self, pair: &str) -> f64 {
+        let price_history = self.price_history();
+        let volatility = self.volatility_windows
+            .iter()
+            .map(|window| price_history.realized_volatility(pair, *window))
+            .fold(price_history.ewma_volatility(pair, self.ewma_lambda), f64::max);
+        info!("Calculated market volatility for {}: {}", pair, volatility);
         volatility
     }
 }
\ No newline at end of file
@@ -0,0 +1,140 @@
+use ethers::providers::HttpClientError;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// JSON-RPC error codes providers use for "slow down".
+const RATE_LIMIT_CODES: [i64; 2] = [429, -32005];
+const RATE_LIMIT_MESSAGES: [&str; 3] = ["rate limit", "too many requests", "limit exceeded"];
+
+/// How a failed RPC request should be handled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// The endpoint asked us to slow down.
+    RateLimited,
+    /// Timeouts, dropped connections and non-JSON replies, e.g. from a proxy.
+    Transient,
+    /// The node answered and would answer the same again, e.g. a revert.
+    Fatal,
+}
+
+impl ErrorClass {
+    pub fn is_retryable(self) -> bool {
+        self != ErrorClass::Fatal
+    }
+}
+
+fn mentions_rate_limit(text: &str) -> bool {
+    let text = text.to_lowercase();
+    RATE_LIMIT_MESSAGES.iter().any(|message| text.contains(message))
+}
+
+pub fn classify(e: &HttpClientError) -> ErrorClass {
+    match e {
+        HttpClientError::ReqwestError(e) if e.status().is_some_and(|status| status.as_u16() == 429) => ErrorClass::RateLimited,
+        HttpClientError::ReqwestError(e) if e.is_builder() => ErrorClass::Fatal,
+        HttpClientError::ReqwestError(_) => ErrorClass::Transient,
+        HttpClientError::JsonRpcError(e) if RATE_LIMIT_CODES.contains(&e.code) || mentions_rate_limit(&e.message) => ErrorClass::RateLimited,
+        HttpClientError::JsonRpcError(_) => ErrorClass::Fatal,
+        // Not JSON-RPC at all, typically the body of an HTTP 429
+        HttpClientError::SerdeJson { text, .. } if mentions_rate_limit(text) || text.contains("429") => ErrorClass::RateLimited,
+        // Valid JSON that does not fit the expected result will not fit next time either
+        HttpClientError::SerdeJson { text, .. } if serde_json::from_str::<serde_json::Value>(text).is_ok() => ErrorClass::Fatal,
+        HttpClientError::SerdeJson { .. } => ErrorClass::Transient,
+    }
+}
+
+/// Exponential backoff between retry rounds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// Rounds after the first one; 0 disables retries.
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry `attempt` (from 0): `base_delay * 2^attempt` capped at
+    /// `max_delay`, with the upper half jittered so clients do not retry in step.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self.base_delay.saturating_mul(2u32.saturating_pow(attempt)).min(self.max_delay);
+        ceiling / 2 + ceiling.mul_f64(rand::random::<f64>() / 2.0)
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token bucket holding `burst` requests, refilled at `requests_per_sec`.
+/// Unlimited until a rate is set.
+#[derive(Debug)]
+pub struct TokenBucket {
+    bucket: Mutex<Bucket>,
+}
+
+impl TokenBucket {
+    pub fn new(requests_per_sec: f64, burst: u32) -> Self {
+        let bucket = TokenBucket::unlimited();
+        bucket.set_rate(requests_per_sec, burst);
+        bucket
+    }
+
+    pub fn unlimited() -> Self {
+        TokenBucket {
+            bucket: Mutex::new(Bucket { rate: f64::INFINITY, capacity: f64::INFINITY, tokens: f64::INFINITY, updated: Instant::now() }),
+        }
+    }
+
+    /// Changes the rate, starting from a full bucket.
+    pub fn set_rate(&self, requests_per_sec: f64, burst: u32) {
+        let capacity = burst.max(1) as f64;
+        *self.bucket.lock().unwrap() = Bucket { rate: requests_per_sec, capacity, tokens: capacity, updated: Instant::now() };
+    }
+
+    /// Takes a token, or says how long until one is available.
+    pub fn try_acquire(&self) -> Result<(), Duration> {
+        let mut bucket = self.bucket.lock().unwrap();
+        if bucket.rate.is_infinite() {
+            return Ok(());
+        }
+        let now = Instant::now();
+        bucket.tokens = (bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * bucket.rate).min(bucket.capacity);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / bucket.rate))
+        }
+    }
+
+    /// Waits for a token.
+    pub async fn acquire(&self) {
+        while let Err(wait) = self.try_acquire() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Empties the bucket, e.g. after the endpoint answered 429.
+    pub fn drain(&self) {
+        let mut bucket = self.bucket.lock().unwrap();
+        if bucket.rate.is_finite() {
+            bucket.tokens = 0.0;
+            bucket.updated = Instant::now();
+        }
+    }
+}
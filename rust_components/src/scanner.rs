@@ -0,0 +1,601 @@
+use crate::arbitrage_finder::ArbitrageOpportunity;
+use crate::circuit_breaker::CircuitBreaker;
+use crate::config::{ChainConfig, PoolConfig};
+use crate::control_api::ControlState;
+use crate::inventory::{Inventory, Location};
+use crate::metrics;
+use crate::opportunity_tracker::{OpportunityState, OpportunityTracker};
+use crate::oracle::OracleGuard;
+use crate::paper_executor::{PaperLedger, PaperTradeExecutor};
+use crate::price_monitor::{GetReservesReturn, IUniswapV2Pair};
+use crate::provider_pool::{PooledProvider, ProviderPool};
+use crate::risk_manager::{optimal_arbitrage_input, PoolReserves, PositionSize, RiskManager, SizingRequest};
+use crate::spans::opportunity_span;
+use crate::trade_executor::{ExecutionRefused, Executor, RoutedTrade, TradeObserver};
+use chrono::{DateTime, Utc};
+use ethers::abi::AbiDecode;
+use ethers::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use log::{debug, error, info, warn};
+use tracing::Instrument;
+
+/// Seconds a submitted round trip stays valid.
+const DEADLINE_SECS: u64 = 120;
+
+/// Outcome of reading one pool's reserves for a snapshot.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum ReserveReading {
+    #[default]
+    Fresh,
+    /// The read failed; the reserves are zero and the pool is not traded.
+    Missing { error: String },
+}
+
+/// Reserves of one configured pool, oriented by its `BASE/QUOTE` pair.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PoolSnapshot {
+    pub dex: String,
+    pub pair: String,
+    pub reserve_base: U256,
+    pub reserve_quote: U256,
+    /// Recordings from before failed reads were kept only hold fresh pools.
+    #[serde(default)]
+    pub reading: ReserveReading,
+}
+
+impl PoolSnapshot {
+    pub fn is_fresh(&self) -> bool {
+        self.reading == ReserveReading::Fresh
+    }
+}
+
+/// Every pool of a chain read at one block; one JSON line in a recording.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MarketSnapshot {
+    pub chain: String,
+    pub block_number: Option<u64>,
+    pub at: DateTime<Utc>,
+    pub pools: Vec<PoolSnapshot>,
+}
+
+/// Orients a pair's `getReserves` by its `BASE/QUOTE` tokens.
+fn pool_snapshot(chain: &ChainConfig, pool: &PoolConfig, pair: &str, reserve0: u128, reserve1: u128) -> Result<PoolSnapshot, String> {
+    let (base, quote) = chain.pair_tokens(pair).ok_or_else(|| format!("Unknown tokens in pair {}", pair))?;
+    // Uniswap V2 orders a pair's tokens by address
+    let (reserve_base, reserve_quote) = if base.address < quote.address { (reserve0, reserve1) } else { (reserve1, reserve0) };
+    Ok(PoolSnapshot {
+        dex: pool.dex.clone(),
+        pair: pair.to_string(),
+        reserve_base: U256::from(reserve_base),
+        reserve_quote: U256::from(reserve_quote),
+        reading: ReserveReading::Fresh,
+    })
+}
+
+/// Name a pool's reserves are reported under; chains often share DEX names.
+fn pool_venue(chain: &ChainConfig, pool: &PoolConfig) -> String {
+    format!("{}/{}", chain.name, pool.dex)
+}
+
+/// Reads the reserves of every pool with a `pair` at the latest block. Pools
+/// that fail to answer are kept as `ReserveReading::Missing`.
+pub async fn fetch_snapshot(provider: Arc<PooledProvider>, chain: &ChainConfig) -> Result<MarketSnapshot, Box<dyn std::error::Error>> {
+    let block_number = provider.get_block_number().await?.as_u64();
+    let mut pools = Vec::new();
+    for pool in &chain.pools {
+        let Some(pair) = &pool.pair else {
+            continue;
+        };
+
+        let started = Instant::now();
+        let reserves = IUniswapV2Pair::new(pool.address, provider.clone()).get_reserves().block(block_number).call().await;
+        metrics::observe_rpc_latency("getReserves", started.elapsed());
+        let (reserve0, reserve1, _) = match reserves {
+            Ok(reserves) => reserves,
+            Err(e) => {
+                error!("Error getting reserves for {}: {:?}", pool.dex, e);
+                let reading = ReserveReading::Missing { error: e.to_string() };
+                pools.push(PoolSnapshot { dex: pool.dex.clone(), pair: pair.clone(), reserve_base: U256::zero(), reserve_quote: U256::zero(), reading });
+                continue;
+            }
+        };
+        metrics::record_book_update(&pool_venue(chain, pool));
+        pools.push(pool_snapshot(chain, pool, pair, reserve0, reserve1)?);
+    }
+    Ok(MarketSnapshot { chain: chain.name.clone(), block_number: Some(block_number), at: Utc::now(), pools })
+}
+
+/// Like `fetch_snapshot`, but every pool's reserves must match on `quorum`
+/// endpoints at the same block. Fails rather than leaving a pool out.
+pub async fn fetch_snapshot_quorum(provider: Arc<PooledProvider>, chain: &ChainConfig, quorum: usize) -> Result<MarketSnapshot, Box<dyn std::error::Error>> {
+    let pool_client: &ProviderPool = (*provider).as_ref();
+    let block_number = pool_client.quorum_block_number(quorum).await?;
+    let mut pools = Vec::new();
+    for pool in &chain.pools {
+        let Some(pair) = &pool.pair else {
+            continue;
+        };
+
+        let started = Instant::now();
+        let call = IUniswapV2Pair::new(pool.address, provider.clone()).get_reserves();
+        let read = pool_client.quorum_call_at(&call.tx, block_number, quorum).await;
+        metrics::observe_rpc_latency("getReserves", started.elapsed());
+        let read = read.map_err(|e| format!("Reserves of {} on {}: {}", pool.dex, chain.name, e))?;
+        let reserves = GetReservesReturn::decode(&read.value)?;
+        metrics::record_book_update(&pool_venue(chain, pool));
+        pools.push(pool_snapshot(chain, pool, pair, reserves.reserve_0, reserves.reserve_1)?);
+    }
+    Ok(MarketSnapshot { chain: chain.name.clone(), block_number: Some(block_number), at: Utc::now(), pools })
+}
+
+pub fn read_snapshots(path: &Path) -> Result<Vec<MarketSnapshot>, Box<dyn std::error::Error>> {
+    let mut snapshots = Vec::new();
+    for (index, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let snapshot = serde_json::from_str(&line).map_err(|e| format!("{} line {}: {}", path.display(), index + 1, e))?;
+        snapshots.push(snapshot);
+    }
+    Ok(snapshots)
+}
+
+/// Appends snapshots as JSON lines for `replay` and `backtest`.
+pub struct SnapshotRecorder {
+    file: Mutex<File>,
+}
+
+impl SnapshotRecorder {
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(SnapshotRecorder { file: Mutex::new(file) })
+    }
+
+    pub fn record(&self, snapshot: &MarketSnapshot) -> std::io::Result<()> {
+        let line = serde_json::to_string(snapshot)?;
+        writeln!(self.file.lock().unwrap(), "{}", line)
+    }
+}
+
+/// Converts a token amount to whole units.
+pub fn to_units(amount: U256, decimals: u8) -> f64 {
+    amount.to_string().parse::<f64>().unwrap_or(f64::MAX) / 10f64.powi(decimals as i32)
+}
+
+/// A round trip from the quote token through the base token and back: bought
+/// on `opportunity.buy_dex` through `buy_router`, sold on `opportunity.sell_dex`
+/// through `sell_router`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Candidate {
+    pub opportunity: ArbitrageOpportunity,
+    pub base: Address,
+    pub quote: Address,
+    pub buy_router: Address,
+    pub sell_router: Address,
+    pub buy_pool: PoolReserves,
+    pub sell_pool: PoolReserves,
+    pub position: PositionSize,
+}
+
+impl Candidate {
+    pub fn path(&self) -> Vec<Address> {
+        vec![self.quote, self.base, self.quote]
+    }
+
+    /// Router of each hop of `path`.
+    pub fn routers(&self) -> Vec<Address> {
+        vec![self.buy_router, self.sell_router]
+    }
+
+    /// Gives the paper executor this candidate's pools under their routers, so
+    /// each hop trades where it would live.
+    pub fn set_paper_reserves(&self, paper: &PaperTradeExecutor) {
+        paper.set_pool_reserves(self.buy_router, self.quote, self.base, self.buy_pool.reserve_in, self.buy_pool.reserve_out);
+        paper.set_pool_reserves(self.sell_router, self.base, self.quote, self.sell_pool.reserve_in, self.sell_pool.reserve_out);
+    }
+}
+
+/// Selling `amount_in` of the base token into one pool.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Quote {
+    pub dex: String,
+    pub amount_out: f64,
+    /// Quote tokens received per base token.
+    pub price: f64,
+    /// Execution price below the pool's spot price, fee included.
+    pub slippage_bps: f64,
+}
+
+/// Finds and sizes two-pool round trips on one chain.
+pub struct Scanner {
+    chain: ChainConfig,
+    risk_manager: RiskManager,
+    min_profit: f64,
+    tracker: Option<Arc<OpportunityTracker>>,
+    control: Option<Arc<ControlState>>,
+    inventory: Option<(Arc<Mutex<Inventory>>, Location)>,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+}
+
+impl Scanner {
+    /// `min_profit` is in quote token units.
+    pub fn new(chain: ChainConfig, risk_manager: RiskManager, min_profit: f64) -> Self {
+        Scanner { chain, risk_manager, min_profit, tracker: None, control: None, inventory: None, circuit_breaker: None }
+    }
+
+    pub fn chain(&self) -> &ChainConfig {
+        &self.chain
+    }
+
+    pub fn min_profit(&self) -> f64 {
+        self.min_profit
+    }
+
+    pub fn risk_manager(&self) -> &RiskManager {
+        &self.risk_manager
+    }
+
+    /// Follows every candidate through its lifecycle in `tracker`.
+    pub fn set_tracker(&mut self, tracker: Arc<OpportunityTracker>) {
+        self.tracker = Some(tracker);
+    }
+
+    /// Reports pool prices and candidates to the control API and takes the
+    /// minimum profit from its thresholds.
+    pub fn set_control(&mut self, control: Arc<ControlState>) {
+        self.control = Some(control);
+    }
+
+    /// Sizes trades to the balance `inventory` holds at `location`, and prices
+    /// it from every scan. Without an inventory, sizes are bounded only by the
+    /// risk limits.
+    pub fn set_inventory(&mut self, inventory: Arc<Mutex<Inventory>>, location: Location) {
+        self.inventory = Some((inventory, location));
+    }
+
+    /// Rejects candidates whose pool prices stray from the Chainlink feeds.
+    pub fn set_oracle_guard(&mut self, oracle_guard: OracleGuard) {
+        self.risk_manager.set_oracle_guard(oracle_guard);
+    }
+
+    /// Rejects every candidate while `circuit_breaker` is tripped, and trips it
+    /// when a pair's volatility goes over the stop loss threshold.
+    pub fn set_circuit_breaker(&mut self, circuit_breaker: Arc<CircuitBreaker>) {
+        self.risk_manager.set_circuit_breaker(circuit_breaker.clone());
+        self.circuit_breaker = Some(circuit_breaker);
+    }
+
+    /// Whether `snapshot` is older than the control API's `max_book_age_secs`.
+    /// Without a control API, e.g. replaying a recording, nothing is stale.
+    fn is_stale(&self, snapshot: &MarketSnapshot) -> bool {
+        let Some(control) = &self.control else {
+            return false;
+        };
+        let max_age = control.thresholds().max_book_age_secs as i64;
+        let age = (Utc::now() - snapshot.at).num_seconds();
+        if age > max_age {
+            warn!("Not trading {} pools read {}s ago, more than {}s", self.chain.name, age, max_age);
+            return true;
+        }
+        false
+    }
+
+    fn min_profit_threshold(&self) -> f64 {
+        self.control.as_ref().map_or(self.min_profit, |control| control.thresholds().min_profit_for(&self.chain.name))
+    }
+
+    /// Sizes buying on `buy` and selling on `sell`, if that clears `min_profit`,
+    /// as opportunity `id` or a new one. Both pools need their own router: one
+    /// router would route both hops through the same pair.
+    fn evaluate(&self, snapshot: &MarketSnapshot, buy: &PoolSnapshot, sell: &PoolSnapshot, min_profit: f64, id: Option<&str>) -> Option<Candidate> {
+        let (base, quote) = self.chain.pair_tokens(&buy.pair)?;
+        let (buy_router, sell_router) = match (self.chain.router(&buy.dex), self.chain.router(&sell.dex)) {
+            (Some(buy_router), Some(sell_router)) if buy_router != sell_router => (buy_router, sell_router),
+            _ => {
+                debug!("No separate routers for {} and {} on {}, skipping", buy.dex, sell.dex, self.chain.name);
+                return None;
+            }
+        };
+        let buy_pool = PoolReserves::new(buy.reserve_quote, buy.reserve_base);
+        let sell_pool = PoolReserves::new(sell.reserve_base, sell.reserve_quote);
+        if optimal_arbitrage_input(&buy_pool, &sell_pool).is_zero() {
+            return None;
+        }
+
+        let mut opportunity = ArbitrageOpportunity::new(&buy.dex, &sell.dex, 0.0).with_pair(&buy.pair).with_chain_id(self.chain.chain_id);
+        if let Some(block_number) = snapshot.block_number {
+            opportunity = opportunity.with_block_number(block_number);
+        }
+        if let Some(id) = id {
+            opportunity.id = id.to_string();
+        }
+        let request = match &self.inventory {
+            Some((inventory, location)) => SizingRequest::from_inventory(&inventory.lock().unwrap(), location, quote.address, buy_pool, sell_pool),
+            None => SizingRequest { token: quote.address, buy_pool, sell_pool, available_balance: U256::MAX },
+        };
+        let position = opportunity_span(&opportunity).in_scope(|| self.risk_manager.calculate_position_size(&opportunity, &request));
+        opportunity.profit = to_units(position.expected_profit, quote.decimals);
+        if position.size.is_zero() || opportunity.profit <= 0.0 || opportunity.profit < min_profit {
+            return None;
+        }
+        Some(Candidate { opportunity, base: base.address, quote: quote.address, buy_router, sell_router, buy_pool, sell_pool, position })
+    }
+
+    /// Profitable candidates in `snapshot`, most profitable first; none when the
+    /// snapshot is stale. Each pair's mean pool price goes into the price history
+    /// volatility is measured on.
+    pub fn scan(&self, snapshot: &MarketSnapshot) -> Vec<Candidate> {
+        if self.is_stale(snapshot) {
+            return Vec::new();
+        }
+        let min_profit = self.min_profit_threshold();
+        let mut candidates = Vec::new();
+        let mut pair_prices: HashMap<String, Vec<f64>> = HashMap::new();
+
+        for buy in &snapshot.pools {
+            if let ReserveReading::Missing { error } = &buy.reading {
+                warn!("Skipping pool {} {} whose reserves could not be read: {}", buy.dex, buy.pair, error);
+                continue;
+            }
+            let Some((base, quote)) = self.chain.pair_tokens(&buy.pair) else {
+                warn!("Skipping pool {} with unknown pair {}", buy.dex, buy.pair);
+                continue;
+            };
+            // A new or drained pool has no price, and one bad reading poisons the volatility
+            let price = to_units(buy.reserve_quote, quote.decimals) / to_units(buy.reserve_base, base.decimals);
+            if buy.reserve_base.is_zero() || buy.reserve_quote.is_zero() || !price.is_finite() {
+                warn!("Skipping pool {} {} with empty reserves", buy.dex, buy.pair);
+                continue;
+            }
+            pair_prices.entry(buy.pair.clone()).or_default().push(price);
+            if let Some(control) = &self.control {
+                // Chains share one control API, and often the same DEX names
+                control.record_pool_price(&format!("{}/{}", self.chain.name, buy.dex), price);
+            }
+
+            for sell in snapshot.pools.iter().filter(|sell| sell.pair == buy.pair && sell.dex != buy.dex && sell.is_fresh()) {
+                let Some(candidate) = self.evaluate(snapshot, buy, sell, min_profit, None) else {
+                    continue;
+                };
+                let opportunity = &candidate.opportunity;
+                info!("Arbitrage opportunity found: Buy {} on {}, Sell on {}, Profit: {}", buy.pair, buy.dex, sell.dex, opportunity.profit);
+                metrics::opportunity_found();
+                if let Some(tracker) = &self.tracker {
+                    tracker.detect(opportunity.clone());
+                }
+                if let Some(control) = &self.control {
+                    control.record_opportunity(opportunity);
+                }
+                candidates.push(candidate);
+            }
+        }
+
+        let mean_prices = pair_prices.into_iter().map(|(pair, prices)| (pair, prices.iter().sum::<f64>() / prices.len() as f64)).collect();
+        self.risk_manager.record_price_snapshot(&mean_prices, snapshot.at.timestamp() as u64);
+        if let Some((inventory, _)) = &self.inventory {
+            self.mark_inventory(&mut inventory.lock().unwrap(), &mean_prices);
+        }
+
+        candidates.sort_by(|a, b| b.opportunity.profit.total_cmp(&a.opportunity.profit));
+        candidates
+    }
+
+    /// Prices `inventory` in the chain's quote token from each pair's mean pool
+    /// price. Pairs quoted in another token are left unpriced.
+    fn mark_inventory(&self, inventory: &mut Inventory, mean_prices: &HashMap<String, f64>) {
+        let Some(quote_token) = self.chain.quote_token() else {
+            return;
+        };
+        inventory.set_price(quote_token.address, 1.0);
+        for (pair, price) in mean_prices {
+            if let Some((base, quote)) = self.chain.pair_tokens(pair) {
+                if quote.address == quote_token.address {
+                    inventory.set_price(base.address, *price);
+                }
+            }
+        }
+    }
+
+    /// Re-sizes `candidate` against a fresher `snapshot`, e.g. one read with
+    /// quorum just before execution. Keeps the opportunity id so the tracker
+    /// still follows it; `None` once its pools stop clearing the minimum profit.
+    pub fn confirm(&self, candidate: &Candidate, snapshot: &MarketSnapshot) -> Option<Candidate> {
+        if self.is_stale(snapshot) {
+            return None;
+        }
+        let opportunity = &candidate.opportunity;
+        let find = |dex: &str| snapshot.pools.iter().find(|pool| pool.dex == dex && opportunity.pair.as_ref() == Some(&pool.pair) && pool.is_fresh());
+        let (buy, sell) = (find(&opportunity.buy_dex)?, find(&opportunity.sell_dex)?);
+        self.evaluate(snapshot, buy, sell, self.min_profit_threshold(), Some(&opportunity.id))
+    }
+
+    /// What selling `amount_in` base tokens of `pair` returns on each pool.
+    pub fn quote(&self, snapshot: &MarketSnapshot, pair: &str, amount_in: U256) -> Result<Vec<Quote>, String> {
+        let (base, quote) = self.chain.pair_tokens(pair).ok_or_else(|| format!("Pair {} is not configured on {}", pair, self.chain.name))?;
+        let amount_in_units = to_units(amount_in, base.decimals);
+        let mut quotes: Vec<Quote> = snapshot
+            .pools
+            .iter()
+            .filter(|pool| pool.pair == pair && pool.is_fresh())
+            .map(|pool| {
+                let amount_out = to_units(PoolReserves::new(pool.reserve_base, pool.reserve_quote).get_amount_out(amount_in), quote.decimals);
+                let spot = to_units(pool.reserve_quote, quote.decimals) / to_units(pool.reserve_base, base.decimals);
+                let price = amount_out / amount_in_units;
+                Quote { dex: pool.dex.clone(), amount_out, price, slippage_bps: (1.0 - price / spot) * 10_000.0 }
+            })
+            .collect();
+        quotes.sort_by(|a, b| b.amount_out.total_cmp(&a.amount_out));
+        Ok(quotes)
+    }
+
+    fn transition(&self, candidate: &Candidate, state: OpportunityState) {
+        if let Some(tracker) = &self.tracker {
+            if let Err(e) = tracker.transition(&candidate.opportunity.id, state) {
+                warn!("{}", e);
+            }
+        }
+    }
+
+    /// Why `candidate` must not be traded right now, if anything.
+    async fn approve(&self, candidate: &Candidate) -> Result<(), String> {
+        if self.control.as_ref().is_some_and(|control| control.is_paused()) {
+            return Err(ExecutionRefused::Paused.to_string());
+        }
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            circuit_breaker.check().map_err(|reason| ExecutionRefused::CircuitBreaker(reason).to_string())?;
+        }
+        if let Some((inventory, location)) = &self.inventory {
+            let available = inventory.lock().unwrap().available(location, candidate.quote);
+            if available < candidate.position.size {
+                return Err(format!("Insufficient balance at {}: available {}, required {}", location, available, candidate.position.size));
+            }
+        }
+        let pair = candidate.opportunity.pair.as_deref().ok_or("Candidate has no pair")?;
+        let volatility = self.risk_manager.calculate_market_volatility(pair);
+        if self.risk_manager.check_circuit_breakers(volatility) {
+            return Err(format!("Volatility of {} is {:.4}, over the stop loss threshold", pair, volatility));
+        }
+        let (base, quote) = self.chain.pair_tokens(pair).ok_or("Candidate has no configured pair")?;
+        let price = |reserve_base: U256, reserve_quote: U256| to_units(reserve_quote, quote.decimals) / to_units(reserve_base, base.decimals);
+        let prices = [
+            price(candidate.buy_pool.reserve_out, candidate.buy_pool.reserve_in),
+            price(candidate.sell_pool.reserve_in, candidate.sell_pool.reserve_out),
+        ];
+        self.risk_manager.check_oracle_prices(base.address, quote.address, &prices).await.map_err(|e| e.to_string())
+    }
+
+    /// Executes `candidate` along its path, each hop on its own router, requiring
+    /// at least the input back, once the risk checks pass. Live executors
+    /// simulate before sending; the tracker sees each stage as the executor
+    /// reaches it.
+    pub async fn execute(&self, executor: &dyn Executor, candidate: &Candidate, account: Address) -> Result<TransactionReceipt, Box<dyn std::error::Error>> {
+        self.execute_in_span(executor, candidate, account).instrument(opportunity_span(&candidate.opportunity)).await
+    }
+
+    async fn execute_in_span(&self, executor: &dyn Executor, candidate: &Candidate, account: Address) -> Result<TransactionReceipt, Box<dyn std::error::Error>> {
+        if let Err(reason) = self.approve(candidate).await {
+            warn!("Rejecting opportunity {}: {}", candidate.opportunity.id, reason);
+            self.transition(candidate, OpportunityState::RiskRejected(reason.clone()));
+            return Err(reason.into());
+        }
+        self.transition(candidate, OpportunityState::RiskApproved);
+        let deadline = U256::from(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() + DEADLINE_SECS);
+        let size = candidate.position.size;
+        let trade = RoutedTrade { routers: candidate.routers(), path: candidate.path(), amount: size, min_amount_out: size, deadline, account };
+        let observer = CandidateObserver { scanner: self, candidate, simulated: AtomicBool::new(false), submitted: AtomicBool::new(false) };
+        let result = executor.execute_routed_trade(trade, &observer).await;
+        if observer.submitted.load(Ordering::SeqCst) {
+            // Settled, or no longer followed
+            self.risk_manager.release_exposure(&candidate.opportunity, candidate.quote, size);
+        }
+        let receipt = match result {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                let state = if e.is::<ExecutionRefused>() {
+                    OpportunityState::RiskRejected(e.to_string())
+                } else if observer.submitted.load(Ordering::SeqCst) {
+                    OpportunityState::Dropped(e.to_string())
+                } else if observer.simulated.load(Ordering::SeqCst) {
+                    OpportunityState::SendFailed(e.to_string())
+                } else {
+                    OpportunityState::SimulationFailed(e.to_string())
+                };
+                self.transition(candidate, state);
+                return Err(e);
+            }
+        };
+
+        let hash = receipt.transaction_hash;
+        if receipt.status == Some(U64::one()) {
+            self.transition(candidate, OpportunityState::Confirmed(hash));
+        } else {
+            self.transition(candidate, OpportunityState::Reverted(hash));
+        }
+        Ok(receipt)
+    }
+}
+
+/// Moves a candidate through the tracker as its trade passes each stage, and
+/// remembers how far it got so a failure lands in the right state.
+struct CandidateObserver<'a> {
+    scanner: &'a Scanner,
+    candidate: &'a Candidate,
+    simulated: AtomicBool,
+    submitted: AtomicBool,
+}
+
+impl TradeObserver for CandidateObserver<'_> {
+    fn simulated(&self) {
+        self.simulated.store(true, Ordering::SeqCst);
+        self.scanner.transition(self.candidate, OpportunityState::Simulated);
+    }
+
+    /// Holds the position against the exposure caps until the trade settles.
+    fn submitted(&self, hash: H256) {
+        if !self.submitted.swap(true, Ordering::SeqCst) {
+            let candidate = self.candidate;
+            self.scanner.risk_manager.add_exposure(&candidate.opportunity, candidate.quote, candidate.position.size);
+        }
+        self.scanner.transition(self.candidate, OpportunityState::Submitted(hash));
+    }
+}
+
+/// Totals of a `paper`, `replay` or `backtest` session.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct SessionReport {
+    pub min_profit: f64,
+    pub snapshots: usize,
+    pub opportunities: usize,
+    pub trades: usize,
+    pub reverted: usize,
+    /// Sum of successful fills in their quote token's units.
+    pub realized_pnl: f64,
+    /// Native token spent on gas, in whole units.
+    pub gas_cost: f64,
+}
+
+impl SessionReport {
+    pub fn new(min_profit: f64) -> Self {
+        SessionReport { min_profit, ..Default::default() }
+    }
+
+    pub fn record_snapshot(&mut self, candidates: &[Candidate]) {
+        self.snapshots += 1;
+        self.opportunities += candidates.len();
+    }
+
+    /// Takes trade counts, PnL and gas from the paper `ledger`.
+    pub fn record_ledger(&mut self, ledger: &PaperLedger, chain: &ChainConfig) {
+        let decimals = |token: Address| chain.tokens.iter().find(|t| t.address == token).map_or(18, |t| t.decimals);
+        self.trades = ledger.fills().len();
+        self.reverted = ledger.fills().iter().filter(|fill| !fill.success).count();
+        self.realized_pnl = ledger
+            .fills()
+            .iter()
+            .map(|fill| {
+                let pnl = to_units(fill.pnl.unsigned_abs(), decimals(fill.path[0]));
+                if fill.pnl.is_negative() { -pnl } else { pnl }
+            })
+            .fold(0.0, |total, pnl| total + pnl);
+        self.gas_cost = to_units(ledger.total_gas_cost(), 18);
+    }
+}
+
+impl fmt::Display for SessionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "min profit {}: {} snapshot(s), {} opportunity(ies), {} trade(s), {} reverted, realized PnL {:.6}, gas {:.6}",
+            self.min_profit, self.snapshots, self.opportunities, self.trades, self.reverted, self.realized_pnl, self.gas_cost
+        )
+    }
+}
@@ -0,0 +1,226 @@
+use crate::logger;
+use async_trait::async_trait;
+use ethers::prelude::*;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SignerError {
+    #[error("Wallet error: {0}")]
+    Wallet(#[from] WalletError),
+    #[error("Environment variable {0} is not set")]
+    MissingEnvVar(String),
+    #[error("Invalid private key from {0}")]
+    InvalidKey(String),
+    #[error("Failed to read key file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Remote signer request failed: {0}")]
+    Remote(String),
+    #[error("No signer for account {0:?}")]
+    UnknownAccount(Address),
+    #[error("Signature recovers to {recovered:?}, expected {expected:?}")]
+    WrongSigner { expected: Address, recovered: Address },
+}
+
+/// Object-safe signing interface shared by every key backend.
+#[async_trait]
+pub trait TransactionSigner: Send + Sync {
+    fn address(&self) -> Address;
+
+    fn chain_id(&self) -> u64;
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, SignerError>;
+}
+
+/// Signs with a key held in memory. The key is registered with `logger` so it is
+/// redacted should it ever reach a log line.
+pub struct LocalSigner {
+    wallet: LocalWallet,
+}
+
+impl LocalSigner {
+    /// Decrypts an encrypted JSON keystore with its passphrase.
+    pub fn from_keystore<P: AsRef<Path>>(path: P, passphrase: &str, chain_id: u64) -> Result<Self, SignerError> {
+        let wallet = LocalWallet::decrypt_keystore(path.as_ref(), passphrase)?;
+        logger::register_secret(&ethers::utils::hex::encode(wallet.signer().to_bytes()));
+        info!("Loaded keystore signer {:?}", wallet.address());
+        Ok(LocalSigner { wallet: wallet.with_chain_id(chain_id) })
+    }
+
+    /// Reads a hex private key from an environment variable. Meant for development.
+    pub fn from_env(var: &str, chain_id: u64) -> Result<Self, SignerError> {
+        let key = std::env::var(var).map_err(|_| SignerError::MissingEnvVar(var.to_string()))?;
+        Self::from_private_key(&key, var, chain_id)
+    }
+
+    /// Reads a hex private key from a file. Meant for development.
+    pub fn from_file<P: AsRef<Path>>(path: P, chain_id: u64) -> Result<Self, SignerError> {
+        let key = std::fs::read_to_string(path.as_ref())?;
+        Self::from_private_key(&key, &path.as_ref().display().to_string(), chain_id)
+    }
+
+    fn from_private_key(key: &str, source: &str, chain_id: u64) -> Result<Self, SignerError> {
+        let key = key.trim();
+        logger::register_secret(key);
+        let wallet: LocalWallet = key.parse().map_err(|_| SignerError::InvalidKey(source.to_string()))?;
+        info!("Loaded signer {:?} from {}", wallet.address(), source);
+        Ok(LocalSigner { wallet: wallet.with_chain_id(chain_id) })
+    }
+}
+
+impl fmt::Debug for LocalSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalSigner").field("address", &self.wallet.address()).finish()
+    }
+}
+
+#[async_trait]
+impl TransactionSigner for LocalSigner {
+    fn address(&self) -> Address {
+        self.wallet.address()
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.wallet.chain_id()
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, SignerError> {
+        Ok(self.wallet.sign_transaction_sync(tx)?)
+    }
+}
+
+#[derive(Serialize)]
+struct RemoteSignRequest<'a> {
+    address: Address,
+    chain_id: u64,
+    transaction: &'a TypedTransaction,
+}
+
+#[derive(Deserialize)]
+struct RemoteSignResponse {
+    signature: Signature,
+}
+
+/// Delegates signing to a remote service that holds the key.
+///
+/// Protocol: `POST {url}/sign` with `{"address", "chain_id", "transaction"}` as JSON
+/// and an optional bearer token; the service answers `{"signature": {"r", "s", "v"}}`
+/// with the EIP-155 `v` for the transaction's chain.
+pub struct RemoteSigner {
+    url: String,
+    address: Address,
+    chain_id: u64,
+    auth_token: Option<String>,
+    client: reqwest::Client,
+}
+
+impl RemoteSigner {
+    pub fn new(url: &str, address: Address, chain_id: u64, auth_token: Option<String>) -> Self {
+        RemoteSigner {
+            url: url.trim_end_matches('/').to_string(),
+            address,
+            chain_id,
+            auth_token,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+impl fmt::Debug for RemoteSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteSigner")
+            .field("url", &self.url)
+            .field("address", &self.address)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl TransactionSigner for RemoteSigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, SignerError> {
+        let request = RemoteSignRequest {
+            address: self.address,
+            chain_id: self.chain_id,
+            transaction: tx,
+        };
+        let mut builder = self.client.post(format!("{}/sign", self.url)).json(&request);
+        if let Some(token) = &self.auth_token {
+            builder = builder.bearer_auth(token);
+        }
+
+        let response = builder.send().await.map_err(|e| SignerError::Remote(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(SignerError::Remote(format!("HTTP {}", response.status())));
+        }
+        let body: RemoteSignResponse = response.json().await.map_err(|e| SignerError::Remote(e.to_string()))?;
+
+        // The service is not trusted to have used the right key
+        let mut tx = tx.clone();
+        if tx.chain_id().is_none() {
+            tx.set_chain_id(self.chain_id);
+        }
+        let recovered = body.signature.recover(tx.sighash()).map_err(|e| SignerError::Remote(e.to_string()))?;
+        if recovered != self.address {
+            return Err(SignerError::WrongSigner { expected: self.address, recovered });
+        }
+        Ok(body.signature)
+    }
+}
+
+/// Set of accounts the bot can send from, so trades can run in parallel on
+/// separate nonces.
+#[derive(Default)]
+pub struct SignerPool {
+    signers: Vec<Arc<dyn TransactionSigner>>,
+    next: AtomicUsize,
+}
+
+impl SignerPool {
+    pub fn new() -> Self {
+        SignerPool::default()
+    }
+
+    pub fn add(&mut self, signer: Arc<dyn TransactionSigner>) {
+        info!("Added signer {:?}", signer.address());
+        self.signers.push(signer);
+    }
+
+    pub fn addresses(&self) -> Vec<Address> {
+        self.signers.iter().map(|signer| signer.address()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.signers.is_empty()
+    }
+
+    pub fn get(&self, address: Address) -> Result<Arc<dyn TransactionSigner>, SignerError> {
+        self.signers
+            .iter()
+            .find(|signer| signer.address() == address)
+            .cloned()
+            .ok_or(SignerError::UnknownAccount(address))
+    }
+
+    /// Picks the accounts in turn.
+    pub fn next_signer(&self) -> Option<Arc<dyn TransactionSigner>> {
+        if self.signers.is_empty() {
+            return None;
+        }
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.signers.len();
+        Some(self.signers[index].clone())
+    }
+}
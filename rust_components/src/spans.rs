@@ -0,0 +1,195 @@
+use crate::arbitrage_finder::ArbitrageOpportunity;
+use chrono::{DateTime, Utc};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{info_span, Span, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::{LookupSpan, Registry};
+
+/// Only spans from this crate are exported; dependencies such as ethers have their own.
+const EXPORTED_TARGET: &str = "rust_components";
+
+/// Root span for one attempt at an opportunity. Stage spans created inside it,
+/// such as `risk` or `send_and_record`, inherit its id and block number.
+pub fn opportunity_span(opportunity: &ArbitrageOpportunity) -> Span {
+    info_span!("opportunity", opportunity_id = %opportunity.id, block_number = opportunity.block_number)
+}
+
+/// Opportunity the current thread is working on, from the innermost entered span.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpanContext {
+    pub stage: &'static str,
+    pub opportunity_id: Option<String>,
+    pub block_number: Option<u64>,
+}
+
+thread_local! {
+    static ENTERED: RefCell<Vec<(Id, SpanContext)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Context of the innermost exported span entered on this thread, used by the
+/// logger to tag log lines with the opportunity they belong to.
+pub fn current_context() -> Option<SpanContext> {
+    ENTERED.with(|entered| entered.borrow().last().map(|(_, context)| context.clone()))
+}
+
+#[derive(Default)]
+struct Fields(BTreeMap<String, String>);
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+struct SpanTiming {
+    fields: Fields,
+    started_at: DateTime<Utc>,
+    start: Instant,
+}
+
+/// `tracing` layer that writes every finished span as a JSON line with its
+/// stage, opportunity id, block number, start time and duration.
+pub struct SpanExporter {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl SpanExporter {
+    pub fn to_writer(writer: Box<dyn Write + Send>) -> Self {
+        SpanExporter { writer: Mutex::new(writer) }
+    }
+
+    /// Appends spans to `path`.
+    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::to_writer(Box::new(file)))
+    }
+
+    /// Streams spans as newline-delimited JSON over TCP to a local collector,
+    /// standing in for an OTLP endpoint.
+    pub fn to_collector(addr: &str) -> io::Result<Self> {
+        Ok(Self::to_writer(Box::new(TcpStream::connect(addr)?)))
+    }
+
+    fn context<S>(&self, id: &Id, ctx: &Context<'_, S>) -> Option<SpanContext>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let span = ctx.span(id)?;
+        let mut context = SpanContext { stage: span.name(), ..Default::default() };
+        for ancestor in span.scope() {
+            let extensions = ancestor.extensions();
+            let Some(timing) = extensions.get::<SpanTiming>() else {
+                continue;
+            };
+            if context.opportunity_id.is_none() {
+                context.opportunity_id = timing.fields.0.get("opportunity_id").cloned();
+            }
+            if context.block_number.is_none() {
+                context.block_number = timing.fields.0.get("block_number").and_then(|block| block.parse().ok());
+            }
+        }
+        Some(context)
+    }
+}
+
+fn exported(target: &str) -> bool {
+    target == EXPORTED_TARGET || target.starts_with("rust_components::")
+}
+
+impl<S> Layer<S> for SpanExporter
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if !exported(attrs.metadata().target()) {
+            return;
+        }
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        span.extensions_mut().insert(SpanTiming { fields, started_at: Utc::now(), start: Instant::now() });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        if let Some(timing) = span.extensions_mut().get_mut::<SpanTiming>() {
+            values.record(&mut timing.fields);
+        }
+        ENTERED.with(|entered| {
+            for (entered_id, context) in entered.borrow_mut().iter_mut() {
+                if let Some(updated) = self.context(entered_id, &ctx) {
+                    *context = updated;
+                }
+            }
+        });
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        if !ctx.metadata(id).is_some_and(|metadata| exported(metadata.target())) {
+            return;
+        }
+        if let Some(context) = self.context(id, &ctx) {
+            ENTERED.with(|entered| entered.borrow_mut().push((id.clone(), context)));
+        }
+    }
+
+    fn on_exit(&self, id: &Id, _ctx: Context<'_, S>) {
+        ENTERED.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            if let Some(position) = entered.iter().rposition(|(entered_id, _)| entered_id == id) {
+                entered.remove(position);
+            }
+        });
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(context) = self.context(&id, &ctx) else {
+            return;
+        };
+        let extensions = span.extensions();
+        let Some(timing) = extensions.get::<SpanTiming>() else {
+            return;
+        };
+
+        let line = serde_json::json!({
+            "stage": context.stage,
+            "opportunity_id": context.opportunity_id,
+            "block_number": context.block_number,
+            "parent": span.parent().map(|parent| parent.name()),
+            "started_at": timing.started_at.to_rfc3339(),
+            "duration_us": timing.start.elapsed().as_micros() as u64,
+            "fields": timing.fields.0,
+        });
+        let mut writer = self.writer.lock().unwrap();
+        if let Err(e) = writeln!(writer, "{}", line).and_then(|()| writer.flush()) {
+            eprintln!("Failed to export span {}: {}", context.stage, e);
+        }
+    }
+}
+
+/// Installs `exporter` as the global `tracing` subscriber.
+pub fn init_exporter(exporter: SpanExporter) -> Result<(), Box<dyn std::error::Error>> {
+    tracing::subscriber::set_global_default(Registry::default().with(exporter))?;
+    Ok(())
+}
@@ -1,72 +1,737 @@
+use crate::alerts::{Alert, AlertDispatcher, Severity};
+use crate::arbitrage_bot_contract::{decode_custom_error, ArbitrageBotContract, BotReceipt};
+use crate::circuit_breaker::{BreakerReason, CircuitBreaker};
+use crate::control_api::ControlState;
+use crate::flash_loan::{check_flash_loan_profit, flash_loan_premium, AavePool, FlashLoanParams};
+use crate::gas_strategy::{GasFees, GasStrategy};
+use crate::inventory::{round_trip_pnl, Inventory};
+use crate::logger::AUDIT_TARGET;
+use crate::metrics;
+use crate::provider_pool::{PooledProvider, ProviderPool};
+use crate::scanner::to_units;
+use crate::signer::SignerPool;
+use crate::trade_journal::TradeJournal;
+use crate::transaction_manager::{TransactionManager, TransactionManagerConfig, TransactionManagerError};
+use async_trait::async_trait;
+use ethers::contract::EthError;
 use ethers::prelude::*;
-use std::sync::Arc;
-use log::{info, error};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use log::{error, info, warn};
+use thiserror::Error;
+use tracing::instrument;
 
+/// Mainnet WETH, the native token until `set_native_token` picks another chain's.
+pub(crate) const WETH_ADDRESS: &str = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2";
+
+abigen!(
+    UniswapRouter,
+    r#"[
+        function swapExactTokensForTokens(uint256 amountIn, uint256 amountOutMin, address[] path, address to, uint256 deadline) external returns (uint256[] amounts)
+        function getAmountsOut(uint256 amountIn, address[] path) external view returns (uint256[] amounts)
+    ]"#
+);
+
+abigen!(
+    FlashLoanProvider,
+    r#"[
+        function flashLoan(address token, uint256 amount, bytes data) external
+    ]"#
+);
+
+#[derive(Error, Debug, PartialEq)]
+pub enum SimulationError {
+    #[error("Simulated transaction reverted: {0}")]
+    Reverted(String),
+    #[error("Simulated output {amount_out} is below required {required}")]
+    InsufficientOutput { amount_out: U256, required: U256 },
+    #[error("Simulation call failed: {0}")]
+    CallFailed(String),
+}
+
+/// Why the executor would not trade at all.
+#[derive(Error, Debug, PartialEq)]
+pub enum ExecutionRefused {
+    #[error("Execution paused by operator")]
+    Paused,
+    #[error("Circuit breaker tripped: {0}")]
+    CircuitBreaker(BreakerReason),
+}
+
+/// Outcome of an `eth_call` of the exact swap against the pending block.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulationResult {
+    pub amounts: Vec<U256>,
+}
+
+impl SimulationResult {
+    pub fn amount_out(&self) -> U256 {
+        self.amounts.last().copied().unwrap_or_default()
+    }
+
+    /// Fails unless the simulated output covers `min_amount_out` plus `costs`,
+    /// both expressed in units of the output token.
+    pub fn check_output(&self, min_amount_out: U256, costs: U256) -> Result<(), SimulationError> {
+        let required = min_amount_out.saturating_add(costs);
+        let amount_out = self.amount_out();
+        if amount_out < required {
+            return Err(SimulationError::InsufficientOutput { amount_out, required });
+        }
+        Ok(())
+    }
+}
+
+/// Extracts the `Error(string)` reason from revert data, e.g. `"No profit"` from
+/// `ArbitrageBot.sol`, or names one of the bot's custom errors, falling back to
+/// the raw hex for anything else.
+pub fn decode_revert_reason(data: &Bytes) -> String {
+    String::decode_with_selector(data)
+        .or_else(|| decode_custom_error(data))
+        .unwrap_or_else(|| data.to_string())
+}
+
+fn simulation_error<M: Middleware>(e: ContractError<M>) -> SimulationError {
+    match e.as_revert() {
+        Some(data) => SimulationError::Reverted(decode_revert_reason(data)),
+        None => SimulationError::CallFailed(e.to_string()),
+    }
+}
+
+/// A swap along `path` from `account`, hop `i` on `routers[i]`, that must
+/// return at least `min_amount_out` before `deadline`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoutedTrade {
+    pub routers: Vec<Address>,
+    pub path: Vec<Address>,
+    pub amount: U256,
+    pub min_amount_out: U256,
+    pub deadline: U256,
+    pub account: Address,
+}
+
+/// Told about a trade as it passes each stage, so callers such as the scanner
+/// can timestamp its lifecycle when things happen rather than after the receipt.
+pub trait TradeObserver: Send + Sync {
+    /// The simulation passed and the trade is about to be sent.
+    fn simulated(&self) {}
+
+    /// The transaction was broadcast and is waiting to be mined.
+    fn submitted(&self, _hash: H256) {}
+}
+
+/// Ignores every stage.
+impl TradeObserver for () {}
+
+/// Common interface for anything that can carry out a trade, so strategies can be
+/// switched between live execution and paper trading without code changes.
+#[async_trait]
+pub trait Executor: Send + Sync {
+    async fn execute_trade(&self, path: Vec<Address>, amount: U256, min_amount_out: U256, deadline: U256, account: Address) -> Result<TransactionReceipt, Box<dyn std::error::Error>>;
+
+    /// Like `execute_trade`, but swaps each hop on its own router, e.g. to buy on
+    /// one DEX and sell on another, reporting its stages to `observer`.
+    async fn execute_routed_trade(&self, trade: RoutedTrade, observer: &dyn TradeObserver) -> Result<TransactionReceipt, Box<dyn std::error::Error>>;
+
+    async fn execute_flash_loan_trade(&self, token: Address, amount: U256, data: Bytes) -> Result<TransactionReceipt, Box<dyn std::error::Error>>;
+}
+
+/// Live executor: every call is broadcast to the network.
 pub struct TradeExecutor {
-    provider: Arc<Provider<Http>>,
-    uniswap_router: Contract<Arc<Provider<Http>>>,
-    flash_loan_provider: Contract<Arc<Provider<Http>>>,
+    provider: Arc<PooledProvider>,
+    uniswap_router: Contract<Arc<PooledProvider>>,
+    flash_loan_provider: Contract<Arc<PooledProvider>>,
+    execution_cost: U256,
+    gas_strategy: GasStrategy,
+    native_token: Address,
+    token_decimals: HashMap<Address, u8>,
+    quote_token: Option<Address>,
+    transaction_manager: TransactionManager,
+    signers: Option<Arc<SignerPool>>,
+    arbitrage_bot: Option<ArbitrageBotContract<PooledProvider>>,
+    inventory: Option<Arc<Mutex<Inventory>>>,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+    journal: Option<Arc<TradeJournal>>,
+    alerts: Option<(Arc<AlertDispatcher>, f64)>,
+    control: Option<Arc<ControlState>>,
 }
 
 impl TradeExecutor {
     pub fn new(provider_url: &str, uniswap_router_address: Address, flash_loan_provider_address: Address) -> Self {
-        let provider = Arc::new(Provider::<Http>::try_from(provider_url).expect("Failed to create provider"));
-        
+        let pool = ProviderPool::new(&[provider_url.to_string()]).expect("Failed to create provider");
+        Self::with_provider(Arc::new(pool.into_provider()), uniswap_router_address, flash_loan_provider_address)
+    }
+
+    /// Sends through `provider`, e.g. a pool over several endpoints.
+    pub fn with_provider(provider: Arc<PooledProvider>, uniswap_router_address: Address, flash_loan_provider_address: Address) -> Self {
+
         let uniswap_router = Contract::new(
             uniswap_router_address,
-            abigen!(UniswapRouter, "function swapExactTokensForTokens(uint256,uint256,address[],address,uint256)"),
-            provider.clone(),
+            UNISWAPROUTER_ABI.clone(),
+            Arc::new(provider.clone()),
         );
 
         let flash_loan_provider = Contract::new(
             flash_loan_provider_address,
-            abigen!(FlashLoanProvider, "function flashLoan(address,uint256,bytes)"),
-            provider.clone(),
+            FLASHLOANPROVIDER_ABI.clone(),
+            Arc::new(provider.clone()),
         );
 
+        let transaction_manager = TransactionManager::new(provider.clone(), TransactionManagerConfig::default())
+            .expect("Failed to create transaction manager");
+
         TradeExecutor {
             provider,
             uniswap_router,
             flash_loan_provider,
+            execution_cost: U256::zero(),
+            gas_strategy: GasStrategy::default(),
+            native_token: WETH_ADDRESS.parse().expect("Invalid WETH address"),
+            token_decimals: HashMap::new(),
+            quote_token: None,
+            transaction_manager,
+            signers: None,
+            arbitrage_bot: None,
+            inventory: None,
+            circuit_breaker: None,
+            journal: None,
+            alerts: None,
+            control: None,
         }
     }
 
-    pub async fn execute_trade(&self, path: Vec<Address>, amount: U256, min_amount_out: U256, deadline: U256, account: Address) -> Result<TransactionReceipt, Box<dyn std::error::Error>> {
-        info!("Executing trade: {:?}", path);
-        
-        let gas_price = self.get_optimal_gas_price().await?;
-        
-        let tx = self.uniswap_router
-            .method("swapExactTokensForTokens", (amount, min_amount_out, path, account, deadline))?
-            .gas_price(gas_price)
-            .send()
+    pub fn set_gas_strategy(&mut self, gas_strategy: GasStrategy) {
+        self.gas_strategy = gas_strategy;
+    }
+
+    /// Replaces the transaction manager, e.g. to persist pending transactions or
+    /// change confirmation targets.
+    pub fn set_transaction_manager_config(&mut self, config: TransactionManagerConfig) -> Result<(), TransactionManagerError> {
+        self.transaction_manager = TransactionManager::new(self.provider.clone(), config)?;
+        if let Some(signers) = &self.signers {
+            self.transaction_manager.set_signers(signers.clone());
+        }
+        Ok(())
+    }
+
+    /// Accounts to sign with. Without signers, transactions are sent through
+    /// `eth_sendTransaction` and must be signed by the node.
+    pub fn set_signers(&mut self, signers: Arc<SignerPool>) {
+        self.transaction_manager.set_signers(signers.clone());
+        self.signers = Some(signers);
+    }
+
+    pub fn transaction_manager(&self) -> &TransactionManager {
+        &self.transaction_manager
+    }
+
+    /// Routes trades through the deployed `ArbitrageBot` contract instead of
+    /// calling the router directly.
+    pub fn set_arbitrage_bot(&mut self, bot_address: Address) {
+        self.arbitrage_bot = Some(ArbitrageBotContract::new(bot_address, self.provider.clone()));
+    }
+
+    /// Inventory to update from every confirmed receipt.
+    pub fn set_inventory(&mut self, inventory: Arc<Mutex<Inventory>>) {
+        self.inventory = Some(inventory);
+    }
+
+    /// Breakers that halt execution once tripped.
+    pub fn set_circuit_breaker(&mut self, circuit_breaker: Arc<CircuitBreaker>) {
+        self.circuit_breaker = Some(circuit_breaker);
+    }
+
+    /// Journal to record every confirmed receipt, its gas and PnL in.
+    pub fn set_journal(&mut self, journal: Arc<TradeJournal>) {
+        self.journal = Some(journal);
+    }
+
+    /// Alerts on reverted transactions and on wins above `large_win_threshold`
+    /// in units of the quote token.
+    pub fn set_alerts(&mut self, alerts: Arc<AlertDispatcher>, large_win_threshold: f64) {
+        self.alerts = Some((alerts, large_win_threshold));
+    }
+
+    /// Lets the control API pause execution.
+    pub fn set_control(&mut self, control: Arc<ControlState>) {
+        self.control = Some(control);
+    }
+
+    fn check_can_trade(&self) -> Result<(), ExecutionRefused> {
+        if self.control.as_ref().is_some_and(|control| control.is_paused()) {
+            warn!("Refusing to trade, execution is paused");
+            return Err(ExecutionRefused::Paused);
+        }
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            if let Err(reason) = circuit_breaker.check() {
+                warn!("Refusing to trade, circuit breaker tripped: {}", reason);
+                return Err(ExecutionRefused::CircuitBreaker(reason));
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends through the transaction manager, tells `observer` once it is
+    /// broadcast, and feeds the outcome to the inventory, circuit breaker and journal.
+    #[instrument(skip_all)]
+    async fn send_and_record(&self, tx: TypedTransaction, fees: GasFees, observer: &dyn TradeObserver) -> Result<TransactionReceipt, Box<dyn std::error::Error>> {
+        let sent = async {
+            let pending = self.transaction_manager.send(tx, fees).await?;
+            if let Some(hash) = pending.latest_hash() {
+                self.journal_submission(hash, pending.account, None);
+                observer.submitted(hash);
+            }
+            let receipt = self.transaction_manager.wait_for_confirmation(pending.account, pending.nonce).await?;
+            if let Some(first) = pending.hashes.first().filter(|first| **first != receipt.transaction_hash) {
+                self.journal_submission(receipt.transaction_hash, pending.account, Some(*first));
+            }
+            Ok::<_, TransactionManagerError>(receipt)
+        };
+        let receipt = match sent.await {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                warn!(target: AUDIT_TARGET, "Transaction failed to send or confirm: {}", e);
+                if let Some(circuit_breaker) = &self.circuit_breaker {
+                    circuit_breaker.record_failure();
+                }
+                return Err(Box::new(e));
+            }
+        };
+        self.record_receipt(&receipt);
+        Ok(receipt)
+    }
+
+    /// Waits on transactions left pending by a previous run and records their
+    /// receipts like fresh ones. Call before trading resumes.
+    pub async fn resume_pending(&self) {
+        for result in self.transaction_manager.resume().await {
+            match result {
+                Ok(receipt) => self.record_receipt(&receipt),
+                Err(e) => {
+                    warn!(target: AUDIT_TARGET, "Restored transaction failed to confirm: {}", e);
+                    if let Some(circuit_breaker) = &self.circuit_breaker {
+                        circuit_breaker.record_failure();
+                    }
+                }
+            }
+        }
+    }
+
+    fn journal_submission(&self, hash: H256, sender: Address, replaces: Option<H256>) {
+        if let Some(journal) = &self.journal {
+            if let Err(e) = journal.record_submission(hash, sender, replaces, chrono::Utc::now()) {
+                error!("Failed to journal submission of {:?}: {}", hash, e);
+            }
+        }
+    }
+
+    /// PnL of a mined round trip in whole units of the quote token, converted at
+    /// the inventory's prices when it traded another; `None` without a price.
+    fn realized_pnl(&self, receipt: &TransactionReceipt) -> Option<f64> {
+        let (token, pnl) = round_trip_pnl(receipt, self.native_token)?;
+        let value = match (self.quote_token, &self.inventory) {
+            (Some(quote_token), Some(inventory)) if token != quote_token => match inventory.lock().unwrap().value(token, pnl.unsigned_abs()) {
+                Ok(value) => value,
+                Err(e) => {
+                    warn!("Not counting PnL of {:?}: {}", receipt.transaction_hash, e);
+                    return None;
+                }
+            },
+            (Some(quote_token), None) if token != quote_token => {
+                warn!("Not counting PnL of {:?}: no inventory to price {:?} in the quote token", receipt.transaction_hash, token);
+                return None;
+            }
+            _ => to_units(pnl.unsigned_abs(), self.token_decimals.get(&token).copied().unwrap_or(18)),
+        };
+        Some(if pnl.is_negative() { -value } else { value })
+    }
+
+    /// Feeds a mined receipt to the inventory, circuit breaker, alerts and journal.
+    fn record_receipt(&self, receipt: &TransactionReceipt) {
+        if let Some(inventory) = &self.inventory {
+            inventory.lock().unwrap().apply_receipt(receipt);
+        }
+        let pnl = self.realized_pnl(receipt);
+        let gas_cost = receipt.gas_used.unwrap_or_default() * receipt.effective_gas_price.unwrap_or_default();
+        info!(target: AUDIT_TARGET, "Transaction {:?} from {:?} mined in block {:?}: status {:?}, gas paid {}, pnl {:?}",
+              receipt.transaction_hash, receipt.from, receipt.block_number, receipt.status, gas_cost, pnl);
+        metrics::add_gas_spent(gas_cost);
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            circuit_breaker.record_transaction(receipt.status == Some(U64::from(1)), gas_cost, pnl.unwrap_or_default());
+        }
+        if let Some((alerts, large_win_threshold)) = &self.alerts {
+            let hash = format!("{:?}", receipt.transaction_hash);
+            if receipt.status != Some(U64::from(1)) {
+                let message = format!("Transaction {} reverted in block {:?}, gas paid {}", hash, receipt.block_number, gas_cost);
+                alerts.notify(Alert::new(Severity::Warning, "Transaction reverted", &message).with_dedup_key(&hash));
+            } else if let Some(pnl) = pnl.filter(|pnl| pnl >= large_win_threshold) {
+                let message = format!("Transaction {} realized {:.2}", hash, pnl);
+                alerts.notify(Alert::new(Severity::Info, "Large win", &message).with_dedup_key(&hash));
+            }
+        }
+        if let Some(journal) = &self.journal {
+            if let Err(e) = journal.record_receipt(receipt, pnl, chrono::Utc::now()) {
+                error!("Failed to journal receipt {:?}: {}", receipt.transaction_hash, e);
+            }
+        }
+    }
+
+    /// Wrapped native token, which profits are valued in to weigh them against gas.
+    pub fn set_native_token(&mut self, native_token: Address) {
+        self.native_token = native_token;
+    }
+
+    /// Token PnL is counted in for the circuit breaker, alerts and journal, e.g.
+    /// the chain's quote token. Unset, PnL stays in the token traded.
+    pub fn set_quote_token(&mut self, quote_token: Address) {
+        self.quote_token = Some(quote_token);
+    }
+
+    /// Decimals of `token`, for PnL in whole units; tokens not set have 18.
+    pub fn set_token_decimals(&mut self, token: Address, decimals: u8) {
+        self.token_decimals.insert(token, decimals);
+    }
+
+    /// Expected costs, in output-token units, that a simulated swap must clear on
+    /// top of `min_amount_out` before it is sent.
+    pub fn set_execution_cost(&mut self, execution_cost: U256) {
+        self.execution_cost = execution_cost;
+    }
+
+    /// `eth_call`s the exact swap at the pending block and decodes the resulting
+    /// amounts, or the revert reason if it would fail.
+    #[instrument(skip_all)]
+    pub async fn simulate_trade(&self, path: Vec<Address>, amount: U256, min_amount_out: U256, deadline: U256, account: Address) -> Result<SimulationResult, SimulationError> {
+        let call = self.uniswap_router
+            .method::<_, Vec<U256>>("swapExactTokensForTokens", (amount, min_amount_out, path, account, deadline))
+            .map_err(|e| SimulationError::CallFailed(e.to_string()))?
+            .from(account)
+            .block(BlockNumber::Pending);
+
+        let amounts = call.call().await.map_err(simulation_error)?;
+        info!("Simulated trade output: {:?}", amounts);
+        Ok(SimulationResult { amounts })
+    }
+
+    /// `eth_call`s `executeArbitrage` on the bot contract at the pending block, so
+    /// reverts such as `"No profit"` are caught before any gas is paid.
+    #[instrument(skip_all)]
+    pub async fn simulate_bot_trade(&self, trade: &RoutedTrade) -> Result<(), SimulationError> {
+        let bot = self.arbitrage_bot
+            .as_ref()
+            .ok_or_else(|| SimulationError::CallFailed("No arbitrage bot contract configured".to_string()))?;
+        let call = bot
+            .execute_arbitrage(trade.path.clone(), trade.amount, trade.min_amount_out, trade.deadline)
+            .from(trade.account)
+            .block(BlockNumber::Pending);
+
+        call.call().await.map_err(simulation_error)?;
+        info!("Simulated bot trade succeeded");
+        Ok(())
+    }
+
+    /// Sends the trade through the bot's `executeArbitrage` and decodes the
+    /// resulting `ArbitrageExecuted`/`ErrorOccurred` events.
+    #[instrument(skip_all)]
+    pub async fn execute_bot_trade(&self, trade: &RoutedTrade, observer: &dyn TradeObserver) -> Result<BotReceipt, Box<dyn std::error::Error>> {
+        self.check_can_trade()?;
+        info!("Executing trade through arbitrage bot: {:?}", trade.path);
+        let bot = self.arbitrage_bot.as_ref().ok_or("No arbitrage bot contract configured")?;
+
+        if let Err(e) = self.simulate_bot_trade(trade).await {
+            warn!("Aborting bot trade after simulation: {}", e);
+            return Err(Box::new(e));
+        }
+        observer.simulated();
+
+        let amount_out = self.quote_routed_amount_out(trade.amount, &trade.routers, &trade.path).await?;
+        let expected_profit = self.expected_native_profit(&trade.path, trade.amount, amount_out).await?;
+
+        let mut call = bot
+            .execute_arbitrage(trade.path.clone(), trade.amount, trade.min_amount_out, trade.deadline)
+            .from(trade.account);
+        let gas_limit = self.gas_strategy.gas_limit_with_margin(call.estimate_gas().await?);
+
+        let fees = self.capped_fees(gas_limit, expected_profit).await?;
+        call = call.gas(gas_limit);
+        let receipt = self.send_and_record(call.tx, fees, observer).await?;
+
+        let bot_receipt = BotReceipt::from_receipt(receipt, bot.address());
+        info!(
+            "Bot trade executed: {:?}, profit: {}, errors: {:?}",
+            bot_receipt.receipt.transaction_hash, bot_receipt.total_profit(), bot_receipt.errors
+        );
+        Ok(bot_receipt)
+    }
+
+    /// `eth_call`s `executeRoutedArbitrage` on the bot contract at the pending block.
+    #[instrument(skip_all)]
+    pub async fn simulate_routed_bot_trade(&self, trade: &RoutedTrade) -> Result<(), SimulationError> {
+        let bot = self.arbitrage_bot
+            .as_ref()
+            .ok_or_else(|| SimulationError::CallFailed("No arbitrage bot contract configured".to_string()))?;
+        let call = bot
+            .execute_routed_arbitrage(trade.routers.clone(), trade.path.clone(), trade.amount, trade.min_amount_out, trade.deadline)
+            .from(trade.account)
+            .block(BlockNumber::Pending);
+
+        call.call().await.map_err(simulation_error)?;
+        info!("Simulated routed bot trade succeeded");
+        Ok(())
+    }
+
+    /// Sends the trade through the bot's `executeRoutedArbitrage`, swapping each
+    /// hop on its own router.
+    #[instrument(skip_all)]
+    pub async fn execute_routed_bot_trade(&self, trade: &RoutedTrade, observer: &dyn TradeObserver) -> Result<BotReceipt, Box<dyn std::error::Error>> {
+        self.check_can_trade()?;
+        info!("Executing routed trade through arbitrage bot: {:?} via {:?}", trade.path, trade.routers);
+        let bot = self.arbitrage_bot.as_ref().ok_or("Trading across DEX routers needs the arbitrage bot contract")?;
+
+        if let Err(e) = self.simulate_routed_bot_trade(trade).await {
+            warn!("Aborting routed bot trade after simulation: {}", e);
+            return Err(Box::new(e));
+        }
+        observer.simulated();
+
+        let amount_out = self.quote_routed_amount_out(trade.amount, &trade.routers, &trade.path).await?;
+        let expected_profit = self.expected_native_profit(&trade.path, trade.amount, amount_out).await?;
+
+        let mut call = bot
+            .execute_routed_arbitrage(trade.routers.clone(), trade.path.clone(), trade.amount, trade.min_amount_out, trade.deadline)
+            .from(trade.account);
+        let gas_limit = self.gas_strategy.gas_limit_with_margin(call.estimate_gas().await?);
+
+        let fees = self.capped_fees(gas_limit, expected_profit).await?;
+        call = call.gas(gas_limit);
+        let receipt = self.send_and_record(call.tx, fees, observer).await?;
+
+        let bot_receipt = BotReceipt::from_receipt(receipt, bot.address());
+        info!(
+            "Routed bot trade executed: {:?}, profit: {}, errors: {:?}",
+            bot_receipt.receipt.transaction_hash, bot_receipt.total_profit(), bot_receipt.errors
+        );
+        Ok(bot_receipt)
+    }
+
+    /// Quotes `path` on the router at the pending block.
+    async fn quote_amount_out(&self, amount: U256, path: Vec<Address>) -> Result<U256, Box<dyn std::error::Error>> {
+        let amounts = self.uniswap_router
+            .method::<_, Vec<U256>>("getAmountsOut", (amount, path))?
+            .block(BlockNumber::Pending)
+            .call()
             .await?;
+        Ok(amounts.last().copied().unwrap_or_default())
+    }
+
+    /// Quotes each hop of `path` on its router at the pending block.
+    async fn quote_routed_amount_out(&self, amount: U256, routers: &[Address], path: &[Address]) -> Result<U256, Box<dyn std::error::Error>> {
+        if routers.len() + 1 != path.len() {
+            return Err(format!("{} router(s) for a path of {} token(s)", routers.len(), path.len()).into());
+        }
+        let mut amount_out = amount;
+        for (router, hop) in routers.iter().zip(path.windows(2)) {
+            let amounts = UniswapRouter::new(*router, self.provider.clone())
+                .get_amounts_out(amount_out, hop.to_vec())
+                .block(BlockNumber::Pending)
+                .call()
+                .await?;
+            amount_out = amounts.last().copied().unwrap_or_default();
+        }
+        Ok(amount_out)
+    }
+
+    /// `amount` of `token` in wei of the native token, at the router's quote.
+    async fn native_value(&self, token: Address, amount: U256) -> Result<U256, Box<dyn std::error::Error>> {
+        if token == self.native_token || amount.is_zero() {
+            return Ok(amount);
+        }
+        self.quote_amount_out(amount, vec![token, self.native_token]).await
+    }
+
+    /// `amount` wei of the native token in units of `token`, at the router's quote.
+    async fn token_value(&self, token: Address, amount: U256) -> Result<U256, Box<dyn std::error::Error>> {
+        if token == self.native_token || amount.is_zero() {
+            return Ok(amount);
+        }
+        self.quote_amount_out(amount, vec![self.native_token, token]).await
+    }
 
-        let receipt = tx.await?;
+    /// Expected profit in wei of swapping `amount` of the first token of `path`
+    /// for `amount_out` of the last, net of the execution cost but not of gas.
+    async fn expected_native_profit(&self, path: &[Address], amount: U256, amount_out: U256) -> Result<U256, Box<dyn std::error::Error>> {
+        let (Some(token_in), Some(token_out)) = (path.first().copied(), path.last().copied()) else {
+            return Err("Empty swap path".into());
+        };
+        let amount_out = amount_out.saturating_sub(self.execution_cost);
+        if token_in == token_out {
+            return self.native_value(token_out, amount_out.saturating_sub(amount)).await;
+        }
+        let value_out = self.native_value(token_out, amount_out).await?;
+        let value_in = self.native_value(token_in, amount).await?;
+        Ok(value_out.saturating_sub(value_in))
+    }
+
+    /// Borrows `amount` of `asset` from the bot's Aave pool and swaps it along
+    /// `params.path` in `executeOperation`. Only sent once the quoted output
+    /// repays the loan with enough left over for the premium and gas.
+    #[instrument(skip_all)]
+    pub async fn execute_aave_flash_loan(&self, asset: Address, amount: U256, params: FlashLoanParams) -> Result<BotReceipt, Box<dyn std::error::Error>> {
+        self.check_can_trade()?;
+        info!("Executing Aave flash loan for token: {:?}, amount: {:?}", asset, amount);
+        let bot = self.arbitrage_bot.as_ref().ok_or("No arbitrage bot contract configured")?;
+        params.check_round_trip(asset)?;
+
+        let pool = AavePool::new(bot.pool().call().await?, self.provider.clone());
+        let premium = flash_loan_premium(amount, U256::from(pool.flashloan_premium_total().call().await?));
+        let amount_out = self.quote_amount_out(amount, params.path.clone()).await?;
+
+        let account = self.default_account().await?;
+        let simulation = bot
+            .execute_flash_loan_arbitrage(asset, amount, params.encode())
+            .from(account)
+            .block(BlockNumber::Pending);
+        if let Err(e) = simulation.call().await.map_err(simulation_error) {
+            warn!("Aborting flash loan trade after simulation: {}", e);
+            return Err(Box::new(e));
+        }
+
+        let mut call = bot
+            .execute_flash_loan_arbitrage(asset, amount, params.encode())
+            .from(account);
+        let gas_limit = self.gas_strategy.gas_limit_with_margin(call.estimate_gas().await?);
+        let profit_before_gas = check_flash_loan_profit(amount, amount_out, premium, self.execution_cost)?;
+        let expected_profit = self.native_value(asset, profit_before_gas).await?;
+        let fees = self.capped_fees(gas_limit, expected_profit).await?;
+
+        // The most the transaction may pay for gas, in the borrowed asset
+        let gas_cost = self.token_value(asset, gas_limit * fees.max_gas_price()).await?;
+        let net_profit = check_flash_loan_profit(amount, amount_out, premium, self.execution_cost.saturating_add(gas_cost))?;
+        info!("Flash loan premium: {}, gas: {}, expected net profit: {}", premium, gas_cost, net_profit);
+
+        call = call.gas(gas_limit);
+        let receipt = self.send_and_record(call.tx, fees, &()).await?;
+        let bot_receipt = BotReceipt::from_receipt(receipt, bot.address());
+        info!(
+            "Flash loan trade executed: {:?}, profit: {}",
+            bot_receipt.receipt.transaction_hash, bot_receipt.total_profit()
+        );
+        Ok(bot_receipt)
+    }
+
+    /// `eth_call`s the flash loan at the pending block so a reverting callback is
+    /// caught before any gas is paid.
+    pub async fn simulate_flash_loan_trade(&self, token: Address, amount: U256, data: Bytes) -> Result<(), SimulationError> {
+        let call = self.flash_loan_provider
+            .method::<_, ()>("flashLoan", (token, amount, data))
+            .map_err(|e| SimulationError::CallFailed(e.to_string()))?
+            .block(BlockNumber::Pending);
+
+        call.call().await.map_err(simulation_error)?;
+        info!("Simulated flash loan for token {:?} succeeded", token);
+        Ok(())
+    }
+
+    /// Sender for transactions that are not tied to a trade account.
+    async fn default_account(&self) -> Result<Address, Box<dyn std::error::Error>> {
+        if let Some(signer) = self.signers.as_ref().and_then(|signers| signers.next_signer()) {
+            return Ok(signer.address());
+        }
+        let accounts = self.provider.get_accounts().await?;
+        let account = accounts.first().copied().ok_or("No account available to send from")?;
+        Ok(account)
+    }
+
+    /// Fees for `gas_limit`, held to the gas strategy's share of `expected_profit`
+    /// in wei. Every send path goes through here.
+    async fn capped_fees(&self, gas_limit: U256, expected_profit: U256) -> Result<GasFees, Box<dyn std::error::Error>> {
+        let fees = self.gas_strategy.estimate_fees(&self.provider).await?;
+        Ok(self.gas_strategy.cap_to_profit(fees, gas_limit, expected_profit)?)
+    }
+}
+
+#[async_trait]
+impl Executor for TradeExecutor {
+    async fn execute_trade(&self, path: Vec<Address>, amount: U256, min_amount_out: U256, deadline: U256, account: Address) -> Result<TransactionReceipt, Box<dyn std::error::Error>> {
+        let routers = vec![self.uniswap_router.address(); path.len().saturating_sub(1)];
+        let trade = RoutedTrade { routers, path, amount, min_amount_out, deadline, account };
+        self.execute_routed_trade(trade, &()).await
+    }
+
+    async fn execute_routed_trade(&self, trade: RoutedTrade, observer: &dyn TradeObserver) -> Result<TransactionReceipt, Box<dyn std::error::Error>> {
+        self.check_can_trade()?;
+        let single_router = trade.routers.iter().all(|router| *router == self.uniswap_router.address());
+        if self.arbitrage_bot.is_some() {
+            let bot_receipt = if single_router {
+                self.execute_bot_trade(&trade, observer).await?
+            } else {
+                self.execute_routed_bot_trade(&trade, observer).await?
+            };
+            return Ok(bot_receipt.receipt);
+        }
+        if !single_router {
+            return Err("Trading across DEX routers needs the arbitrage bot contract".into());
+        }
+
+        let RoutedTrade { path, amount, min_amount_out, deadline, account, .. } = trade;
+        info!("Executing trade: {:?}", path);
+
+        let simulation = self.simulate_trade(path.clone(), amount, min_amount_out, deadline, account).await
+            .and_then(|result| result.check_output(min_amount_out, self.execution_cost).map(|()| result));
+        let simulation = match simulation {
+            Ok(simulation) => simulation,
+            Err(e) => {
+                warn!("Aborting trade after simulation: {}", e);
+                return Err(Box::new(e));
+            }
+        };
+        observer.simulated();
+        let expected_profit = self.expected_native_profit(&path, amount, simulation.amount_out()).await?;
+
+        let mut call = self.uniswap_router
+            .method::<_, Vec<U256>>("swapExactTokensForTokens", (amount, min_amount_out, path, account, deadline))?
+            .from(account);
+        let gas_limit = self.gas_strategy.gas_limit_with_margin(call.estimate_gas().await?);
+
+        let fees = self.capped_fees(gas_limit, expected_profit).await?;
+        call = call.gas(gas_limit);
+        let receipt = self.send_and_record(call.tx, fees, observer).await?;
         info!("Trade executed successfully: {:?}", receipt.transaction_hash);
-        
+
         Ok(receipt)
     }
 
-    pub async fn execute_flash_loan_trade(&self, token: Address, amount: U256, data: Bytes) -> Result<TransactionReceipt, Box<dyn std::error::Error>> {
+    async fn execute_flash_loan_trade(&self, token: Address, amount: U256, data: Bytes) -> Result<TransactionReceipt, Box<dyn std::error::Error>> {
+        self.check_can_trade()?;
+        if self.arbitrage_bot.is_some() {
+            let params = FlashLoanParams::decode(&data)?;
+            let bot_receipt = self.execute_aave_flash_loan(token, amount, params).await?;
+            return Ok(bot_receipt.receipt);
+        }
+
         info!("Executing flash loan trade for token: {:?}, amount: {:?}", token, amount);
-        
-        let gas_price = self.get_optimal_gas_price().await?;
-        
-        let tx = self.flash_loan_provider
-            .method("flashLoan", (token, amount, data))?
-            .gas_price(gas_price)
-            .send()
-            .await?;
 
-        let receipt = tx.await?;
+        if let Err(e) = self.simulate_flash_loan_trade(token, amount, data.clone()).await {
+            warn!("Aborting flash loan trade after simulation: {}", e);
+            return Err(Box::new(e));
+        }
+        // The provider's fee is unknown here, so it belongs in the execution cost
+        let params = FlashLoanParams::decode(&data)?;
+        params.check_round_trip(token)?;
+        let amount_out = self.quote_amount_out(amount, params.path.clone()).await?;
+        let expected_profit = self.expected_native_profit(&params.path, amount, amount_out).await?;
+
+        let account = self.default_account().await?;
+        let mut call = self.flash_loan_provider
+            .method::<_, ()>("flashLoan", (token, amount, data))?
+            .from(account);
+        let gas_limit = self.gas_strategy.gas_limit_with_margin(call.estimate_gas().await?);
+
+        let fees = self.capped_fees(gas_limit, expected_profit).await?;
+        call = call.gas(gas_limit);
+        let receipt = self.send_and_record(call.tx, fees, &()).await?;
         info!("Flash loan trade executed successfully: {:?}", receipt.transaction_hash);
-        
-        Ok(receipt)
-    }
 
-    async fn get_optimal_gas_price(&self) -> Result<U256, Box<dyn std::error::Error>> {
-        let gas_price = self.provider.get_gas_price().await?;
-        Ok(gas_price)
+        Ok(receipt)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,367 @@
+use crate::arbitrage_finder::ArbitrageOpportunity;
+use crate::opportunity_tracker::OpportunityState;
+use chrono::{DateTime, NaiveDate, Utc};
+use ethers::types::{Address, TransactionReceipt, H256, U256};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use std::sync::Mutex;
+use log::{debug, info};
+use thiserror::Error;
+
+/// Schema migrations, applied in order. `PRAGMA user_version` records how many
+/// have run, so released entries must never be edited, only appended to.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE opportunities (
+        id TEXT PRIMARY KEY,
+        detected_at TEXT NOT NULL,
+        block_number INTEGER,
+        pair TEXT,
+        buy_venue TEXT NOT NULL,
+        sell_venue TEXT NOT NULL,
+        expected_profit REAL NOT NULL
+    );
+    CREATE TABLE opportunity_states (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        opportunity_id TEXT NOT NULL REFERENCES opportunities(id),
+        state TEXT NOT NULL,
+        detail TEXT,
+        at TEXT NOT NULL
+    );
+    CREATE INDEX opportunity_states_opportunity ON opportunity_states(opportunity_id);
+    CREATE TABLE risk_decisions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        opportunity_id TEXT NOT NULL REFERENCES opportunities(id),
+        approved INTEGER NOT NULL,
+        reason TEXT,
+        decided_at TEXT NOT NULL
+    );
+    CREATE TABLE transactions (
+        hash TEXT PRIMARY KEY,
+        opportunity_id TEXT REFERENCES opportunities(id),
+        sender TEXT,
+        submitted_at TEXT NOT NULL
+    );
+    CREATE TABLE receipts (
+        hash TEXT PRIMARY KEY REFERENCES transactions(hash),
+        block_number INTEGER,
+        success INTEGER NOT NULL,
+        gas_used TEXT NOT NULL,
+        effective_gas_price TEXT NOT NULL,
+        gas_paid TEXT NOT NULL,
+        realized_pnl REAL,
+        settled_at TEXT NOT NULL
+    );
+    CREATE INDEX receipts_settled_at ON receipts(settled_at);",
+    "ALTER TABLE opportunities ADD COLUMN chain_id INTEGER;",
+];
+
+#[derive(Error, Debug)]
+pub enum JournalError {
+    #[error("Journal database error: {0}")]
+    Database(#[from] rusqlite::Error),
+    #[error("Journal schema version {found} is newer than this build supports ({supported})")]
+    UnsupportedSchema { found: usize, supported: usize },
+}
+
+/// Realized PnL of the trades settled on one UTC day, grouped by pair or venue.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DailyPnl {
+    pub day: NaiveDate,
+    pub key: String,
+    pub realized_pnl: f64,
+    pub trades: u32,
+}
+
+/// A journaled lifecycle transition; `detail` holds the reason or transaction hash.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JournaledState {
+    pub state: String,
+    pub detail: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+/// Embedded SQLite store of opportunities, risk decisions, transactions and
+/// their settlement, kept across restarts.
+#[derive(Debug)]
+pub struct TradeJournal {
+    connection: Mutex<Connection>,
+}
+
+impl TradeJournal {
+    /// Opens or creates the journal at `path` and migrates it to the latest schema.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, JournalError> {
+        let connection = Connection::open(path.as_ref())?;
+        connection.busy_timeout(std::time::Duration::from_secs(5))?;
+        info!("Opened trade journal at {}", path.as_ref().display());
+        Self::from_connection(connection)
+    }
+
+    pub fn open_in_memory() -> Result<Self, JournalError> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(mut connection: Connection) -> Result<Self, JournalError> {
+        connection.pragma_update(None, "foreign_keys", true)?;
+        migrate(&mut connection)?;
+        Ok(TradeJournal { connection: Mutex::new(connection) })
+    }
+
+    pub fn schema_version(&self) -> Result<usize, JournalError> {
+        schema_version(&self.connection.lock().unwrap())
+    }
+
+    pub fn record_opportunity(&self, opportunity: &ArbitrageOpportunity) -> Result<(), JournalError> {
+        let mut connection = self.connection.lock().unwrap();
+        let tx = connection.transaction()?;
+        let inserted = tx.execute(
+            "INSERT OR IGNORE INTO opportunities (id, detected_at, chain_id, block_number, pair, buy_venue, sell_venue, expected_profit)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                opportunity.id,
+                opportunity.detected_at,
+                opportunity.chain_id,
+                opportunity.block_number,
+                opportunity.pair,
+                opportunity.buy_dex,
+                opportunity.sell_dex,
+                opportunity.profit,
+            ],
+        )?;
+        if inserted > 0 {
+            insert_state(&tx, &opportunity.id, &OpportunityState::Detected, opportunity.detected_at)?;
+        }
+        tx.commit()?;
+        debug!("Journaled opportunity {}", opportunity.id);
+        Ok(())
+    }
+
+    /// Records a lifecycle transition. Risk outcomes are also written as risk
+    /// decisions and submissions link the transaction to the opportunity.
+    pub fn record_state(&self, opportunity_id: &str, state: &OpportunityState, at: DateTime<Utc>) -> Result<(), JournalError> {
+        let mut connection = self.connection.lock().unwrap();
+        let tx = connection.transaction()?;
+        insert_state(&tx, opportunity_id, state, at)?;
+        match state {
+            OpportunityState::RiskApproved | OpportunityState::RiskRejected(_) => {
+                let reason = match state {
+                    OpportunityState::RiskRejected(reason) => Some(reason),
+                    _ => None,
+                };
+                tx.execute(
+                    "INSERT INTO risk_decisions (opportunity_id, approved, reason, decided_at) VALUES (?1, ?2, ?3, ?4)",
+                    params![opportunity_id, reason.is_none(), reason, at],
+                )?;
+            }
+            OpportunityState::Submitted(hash) => {
+                tx.execute(
+                    "INSERT INTO transactions (hash, opportunity_id, submitted_at) VALUES (?1, ?2, ?3)
+                     ON CONFLICT(hash) DO UPDATE SET opportunity_id = excluded.opportunity_id",
+                    params![format!("{:?}", hash), opportunity_id, at],
+                )?;
+            }
+            _ => {}
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Records a transaction as broadcast by `sender` at `at`. A replacement
+    /// keeps the opportunity and broadcast time of the transaction it replaced.
+    pub fn record_submission(&self, hash: H256, sender: Address, replaces: Option<H256>, at: DateTime<Utc>) -> Result<(), JournalError> {
+        let connection = self.connection.lock().unwrap();
+        let hash = format!("{:?}", hash);
+        let inserted = match replaces {
+            Some(original) => connection.execute(
+                "INSERT OR IGNORE INTO transactions (hash, opportunity_id, sender, submitted_at)
+                 SELECT ?1, opportunity_id, ?2, submitted_at FROM transactions WHERE hash = ?3",
+                params![hash, format!("{:?}", sender), format!("{:?}", original)],
+            )?,
+            None => 0,
+        };
+        if inserted == 0 {
+            connection.execute(
+                "INSERT INTO transactions (hash, sender, submitted_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(hash) DO UPDATE SET sender = excluded.sender",
+                params![hash, format!("{:?}", sender), at],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Records a mined transaction with the gas it paid and, when known, the
+    /// PnL it realized in the quote token. Transactions never recorded as
+    /// submitted are taken to have been broadcast at `at`.
+    pub fn record_receipt(&self, receipt: &TransactionReceipt, realized_pnl: Option<f64>, at: DateTime<Utc>) -> Result<(), JournalError> {
+        let hash = format!("{:?}", receipt.transaction_hash);
+        let gas_used = receipt.gas_used.unwrap_or_default();
+        let effective_gas_price = receipt.effective_gas_price.unwrap_or_default();
+
+        let mut connection = self.connection.lock().unwrap();
+        let tx = connection.transaction()?;
+        tx.execute(
+            "INSERT INTO transactions (hash, sender, submitted_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(hash) DO UPDATE SET sender = excluded.sender",
+            params![hash, format!("{:?}", receipt.from), at],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO receipts (hash, block_number, success, gas_used, effective_gas_price, gas_paid, realized_pnl, settled_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                hash,
+                receipt.block_number.map(|block| block.as_u64()),
+                receipt.status == Some(1u64.into()),
+                gas_used.to_string(),
+                effective_gas_price.to_string(),
+                (gas_used * effective_gas_price).to_string(),
+                realized_pnl,
+                at,
+            ],
+        )?;
+        tx.commit()?;
+        debug!("Journaled receipt {}", hash);
+        Ok(())
+    }
+
+    /// States an opportunity went through, oldest first.
+    pub fn state_history(&self, opportunity_id: &str) -> Result<Vec<JournaledState>, JournalError> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare("SELECT state, detail, at FROM opportunity_states WHERE opportunity_id = ?1 ORDER BY id")?;
+        let rows = statement.query_map([opportunity_id], |row| {
+            Ok(JournaledState { state: row.get(0)?, detail: row.get(1)?, at: row.get(2)? })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// When a transaction, or the one it replaced, was broadcast.
+    pub fn submitted_at(&self, hash: H256) -> Result<Option<DateTime<Utc>>, JournalError> {
+        let connection = self.connection.lock().unwrap();
+        let submitted_at = connection
+            .query_row("SELECT submitted_at FROM transactions WHERE hash = ?1", [format!("{:?}", hash)], |row| row.get(0))
+            .optional()?;
+        Ok(submitted_at)
+    }
+
+    /// Opportunity a transaction was submitted for, if it was linked to one.
+    pub fn opportunity_for_transaction(&self, hash: H256) -> Result<Option<String>, JournalError> {
+        let connection = self.connection.lock().unwrap();
+        let opportunity_id = connection
+            .query_row("SELECT opportunity_id FROM transactions WHERE hash = ?1", [format!("{:?}", hash)], |row| row.get(0))
+            .optional()?;
+        Ok(opportunity_id.flatten())
+    }
+
+    /// Total gas paid, in wei, by transactions settled on `day`.
+    pub fn gas_paid(&self, day: NaiveDate) -> Result<U256, JournalError> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare("SELECT gas_paid FROM receipts WHERE date(settled_at) = ?1")?;
+        let rows = statement.query_map([day], |row| row.get::<_, String>(0))?;
+        let mut total = U256::zero();
+        for gas_paid in rows {
+            total += U256::from_dec_str(&gas_paid?).unwrap_or_default();
+        }
+        Ok(total)
+    }
+
+    /// Daily realized PnL per pair since `since`. Trades without a known pair
+    /// are grouped under `"unknown"`.
+    pub fn daily_pnl_by_pair(&self, since: NaiveDate) -> Result<Vec<DailyPnl>, JournalError> {
+        self.daily_pnl(
+            "WITH settled AS (
+                SELECT date(r.settled_at) AS day, COALESCE(o.pair, 'unknown') AS key, r.realized_pnl
+                FROM receipts r
+                LEFT JOIN transactions t ON t.hash = r.hash
+                LEFT JOIN opportunities o ON o.id = t.opportunity_id
+                WHERE date(r.settled_at) >= ?1
+            )
+            SELECT day, key, COALESCE(SUM(realized_pnl), 0.0), COUNT(*) FROM settled GROUP BY day, key ORDER BY day, key",
+            since,
+        )
+    }
+
+    /// Daily realized PnL per chain id since `since`, with untagged trades under
+    /// `"unknown"`.
+    pub fn daily_pnl_by_chain(&self, since: NaiveDate) -> Result<Vec<DailyPnl>, JournalError> {
+        self.daily_pnl(
+            "WITH settled AS (
+                SELECT date(r.settled_at) AS day, COALESCE(CAST(o.chain_id AS TEXT), 'unknown') AS key, r.realized_pnl
+                FROM receipts r
+                LEFT JOIN transactions t ON t.hash = r.hash
+                LEFT JOIN opportunities o ON o.id = t.opportunity_id
+                WHERE date(r.settled_at) >= ?1
+            )
+            SELECT day, key, COALESCE(SUM(realized_pnl), 0.0), COUNT(*) FROM settled GROUP BY day, key ORDER BY day, key",
+            since,
+        )
+    }
+
+    /// Daily realized PnL per venue since `since`. A trade counts towards both
+    /// the venue it bought on and the venue it sold on, so venues do not sum to
+    /// the daily total.
+    pub fn daily_pnl_by_venue(&self, since: NaiveDate) -> Result<Vec<DailyPnl>, JournalError> {
+        self.daily_pnl(
+            "WITH settled AS (
+                SELECT date(r.settled_at) AS day, o.buy_venue, o.sell_venue, r.realized_pnl
+                FROM receipts r
+                LEFT JOIN transactions t ON t.hash = r.hash
+                LEFT JOIN opportunities o ON o.id = t.opportunity_id
+                WHERE date(r.settled_at) >= ?1
+            ),
+            venues AS (
+                SELECT day, COALESCE(buy_venue, 'unknown') AS key, realized_pnl FROM settled
+                UNION ALL
+                SELECT day, sell_venue, realized_pnl FROM settled WHERE sell_venue IS NOT NULL AND sell_venue != buy_venue
+            )
+            SELECT day, key, COALESCE(SUM(realized_pnl), 0.0), COUNT(*) FROM venues GROUP BY day, key ORDER BY day, key",
+            since,
+        )
+    }
+
+    fn daily_pnl(&self, sql: &str, since: NaiveDate) -> Result<Vec<DailyPnl>, JournalError> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(sql)?;
+        let rows = statement.query_map([since], |row| {
+            Ok(DailyPnl {
+                day: row.get(0)?,
+                key: row.get(1)?,
+                realized_pnl: row.get(2)?,
+                trades: row.get(3)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+}
+
+fn schema_version(connection: &Connection) -> Result<usize, JournalError> {
+    Ok(connection.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+}
+
+fn migrate(connection: &mut Connection) -> Result<(), JournalError> {
+    let found = schema_version(connection)?;
+    if found > MIGRATIONS.len() {
+        return Err(JournalError::UnsupportedSchema { found, supported: MIGRATIONS.len() });
+    }
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(found) {
+        let tx = connection.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+        tx.commit()?;
+        info!("Migrated trade journal to schema version {}", index + 1);
+    }
+    Ok(())
+}
+
+fn insert_state(connection: &Connection, opportunity_id: &str, state: &OpportunityState, at: DateTime<Utc>) -> Result<(), JournalError> {
+    let detail = match state {
+        OpportunityState::RiskRejected(reason)
+        | OpportunityState::SimulationFailed(reason)
+        | OpportunityState::SendFailed(reason)
+        | OpportunityState::Dropped(reason) => Some(reason.clone()),
+        OpportunityState::Submitted(hash) | OpportunityState::Confirmed(hash) | OpportunityState::Reverted(hash) => Some(format!("{:?}", hash)),
+        _ => None,
+    };
+    connection.execute(
+        "INSERT INTO opportunity_states (opportunity_id, state, detail, at) VALUES (?1, ?2, ?3, ?4)",
+        params![opportunity_id, state.name(), detail, at],
+    )?;
+    Ok(())
+}
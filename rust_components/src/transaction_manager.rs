@@ -0,0 +1,429 @@
+use crate::gas_strategy::GasFees;
+use crate::provider_pool::PooledProvider;
+use crate::signer::{SignerError, SignerPool};
+use ethers::prelude::*;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::utils::keccak256;
+use futures::future::join_all;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+
+const CANCEL_GAS_LIMIT: u64 = 21_000;
+
+#[derive(Error, Debug)]
+pub enum TransactionManagerError {
+    #[error("Provider error: {0}")]
+    Provider(#[from] ProviderError),
+    #[error("Signer error: {0}")]
+    Signer(#[from] SignerError),
+    #[error("Transaction has no sender")]
+    MissingSender,
+    #[error("No pending transaction for {0:?} with nonce {1}")]
+    UnknownTransaction(Address, U256),
+    #[error("Transaction for {0:?} with nonce {1} still pending after {2} replacements")]
+    Stuck(Address, U256, u32),
+    #[error("Failed to persist pending transactions: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to encode pending transactions: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+#[derive(Clone, Debug)]
+pub struct TransactionManagerConfig {
+    /// Blocks on top of the inclusion block before a receipt is returned.
+    pub confirmations: u64,
+    /// Blocks without inclusion after which a transaction is replaced with higher fees.
+    pub stuck_after_blocks: u64,
+    /// Fee increase per replacement; nodes require at least 10%.
+    pub fee_bump_percent: u64,
+    /// Replacements after which a transaction is cancelled and given up on.
+    pub max_replacements: u32,
+    pub poll_interval: Duration,
+    /// Where pending transactions are written so a restart can pick them up again.
+    pub persistence_path: Option<PathBuf>,
+}
+
+impl Default for TransactionManagerConfig {
+    fn default() -> Self {
+        TransactionManagerConfig {
+            confirmations: 1,
+            stuck_after_blocks: 3,
+            fee_bump_percent: 12,
+            max_replacements: 5,
+            poll_interval: Duration::from_secs(2),
+            persistence_path: None,
+        }
+    }
+}
+
+/// A broadcast transaction that has not been confirmed yet. Every hash sent for
+/// the nonce is kept, since any of them may be the one that gets mined.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TrackedTransaction {
+    pub account: Address,
+    pub nonce: U256,
+    pub tx: TypedTransaction,
+    pub fees: GasFees,
+    pub hashes: Vec<H256>,
+    pub submitted_block: u64,
+    pub replacements: u32,
+}
+
+impl TrackedTransaction {
+    pub fn latest_hash(&self) -> Option<H256> {
+        self.hashes.last().copied()
+    }
+}
+
+/// Raises every fee component by `percent`, as required for a replacement.
+pub fn bump_fees(fees: GasFees, percent: u64) -> GasFees {
+    let bump = |value: U256| value + value * percent / 100;
+    match fees {
+        GasFees::Eip1559 { base_fee_per_gas, max_fee_per_gas, max_priority_fee_per_gas } => GasFees::Eip1559 {
+            base_fee_per_gas,
+            max_fee_per_gas: bump(max_fee_per_gas),
+            max_priority_fee_per_gas: bump(max_priority_fee_per_gas),
+        },
+        GasFees::Legacy { gas_price } => GasFees::Legacy { gas_price: bump(gas_price) },
+    }
+}
+
+pub fn save_pending(path: &Path, pending: &[TrackedTransaction]) -> Result<(), TransactionManagerError> {
+    let encoded = serde_json::to_string_pretty(pending)?;
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, encoded)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+pub fn load_pending(path: &Path) -> Result<Vec<TrackedTransaction>, TransactionManagerError> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let encoded = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&encoded)?)
+}
+
+/// Whether the node rejected a raw transaction because it already has it, e.g.
+/// after an earlier send timed out on our side.
+fn is_already_known(error: &ProviderError) -> bool {
+    let message = error.to_string().to_lowercase();
+    message.contains("already known") || message.contains("known transaction")
+}
+
+/// Whether the node answered and refused the transaction, as opposed to a
+/// transport error after which it may still have been accepted.
+fn is_rejection(error: &TransactionManagerError) -> bool {
+    match error {
+        TransactionManagerError::Provider(e) => RpcError::as_error_response(e).is_some(),
+        _ => true,
+    }
+}
+
+/// Allocates nonces per account, follows transactions until they are confirmed and
+/// replaces the ones that get stuck.
+pub struct TransactionManager {
+    provider: Arc<PooledProvider>,
+    config: TransactionManagerConfig,
+    nonces: Mutex<HashMap<Address, U256>>,
+    pending: Mutex<HashMap<(Address, U256), TrackedTransaction>>,
+    signers: Option<Arc<SignerPool>>,
+}
+
+impl TransactionManager {
+    /// Restores any transactions persisted by a previous run, so they are still
+    /// followed and their nonces are not handed out again.
+    pub fn new(provider: Arc<PooledProvider>, config: TransactionManagerConfig) -> Result<Self, TransactionManagerError> {
+        let restored = match &config.persistence_path {
+            Some(path) => load_pending(path)?,
+            None => Vec::new(),
+        };
+
+        let mut nonces: HashMap<Address, U256> = HashMap::new();
+        let mut pending = HashMap::new();
+        for tx in restored {
+            let next_nonce = nonces.entry(tx.account).or_insert(tx.nonce + 1);
+            *next_nonce = (*next_nonce).max(tx.nonce + 1);
+            pending.insert((tx.account, tx.nonce), tx);
+        }
+        if !pending.is_empty() {
+            info!("Restored {} pending transactions", pending.len());
+        }
+
+        Ok(TransactionManager {
+            provider,
+            config,
+            nonces: Mutex::new(nonces),
+            pending: Mutex::new(pending),
+            signers: None,
+        })
+    }
+
+    /// Signs transactions locally with these accounts and sends them raw, instead
+    /// of relying on accounts unlocked on the node.
+    pub fn set_signers(&mut self, signers: Arc<SignerPool>) {
+        self.signers = Some(signers);
+    }
+
+    pub fn pending_transactions(&self) -> Vec<TrackedTransaction> {
+        let mut pending: Vec<_> = self.pending.lock().unwrap().values().cloned().collect();
+        pending.sort_by_key(|tx| (tx.account, tx.nonce));
+        pending
+    }
+
+    /// Hands out the next nonce for `account`, starting from the node's pending
+    /// transaction count the first time the account is seen.
+    pub async fn next_nonce(&self, account: Address) -> Result<U256, TransactionManagerError> {
+        let cached = self.nonces.lock().unwrap().get(&account).copied();
+        let start = match cached {
+            Some(nonce) => nonce,
+            None => self.provider.get_transaction_count(account, Some(BlockNumber::Pending.into())).await?,
+        };
+
+        let mut nonces = self.nonces.lock().unwrap();
+        let next = nonces.entry(account).or_insert(start);
+        let nonce = *next;
+        *next += U256::one();
+        debug!("Allocated nonce {} for {:?}", nonce, account);
+        Ok(nonce)
+    }
+
+    /// Forgets the cached nonce so it is re-read from the node, e.g. after a
+    /// transaction was rejected before reaching the mempool.
+    pub fn reset_nonce(&self, account: Address) {
+        self.nonces.lock().unwrap().remove(&account);
+    }
+
+    /// Records the transaction as pending before it is broadcast, so a crash or
+    /// error right after the send cannot orphan it. Signed transactions that may
+    /// have reached the node are kept pending; definite rejections free the nonce.
+    pub async fn send(&self, mut tx: TypedTransaction, fees: GasFees) -> Result<TrackedTransaction, TransactionManagerError> {
+        let account = *tx.from().ok_or(TransactionManagerError::MissingSender)?;
+        let nonce = self.next_nonce(account).await?;
+        tx.set_nonce(nonce);
+        fees.apply(&mut tx);
+
+        let prepared = async {
+            let submitted_block = self.provider.get_block_number().await?.as_u64();
+            let signed = self.sign(&tx).await?;
+            Ok::<_, TransactionManagerError>((submitted_block, signed))
+        };
+        let (submitted_block, signed) = match prepared.await {
+            Ok(prepared) => prepared,
+            Err(e) => {
+                self.reset_nonce(account);
+                return Err(e);
+            }
+        };
+
+        let mut pending = TrackedTransaction {
+            account,
+            nonce,
+            tx: tx.clone(),
+            fees,
+            hashes: signed.iter().map(|(_, hash)| *hash).collect(),
+            submitted_block,
+            replacements: 0,
+        };
+        self.pending.lock().unwrap().insert((account, nonce), pending.clone());
+        if let Err(e) = self.persist() {
+            self.forget(account, nonce);
+            return Err(e);
+        }
+
+        let hash = match self.submit(&tx, signed.clone()).await {
+            Ok(hash) => hash,
+            Err(e) if signed.is_some() && !is_rejection(&e) => {
+                warn!("Sending {:?} from {:?} with nonce {} may have failed, following it: {}", pending.latest_hash(), account, nonce, e);
+                return Ok(pending);
+            }
+            Err(e) => {
+                self.forget(account, nonce);
+                return Err(e);
+            }
+        };
+        info!("Sent transaction {:?} from {:?} with nonce {}", hash, account, nonce);
+
+        if !pending.hashes.contains(&hash) {
+            pending.hashes.push(hash);
+            self.pending.lock().unwrap().insert((account, nonce), pending.clone());
+            self.persist()?;
+        }
+        Ok(pending)
+    }
+
+    /// Follows every transaction restored from a previous run until it is
+    /// confirmed, replacing the ones that are stuck. Call once at startup.
+    pub async fn resume(&self) -> Vec<Result<TransactionReceipt, TransactionManagerError>> {
+        let pending = self.pending_transactions();
+        if !pending.is_empty() {
+            info!("Resuming {} pending transactions", pending.len());
+        }
+        join_all(pending.iter().map(|tx| self.wait_for_confirmation(tx.account, tx.nonce))).await
+    }
+
+    pub async fn send_and_confirm(&self, tx: TypedTransaction, fees: GasFees) -> Result<TransactionReceipt, TransactionManagerError> {
+        let pending = self.send(tx, fees).await?;
+        self.wait_for_confirmation(pending.account, pending.nonce).await
+    }
+
+    /// Polls until one of the transaction's hashes has the configured number of
+    /// confirmations, replacing it with bumped fees while it is stuck.
+    pub async fn wait_for_confirmation(&self, account: Address, nonce: U256) -> Result<TransactionReceipt, TransactionManagerError> {
+        loop {
+            let pending = self.get_pending(account, nonce)?;
+            let current_block = self.provider.get_block_number().await?.as_u64();
+
+            let mut mined = false;
+            for hash in pending.hashes.iter().rev() {
+                if let Some(receipt) = self.provider.get_transaction_receipt(*hash).await? {
+                    mined = true;
+                    let mined_block = receipt.block_number.unwrap_or_default().as_u64();
+                    if current_block + 1 >= mined_block + self.config.confirmations {
+                        info!("Transaction {:?} confirmed in block {}", hash, mined_block);
+                        self.pending.lock().unwrap().remove(&(account, nonce));
+                        self.persist()?;
+                        return Ok(receipt);
+                    }
+                    break;
+                }
+            }
+
+            if !mined && current_block >= pending.submitted_block + self.config.stuck_after_blocks {
+                if pending.replacements >= self.config.max_replacements {
+                    self.give_up(account, nonce).await;
+                    return Err(TransactionManagerError::Stuck(account, nonce, pending.replacements));
+                }
+                self.replace(pending, None, current_block).await?;
+            }
+
+            tokio::time::sleep(self.config.poll_interval).await;
+        }
+    }
+
+    /// Replaces a pending transaction with a zero-value transfer to self using the
+    /// same nonce and bumped fees.
+    pub async fn cancel(&self, account: Address, nonce: U256) -> Result<H256, TransactionManagerError> {
+        let pending = self.get_pending(account, nonce)?;
+        let mut cancel_tx: TypedTransaction = match pending.tx {
+            TypedTransaction::Eip1559(_) => Eip1559TransactionRequest::new().to(account).into(),
+            _ => TransactionRequest::new().to(account).into(),
+        };
+        cancel_tx.set_from(account);
+        cancel_tx.set_value(U256::zero());
+        cancel_tx.set_gas(CANCEL_GAS_LIMIT);
+        cancel_tx.set_nonce(nonce);
+
+        let current_block = self.provider.get_block_number().await?.as_u64();
+        let hash = self.replace(pending, Some(cancel_tx), current_block).await?;
+        info!("Sent cancellation {:?} for {:?} nonce {}", hash, account, nonce);
+        Ok(hash)
+    }
+
+    async fn replace(&self, pending: TrackedTransaction, tx: Option<TypedTransaction>, current_block: u64) -> Result<H256, TransactionManagerError> {
+        let fees = bump_fees(pending.fees, self.config.fee_bump_percent);
+        let mut tx = tx.unwrap_or(pending.tx);
+        fees.apply(&mut tx);
+
+        let hash = self.broadcast(&tx).await?;
+        warn!(
+            "Replaced transaction from {:?} with nonce {}: {:?} (replacement {})",
+            pending.account, pending.nonce, hash, pending.replacements + 1
+        );
+
+        let mut hashes = pending.hashes;
+        hashes.push(hash);
+        let replacement = TrackedTransaction {
+            account: pending.account,
+            nonce: pending.nonce,
+            tx,
+            fees,
+            hashes,
+            submitted_block: current_block,
+            replacements: pending.replacements + 1,
+        };
+        self.pending.lock().unwrap().insert((pending.account, pending.nonce), replacement);
+        self.persist()?;
+        Ok(hash)
+    }
+
+    async fn broadcast(&self, tx: &TypedTransaction) -> Result<H256, TransactionManagerError> {
+        let signed = self.sign(tx).await?;
+        self.submit(tx, signed).await
+    }
+
+    /// Signs with the sender's signer, returning the raw transaction and its hash,
+    /// or `None` when the node signs.
+    async fn sign(&self, tx: &TypedTransaction) -> Result<Option<(Bytes, H256)>, TransactionManagerError> {
+        let Some(signers) = &self.signers else {
+            return Ok(None);
+        };
+        let account = *tx.from().ok_or(TransactionManagerError::MissingSender)?;
+        let signer = signers.get(account)?;
+        let mut tx = tx.clone();
+        if tx.chain_id().is_none() {
+            tx.set_chain_id(signer.chain_id());
+        }
+        let signature = signer.sign_transaction(&tx).await?;
+        let raw = tx.rlp_signed(&signature);
+        let hash = H256::from(keccak256(&raw));
+        Ok(Some((raw, hash)))
+    }
+
+    async fn submit(&self, tx: &TypedTransaction, signed: Option<(Bytes, H256)>) -> Result<H256, TransactionManagerError> {
+        let pending_tx = match signed {
+            Some((raw, hash)) => match self.provider.send_raw_transaction(raw).await {
+                Ok(pending_tx) => pending_tx,
+                Err(e) if is_already_known(&e) => {
+                    info!("Transaction {:?} is already known to the node", hash);
+                    return Ok(hash);
+                }
+                Err(e) => return Err(e.into()),
+            },
+            None => self.provider.send_transaction(tx.clone(), None).await?,
+        };
+        Ok(pending_tx.tx_hash())
+    }
+
+    /// Stops following a stuck transaction: a last cancellation tries to close its
+    /// nonce, and the account's nonce is re-read from the node so later sends do
+    /// not queue behind a gap nobody is closing.
+    async fn give_up(&self, account: Address, nonce: U256) {
+        warn!("Giving up on transaction from {:?} with nonce {}, cancelling it", account, nonce);
+        if let Err(e) = self.cancel(account, nonce).await {
+            warn!("Failed to cancel transaction from {:?} with nonce {}: {}", account, nonce, e);
+        }
+        self.forget(account, nonce);
+    }
+
+    /// Drops a transaction that never reached the mempool and frees its nonce.
+    fn forget(&self, account: Address, nonce: U256) {
+        self.pending.lock().unwrap().remove(&(account, nonce));
+        self.reset_nonce(account);
+        if let Err(e) = self.persist() {
+            warn!("Failed to persist pending transactions: {}", e);
+        }
+    }
+
+    fn get_pending(&self, account: Address, nonce: U256) -> Result<TrackedTransaction, TransactionManagerError> {
+        self.pending
+            .lock()
+            .unwrap()
+            .get(&(account, nonce))
+            .cloned()
+            .ok_or(TransactionManagerError::UnknownTransaction(account, nonce))
+    }
+
+    fn persist(&self) -> Result<(), TransactionManagerError> {
+        if let Some(path) = &self.config.persistence_path {
+            save_pending(path, &self.pending_transactions())?;
+        }
+        Ok(())
+    }
+}
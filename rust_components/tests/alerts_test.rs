@@ -0,0 +1,233 @@
+use rust_components::alerts::{Alert, AlertDispatcher, AlertError, AlertSink, DispatchOutcome, Severity, TelegramSink, WebhookSink};
+use rust_components::circuit_breaker::{BreakerReason, CircuitBreaker, CircuitBreakerConfig};
+use rust_components::gas_strategy::GasFees;
+use rust_components::inventory::{Inventory, TransferFilter};
+use rust_components::provider_pool::ProviderPool;
+use rust_components::trade_executor::TradeExecutor;
+use rust_components::transaction_manager::{save_pending, TrackedTransaction, TransactionManagerConfig};
+use async_trait::async_trait;
+use chrono::Duration;
+use ethers::abi::{encode, Token};
+use ethers::contract::EthEvent;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, Log, TransactionReceipt, TransactionRequest, H256, U256, U64};
+use serde_json::{json, Value};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Serves a single HTTP request with `{"ok":true}` and hands back its method,
+/// URL and body as `"POST /path {...}"`.
+fn serve_once() -> (String, mpsc::Receiver<String>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut request = server.recv().unwrap();
+        let mut body = String::new();
+        request.as_reader().read_to_string(&mut body).unwrap();
+        sender.send(format!("{} {} {}", request.method(), request.url(), body)).unwrap();
+        let header = tiny_http::Header::from_bytes("Content-Type", "application/json").unwrap();
+        request.respond(tiny_http::Response::from_string(r#"{"ok":true}"#).with_header(header)).unwrap();
+    });
+
+    (url, receiver)
+}
+
+#[derive(Clone, Default)]
+struct RecordingSink(Arc<Mutex<Vec<Alert>>>);
+
+#[async_trait]
+impl AlertSink for RecordingSink {
+    fn name(&self) -> &str {
+        "recording"
+    }
+
+    async fn send(&self, alert: &Alert) -> Result<(), AlertError> {
+        self.0.lock().unwrap().push(alert.clone());
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_webhook_and_telegram_sinks() {
+    let (webhook_url, webhook_requests) = serve_once();
+    let (telegram_url, telegram_requests) = serve_once();
+    let mut dispatcher = AlertDispatcher::new();
+    dispatcher.add_sink(Box::new(WebhookSink::new(&format!("{}/alerts", webhook_url))), Severity::Info);
+    dispatcher.add_sink(Box::new(TelegramSink::new("123:test-token", "42").with_api_base(&telegram_url)), Severity::Warning);
+
+    let outcome = dispatcher.dispatch(Alert::new(Severity::Critical, "Circuit breaker tripped", "daily loss")).await;
+    assert_eq!(outcome, DispatchOutcome::Sent(2));
+
+    let webhook = webhook_requests.recv().unwrap();
+    assert!(webhook.starts_with("POST /alerts "));
+    assert!(webhook.contains(r#""severity":"critical""#));
+    assert!(webhook.contains(r#""title":"Circuit breaker tripped""#));
+
+    let telegram = telegram_requests.recv().unwrap();
+    assert!(telegram.starts_with("POST /bot123:test-token/sendMessage "));
+    assert!(telegram.contains(r#""chat_id":"42""#));
+    assert!(telegram.contains("[CRITICAL] Circuit breaker tripped"));
+}
+
+#[tokio::test]
+async fn test_severity_dedup_and_rate_limit() {
+    let info_sink = RecordingSink::default();
+    let critical_sink = RecordingSink::default();
+    let mut dispatcher = AlertDispatcher::new();
+    dispatcher.add_sink(Box::new(info_sink.clone()), Severity::Info);
+    dispatcher.add_sink(Box::new(critical_sink.clone()), Severity::Critical);
+    dispatcher.set_rate_limit(2, Duration::minutes(1));
+
+    assert_eq!(dispatcher.dispatch(Alert::new(Severity::Info, "Large win", "0x1").with_dedup_key("0x1")).await, DispatchOutcome::Sent(1));
+    assert_eq!(dispatcher.dispatch(Alert::new(Severity::Info, "Large win", "0x1").with_dedup_key("0x1")).await, DispatchOutcome::Duplicate);
+    assert_eq!(dispatcher.dispatch(Alert::new(Severity::Warning, "Transaction reverted", "0x2")).await, DispatchOutcome::Sent(1));
+    assert_eq!(dispatcher.dispatch(Alert::new(Severity::Warning, "Slow RPC", "3s")).await, DispatchOutcome::RateLimited);
+    assert_eq!(dispatcher.dispatch(Alert::new(Severity::Critical, "Kill switch", "operator")).await, DispatchOutcome::Sent(2));
+
+    assert_eq!(info_sink.0.lock().unwrap().len(), 3);
+    assert_eq!(critical_sink.0.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn test_breaker_trip_raises_critical_alert() {
+    let sink = RecordingSink::default();
+    let mut dispatcher = AlertDispatcher::new();
+    dispatcher.add_sink(Box::new(sink.clone()), Severity::Critical);
+    let dispatcher = Arc::new(dispatcher);
+
+    let mut breaker = CircuitBreaker::new(CircuitBreakerConfig::default());
+    breaker.set_alert_handler(dispatcher.breaker_alert_handler("mainnet"));
+    breaker.trip(BreakerReason::KillSwitch("test".to_string()));
+
+    for _ in 0..100 {
+        if !sink.0.lock().unwrap().is_empty() {
+            break;
+        }
+        thread::sleep(std::time::Duration::from_millis(10));
+    }
+    let alerts = sink.0.lock().unwrap();
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].severity, Severity::Critical);
+    assert!(alerts[0].message.contains("on mainnet: kill switch engaged by test"));
+}
+
+fn transfer_log(token: Address, from: Address, to: Address, value: U256) -> Log {
+    Log {
+        address: token,
+        topics: vec![TransferFilter::signature(), H256::from(from), H256::from(to)],
+        data: encode(&[Token::Uint(value)]).into(),
+        ..Default::default()
+    }
+}
+
+/// Node at block 0x20 that has mined `receipt`.
+fn node_with_receipt(receipt: TransactionReceipt) -> String {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+    thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            let call: Value = serde_json::from_str(&body).unwrap();
+            let result = match call["method"].as_str().unwrap() {
+                "eth_blockNumber" => json!("0x20"),
+                "eth_getTransactionReceipt" => serde_json::to_value(&receipt).unwrap(),
+                _ => Value::Null,
+            };
+            let response = json!({"jsonrpc": "2.0", "id": call["id"], "result": result});
+            let _ = request.respond(tiny_http::Response::from_string(response.to_string()));
+        }
+    });
+    url
+}
+
+/// Alerts raised when `executor`, set up by `configure`, resumes a pending
+/// transaction that mined as a round trip from `wallet` making 2 of `token`.
+async fn alerts_on_round_trip(wallet: Address, token: Address, configure: impl FnOnce(&mut TradeExecutor)) -> Vec<Alert> {
+    let bot = Address::from_low_u64_be(2);
+    let hash = H256::from_low_u64_be(4);
+    let receipt = TransactionReceipt {
+        transaction_hash: hash,
+        from: wallet,
+        status: Some(U64::from(1)),
+        block_number: Some(U64::from(0x10)),
+        logs: vec![
+            transfer_log(token, wallet, bot, U256::exp10(18)),
+            transfer_log(token, bot, wallet, U256::exp10(18) * 3),
+        ],
+        ..Default::default()
+    };
+    let url = node_with_receipt(receipt);
+
+    // A transaction left pending by a previous run
+    let path = std::env::temp_dir().join(format!("alerts-test-pending-{}-{:?}.json", std::process::id(), token));
+    let tx: TypedTransaction = TransactionRequest::new().from(wallet).to(bot).nonce(0u64).into();
+    let pending = TrackedTransaction {
+        account: wallet,
+        nonce: U256::zero(),
+        tx,
+        fees: GasFees::Legacy { gas_price: U256::from(1u64) },
+        hashes: vec![hash],
+        submitted_block: 0x10,
+        replacements: 0,
+    };
+    save_pending(&path, &[pending]).unwrap();
+
+    let sink = RecordingSink::default();
+    let mut dispatcher = AlertDispatcher::new();
+    dispatcher.add_sink(Box::new(sink.clone()), Severity::Info);
+    let provider = Arc::new(ProviderPool::new(&[url]).unwrap().into_provider());
+    let mut executor = TradeExecutor::with_provider(provider, Address::zero(), Address::zero());
+    executor.set_transaction_manager_config(TransactionManagerConfig { persistence_path: Some(path.clone()), ..Default::default() }).unwrap();
+    configure(&mut executor);
+    executor.set_alerts(Arc::new(dispatcher), 1.0);
+
+    executor.resume_pending().await;
+
+    for _ in 0..100 {
+        if !sink.0.lock().unwrap().is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    let _ = std::fs::remove_file(&path);
+    let alerts = sink.0.lock().unwrap().clone();
+    alerts
+}
+
+#[tokio::test]
+async fn test_large_win_alert_without_inventory() {
+    let alerts = alerts_on_round_trip(Address::from_low_u64_be(1), Address::from_low_u64_be(3), |_| {}).await;
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].title, "Large win");
+    assert!(alerts[0].message.contains("realized 2.00"));
+}
+
+#[tokio::test]
+async fn test_large_win_is_valued_in_quote_token() {
+    let (wallet, token, quote) = (Address::from_low_u64_be(5), Address::from_low_u64_be(6), Address::from_low_u64_be(7));
+    let mut inventory = Inventory::new(Address::zero());
+    inventory.set_price(token, 0.25);
+    let inventory = Arc::new(Mutex::new(inventory));
+    let alerts = alerts_on_round_trip(wallet, token, |executor| {
+        executor.set_quote_token(quote);
+        executor.set_inventory(inventory);
+    })
+    .await;
+    // 2 tokens at 0.25 stay under the threshold of 1
+    assert!(alerts.is_empty());
+
+    let (wallet, token) = (Address::from_low_u64_be(8), Address::from_low_u64_be(9));
+    let mut inventory = Inventory::new(Address::zero());
+    inventory.set_price(token, 1500.0);
+    let inventory = Arc::new(Mutex::new(inventory));
+    let alerts = alerts_on_round_trip(wallet, token, |executor| {
+        executor.set_quote_token(quote);
+        executor.set_inventory(inventory);
+    })
+    .await;
+    assert!(alerts[0].message.contains("realized 3000.00"));
+}
@@ -0,0 +1,82 @@
+use rust_components::arbitrage_bot_contract::{ArbitrageExecutedFilter, BotReceipt, ErrorOccurredFilter};
+use ethers::abi::{encode, Token};
+use ethers::contract::EthEvent;
+use ethers::types::{Address, Log, TransactionReceipt, H256, U256, U64};
+
+fn bot_address() -> Address {
+    Address::from_low_u64_be(0xb07)
+}
+
+fn arbitrage_executed_log(address: Address, path: Vec<Address>, profit: u64) -> Log {
+    Log {
+        address,
+        topics: vec![ArbitrageExecutedFilter::signature()],
+        data: encode(&[
+            Token::Array(path.into_iter().map(Token::Address).collect()),
+            Token::Uint(U256::from(profit)),
+        ])
+        .into(),
+        ..Default::default()
+    }
+}
+
+fn error_occurred_log(address: Address, message: &str, reason: &str) -> Log {
+    Log {
+        address,
+        topics: vec![ErrorOccurredFilter::signature()],
+        data: encode(&[Token::String(message.to_string()), Token::String(reason.to_string())]).into(),
+        ..Default::default()
+    }
+}
+
+fn receipt(logs: Vec<Log>) -> TransactionReceipt {
+    TransactionReceipt {
+        transaction_hash: H256::from_low_u64_be(1),
+        status: Some(U64::from(1)),
+        logs,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_decodes_arbitrage_executed_events() {
+    let path = vec![Address::from_low_u64_be(1), Address::from_low_u64_be(2), Address::from_low_u64_be(1)];
+    let bot_receipt = BotReceipt::from_receipt(
+        receipt(vec![
+            arbitrage_executed_log(bot_address(), path.clone(), 25),
+            arbitrage_executed_log(bot_address(), path.clone(), 40),
+        ]),
+        bot_address(),
+    );
+
+    assert!(bot_receipt.success());
+    assert_eq!(bot_receipt.executions.len(), 2);
+    assert_eq!(bot_receipt.executions[0].path, path);
+    assert_eq!(bot_receipt.total_profit(), U256::from(65u64));
+    assert!(bot_receipt.errors.is_empty());
+}
+
+#[test]
+fn test_decodes_error_occurred_events() {
+    let bot_receipt = BotReceipt::from_receipt(receipt(vec![error_occurred_log(bot_address(), "Swap failed", "No profit")]), bot_address());
+
+    assert_eq!(bot_receipt.errors.len(), 1);
+    assert_eq!(bot_receipt.errors[0].message, "Swap failed");
+    assert_eq!(bot_receipt.errors[0].reason, "No profit");
+    assert_eq!(bot_receipt.total_profit(), U256::zero());
+}
+
+#[test]
+fn test_ignores_logs_from_other_contracts() {
+    let other = Address::from_low_u64_be(0xdead);
+    let bot_receipt = BotReceipt::from_receipt(
+        receipt(vec![
+            arbitrage_executed_log(other, vec![Address::from_low_u64_be(1)], 25),
+            error_occurred_log(other, "Swap failed", "unrelated"),
+        ]),
+        bot_address(),
+    );
+
+    assert!(bot_receipt.executions.is_empty());
+    assert!(bot_receipt.errors.is_empty());
+}
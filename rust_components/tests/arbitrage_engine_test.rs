@@ -57,3 +57,17 @@ fn test_calculate_arbitrage() {
     
     assert!(matches!(result, Ok(Some(profit)) if profit == 2.0), "Expected arbitrage opportunity with profit of 2.0");
 }
+
+#[test]
+fn test_find_opportunity_assigns_ids() {
+    let book_a = OrderBook::from_orders(vec![Order::new(100.0, 5.0).unwrap()], vec![]);
+    let book_b = OrderBook::from_orders(vec![], vec![Order::new(99.0, 5.0).unwrap()]);
+
+    let first = ArbitrageEngine::find_opportunity("Exchange1", &book_a, "Exchange2", &book_b).unwrap().unwrap();
+    let second = ArbitrageEngine::find_opportunity("Exchange1", &book_a, "Exchange2", &book_b).unwrap().unwrap();
+
+    assert_eq!(first.buy_dex, "Exchange2");
+    assert_eq!(first.sell_dex, "Exchange1");
+    assert_eq!(first.profit, 1.0);
+    assert_ne!(first.id, second.id);
+}
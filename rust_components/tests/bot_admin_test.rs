@@ -0,0 +1,45 @@
+use rust_components::bot_admin::{AdminAction, BotAdmin, TokenState};
+use ethers::types::{Address, U256};
+
+fn anvil_url() -> String {
+    std::env::var("ANVIL_RPC_URL").unwrap_or_else(|_| "http://127.0.0.1:8545".to_string())
+}
+
+#[test]
+fn test_action_token() {
+    let token = Address::from_low_u64_be(1);
+    assert_eq!(AdminAction::AddSupportedToken { token }.token(), token);
+    assert_eq!(AdminAction::AddPriceFeed { token, price_feed: Address::from_low_u64_be(2) }.token(), token);
+    assert_eq!(AdminAction::WithdrawToken { token, amount: U256::one() }.token(), token);
+}
+
+#[test]
+fn test_action_display() {
+    let action = AdminAction::WithdrawToken { token: Address::from_low_u64_be(1), amount: U256::from(1_000u64) };
+    assert_eq!(action.to_string(), "withdrawToken(0x0000000000000000000000000000000000000001, 1000)");
+}
+
+#[test]
+fn test_token_state_display() {
+    let state = TokenState { token: Address::from_low_u64_be(1), supported: true, price_feed: None };
+    assert_eq!(
+        state.to_string(),
+        "token 0x0000000000000000000000000000000000000001: supported = true, price feed = none"
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires a local anvil node with ConcreteArbitrageBot deployed at ARBITRAGE_BOT_ADDRESS"]
+async fn test_add_token_round_trip_on_anvil() {
+    let bot: Address = std::env::var("ARBITRAGE_BOT_ADDRESS").unwrap().parse().unwrap();
+    let admin = BotAdmin::new(&anvil_url(), bot).unwrap();
+    let owner = admin.owner().await.unwrap();
+    let token = Address::random();
+    let action = AdminAction::AddSupportedToken { token };
+
+    // Non-owners are rejected in simulation
+    assert!(admin.simulate(&action, Address::random()).await.is_err());
+
+    admin.execute(&action, owner).await.unwrap();
+    assert!(admin.token_state(token).await.unwrap().supported);
+}
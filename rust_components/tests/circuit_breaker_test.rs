@@ -0,0 +1,133 @@
+use rust_components::circuit_breaker::{BreakerReason, CircuitBreaker, CircuitBreakerConfig};
+use chrono::{TimeZone, Utc};
+use ethers::types::U256;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn config() -> CircuitBreakerConfig {
+    CircuitBreakerConfig {
+        max_daily_loss: 100.0,
+        max_consecutive_failures: 3,
+        max_gas_per_hour: U256::from(1_000u64),
+        kill_switch_path: None,
+        state_path: None,
+    }
+}
+
+#[test]
+fn test_daily_loss_resets_each_day() {
+    let breaker = CircuitBreaker::new(config());
+    let day_one = Utc.with_ymd_and_hms(2024, 1, 1, 23, 0, 0).unwrap();
+    let day_two = Utc.with_ymd_and_hms(2024, 1, 2, 1, 0, 0).unwrap();
+
+    breaker.record_transaction_at(true, U256::zero(), -60.0, day_one);
+    breaker.record_transaction_at(true, U256::zero(), -60.0, day_two);
+    assert!(breaker.check().is_ok());
+
+    breaker.record_transaction_at(true, U256::zero(), -50.0, day_two);
+    assert_eq!(breaker.check(), Err(BreakerReason::DailyLoss { loss: 110.0, limit: 100.0 }));
+}
+
+#[test]
+fn test_consecutive_failures() {
+    let breaker = CircuitBreaker::new(config());
+    let now = Utc::now();
+
+    breaker.record_transaction_at(false, U256::zero(), 0.0, now);
+    breaker.record_transaction_at(false, U256::zero(), 0.0, now);
+    breaker.record_transaction_at(true, U256::zero(), 0.0, now);
+    breaker.record_transaction_at(false, U256::zero(), 0.0, now);
+    breaker.record_transaction_at(false, U256::zero(), 0.0, now);
+    assert!(!breaker.is_tripped());
+
+    breaker.record_failure();
+    assert_eq!(breaker.check(), Err(BreakerReason::ConsecutiveFailures { count: 3, limit: 3 }));
+}
+
+#[test]
+fn test_hourly_gas_window() {
+    let breaker = CircuitBreaker::new(config());
+    let start = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+
+    breaker.record_transaction_at(true, U256::from(600u64), 0.0, start);
+    breaker.record_transaction_at(true, U256::from(600u64), 0.0, start + chrono::Duration::minutes(61));
+    assert!(!breaker.is_tripped());
+
+    breaker.record_transaction_at(true, U256::from(600u64), 0.0, start + chrono::Duration::minutes(90));
+    assert!(matches!(breaker.check(), Err(BreakerReason::HourlyGas { .. })));
+}
+
+#[test]
+fn test_kill_switch_file_and_alert() {
+    let kill_switch = temp_path("breaker-kill-switch");
+    let alerts = Arc::new(AtomicUsize::new(0));
+    let mut breaker = CircuitBreaker::new(CircuitBreakerConfig { kill_switch_path: Some(kill_switch.clone()), ..config() });
+    let counter = alerts.clone();
+    breaker.set_alert_handler(Box::new(move |_| {
+        counter.fetch_add(1, Ordering::SeqCst);
+    }));
+
+    assert!(breaker.check().is_ok());
+    std::fs::write(&kill_switch, "").unwrap();
+    assert!(matches!(breaker.check(), Err(BreakerReason::KillSwitch(_))));
+    assert!(breaker.check().is_err());
+    assert_eq!(alerts.load(Ordering::SeqCst), 1);
+
+    // Removing the file is not enough, an operator has to reset
+    std::fs::remove_file(&kill_switch).unwrap();
+    assert!(breaker.check().is_err());
+    breaker.reset("ops").unwrap();
+    assert!(breaker.check().is_ok());
+}
+
+#[test]
+fn test_trip_survives_restart() {
+    let state_path = temp_path("breaker-state.json");
+    let config = CircuitBreakerConfig { state_path: Some(state_path.clone()), ..config() };
+
+    CircuitBreaker::new(config.clone()).trip(BreakerReason::KillSwitch("test".to_string()));
+    let restarted = CircuitBreaker::new(config.clone());
+    assert_eq!(restarted.check(), Err(BreakerReason::KillSwitch("test".to_string())));
+
+    restarted.reset("ops").unwrap();
+    assert!(!state_path.exists());
+    assert!(CircuitBreaker::new(config).check().is_ok());
+}
+
+#[test]
+fn test_removing_state_file_resumes_running_bot() {
+    let state_path = temp_path("breaker-remove-state.json");
+    let breaker = CircuitBreaker::new(CircuitBreakerConfig { state_path: Some(state_path.clone()), ..config() });
+
+    breaker.trip(BreakerReason::KillSwitch("test".to_string()));
+    assert!(breaker.check().is_err());
+
+    std::fs::remove_file(&state_path).unwrap();
+    assert!(breaker.check().is_ok());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_sigusr1_trips_breaker() {
+    let breaker = Arc::new(CircuitBreaker::new(config()));
+    tokio::spawn(rust_components::circuit_breaker::watch_kill_signal(breaker.clone()));
+    // Let the watcher install its handler before signalling
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    let status = std::process::Command::new("kill").args(["-USR1", &std::process::id().to_string()]).status().unwrap();
+    assert!(status.success());
+    for _ in 0..100 {
+        if breaker.is_tripped() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    assert!(matches!(breaker.tripped().unwrap().reason, BreakerReason::KillSwitch(ref by) if by == "SIGUSR1"));
+}
@@ -0,0 +1,146 @@
+use rust_components::config::{Config, ConfigError, ConfigWatcher};
+use rust_components::control_api::ControlState;
+use ethers::types::U256;
+use std::path::Path;
+
+const EXAMPLE: &str = include_str!("../config.example.toml");
+
+fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+    vars.iter().map(|(var, value)| (var.to_string(), value.to_string())).collect()
+}
+
+#[test]
+fn test_example_config_with_env_overrides() {
+    let config = Config::parse(
+        EXAMPLE,
+        "example",
+        env(&[
+            ("ARB__THRESHOLDS__MIN_PROFIT", "0.5"),
+            ("ARB__CHAINS__MAINNET__RPC_URLS", r#"["https://eth.example/v2/key", "http://127.0.0.1:8545"]"#),
+            ("ARB__BOT__RUN_SECS", "60"),
+            ("RUST_LOG", "debug"),
+        ]),
+    )
+    .unwrap();
+
+    assert_eq!(config.thresholds.min_profit, 0.5);
+    assert_eq!(config.bot.run_secs, Some(60));
+    let mainnet = config.chain("mainnet").unwrap();
+    assert_eq!(mainnet.rpc_url(), "https://eth.example/v2/key");
+    assert_eq!(mainnet.token("USDC").unwrap().decimals, 6);
+    assert_eq!(mainnet.pool_addresses().len(), 2);
+    assert_eq!(mainnet.token("USDC").unwrap().max_position_size(), Some(U256::from(50_000) * U256::exp10(6)));
+}
+
+#[test]
+fn test_validation_reports_every_problem() {
+    let text = r#"
+        [thresholds]
+        min_profit = -1.0
+
+        [[chains]]
+        name = "mainnet"
+        chain_id = 1
+        rpc_urls = ["127.0.0.1:8545"]
+
+        [[chains.pools]]
+        dex = "uniswap"
+        address = "0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"
+        pair = "WETH/USDC"
+    "#;
+    let Err(ConfigError::Invalid(problems)) = Config::parse(text, "test", env(&[])) else {
+        panic!("expected validation to fail");
+    };
+    assert_eq!(problems.len(), 4, "{:?}", problems);
+    assert!(problems[0].contains("thresholds.min_profit"));
+    assert!(problems[1].contains("must start with http(s)://"));
+    assert!(problems[2].contains("unknown token WETH"));
+
+    let typo = Config::parse("[thresholds]\nmin_proft = 1.0", "test", env(&[])).unwrap_err();
+    assert!(typo.to_string().contains("unknown field `min_proft`"), "{}", typo);
+    let missing = Config::parse(EXAMPLE, "example", env(&[("ARB__CHAINS__GOERLI__CHAIN_ID", "5")])).unwrap_err();
+    assert!(matches!(missing, ConfigError::Env { .. }), "{}", missing);
+}
+
+fn rewrite(path: &Path, text: &str) {
+    std::fs::write(path, text).unwrap();
+    // Filesystems with coarse timestamps need the mtime to move
+    let later = std::time::SystemTime::now() + std::time::Duration::from_secs(2);
+    std::fs::File::options().write(true).open(path).unwrap().set_modified(later).unwrap();
+}
+
+#[test]
+fn test_watcher_reloads_thresholds_only() {
+    let path = std::env::temp_dir().join(format!("config_test_{}.toml", std::process::id()));
+    std::fs::write(&path, EXAMPLE).unwrap();
+    let mut watcher = ConfigWatcher::new(&path, Config::load(&path).unwrap());
+    assert!(watcher.check().unwrap().is_none());
+
+    rewrite(&path, &EXAMPLE.replace("min_profit = 0.01", "min_profit = 2.0"));
+    let control = ControlState::new();
+    watcher.check().unwrap().unwrap().apply_to_control(&control);
+    assert_eq!(control.thresholds().min_profit, 2.0);
+
+    rewrite(&path, &EXAMPLE.replace("chain_id = 1", "chain_id = 10"));
+    let error = watcher.check().unwrap_err();
+    assert!(matches!(error, ConfigError::Structural(ref sections) if sections == &["chains"]), "{}", error);
+    assert_eq!(watcher.current().thresholds.min_profit, 2.0);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_chains_have_their_own_native_token_and_block_time() {
+    let arbitrum = r#"
+        [[chains]]
+        name = "arbitrum"
+        chain_id = 42161
+        rpc_urls = ["http://127.0.0.1:8547"]
+        uniswap_router = "0x1b02dA8Cb0d097eB8D57A175b88c7D8b47997506"
+        block_time_ms = 250
+    "#;
+    let Err(ConfigError::Invalid(problems)) = Config::parse(&format!("{}{}", EXAMPLE, arbitrum), "test", env(&[])) else {
+        panic!("expected validation to fail");
+    };
+    assert_eq!(problems, vec!["chain arbitrum: wrapped_native is required to trade off mainnet".to_string()]);
+
+    let arbitrum = arbitrum.replace("block_time_ms", "wrapped_native = \"0x82aF49447D8a07e3bd95BD0d56f35241523fBab1\"\nblock_time_ms");
+    let config = Config::parse(&format!("{}{}", EXAMPLE, arbitrum), "test", env(&[])).unwrap();
+    let (mainnet, arbitrum) = (config.chain("mainnet").unwrap(), config.chain("arbitrum").unwrap());
+    assert_eq!(mainnet.wrapped_native(), Some("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2".parse().unwrap()));
+    assert_eq!(arbitrum.wrapped_native(), Some("0x82aF49447D8a07e3bd95BD0d56f35241523fBab1".parse().unwrap()));
+    // Never faster than poll_interval_ms, never faster than a block
+    assert_eq!(arbitrum.poll_interval(&config.bot), std::time::Duration::from_secs(10));
+    assert_eq!(mainnet.poll_interval(&config.bot), std::time::Duration::from_secs(12));
+}
+
+#[test]
+fn test_each_chain_has_its_own_limits() {
+    let arbitrum = r#"
+        [[chains]]
+        name = "arbitrum"
+        chain_id = 42161
+        rpc_urls = ["http://127.0.0.1:8547"]
+        wrapped_native = "0x82aF49447D8a07e3bd95BD0d56f35241523fBab1"
+        max_daily_loss = 200.0
+        max_gas_per_hour = "50000000000000000"
+
+        [thresholds.chain_min_profit]
+        arbitrum = 0.002
+    "#;
+    let config = Config::parse(&format!("{}{}", EXAMPLE, arbitrum), "test", env(&[("ARB__RISK__STATE_PATH", "/var/lib/arbitrage/breaker.json")])).unwrap();
+    let (mainnet, arbitrum) = (config.chain("mainnet").unwrap(), config.chain("arbitrum").unwrap());
+
+    assert_eq!(config.thresholds.min_profit_for("mainnet"), 0.01);
+    assert_eq!(config.thresholds.min_profit_for("arbitrum"), 0.002);
+    let (mainnet, arbitrum) = (config.risk.circuit_breaker_config(mainnet), config.risk.circuit_breaker_config(arbitrum));
+    assert_eq!((mainnet.max_daily_loss, arbitrum.max_daily_loss), (1_000.0, 200.0));
+    assert_eq!((mainnet.max_gas_per_hour, arbitrum.max_gas_per_hour), (U256::exp10(18), U256::from(5) * U256::exp10(16)));
+    assert_eq!(mainnet.state_path.unwrap(), Path::new("/var/lib/arbitrage/breaker-mainnet.json"));
+    assert_eq!(arbitrum.state_path.unwrap(), Path::new("/var/lib/arbitrage/breaker-arbitrum.json"));
+
+    let Err(ConfigError::Invalid(problems)) = Config::parse(&format!("{}\n[thresholds.chain_min_profit]\ngoerli = 1.0", EXAMPLE), "test", env(&[])) else {
+        panic!("expected validation to fail");
+    };
+    assert_eq!(problems, vec!["thresholds.chain_min_profit names unknown chain goerli".to_string()]);
+}
@@ -0,0 +1,103 @@
+use rust_components::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig};
+use rust_components::control_api::{self, route, ControlState};
+use rust_components::inventory::{Inventory, Location};
+use rust_components::main_module::ArbitrageBot;
+use rust_components::order::Order;
+use ethers::types::{Address, U256};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+fn bot_with_books(control: &Arc<ControlState>) -> ArbitrageBot {
+    let mut bot = ArbitrageBot::new();
+    bot.set_control(control.clone());
+    bot.update_order_books("Exchange1", vec![Order::new(102.0, 1.0).unwrap()], vec![Order::new(103.0, 1.0).unwrap()]);
+    bot.update_order_books("Exchange2", vec![Order::new(98.0, 1.0).unwrap()], vec![Order::new(99.0, 1.0).unwrap()]);
+    bot
+}
+
+#[test]
+fn test_bot_obeys_pause_and_thresholds() {
+    let control = Arc::new(ControlState::new());
+    let mut bot = bot_with_books(&control);
+
+    bot.run("Exchange1", "Exchange2", Duration::from_millis(1));
+    let opportunities = control.recent_opportunities(10);
+    assert_eq!(opportunities.len(), 1);
+    assert_eq!((opportunities[0].buy_dex.as_str(), opportunities[0].sell_dex.as_str()), ("Exchange2", "Exchange1"));
+
+    let (status, _) = route(&control, "PUT", "/thresholds", r#"{"min_profit": 10.0}"#);
+    assert_eq!(status, 200);
+    bot.run("Exchange1", "Exchange2", Duration::from_millis(1));
+    assert_eq!(control.recent_opportunities(10).len(), 1);
+
+    route(&control, "PUT", "/thresholds", r#"{"min_profit": 0.0}"#);
+    route(&control, "POST", "/pause", "");
+    bot.run("Exchange1", "Exchange2", Duration::from_millis(1));
+    assert_eq!(control.recent_opportunities(10).len(), 1);
+
+    route(&control, "POST", "/resume", "");
+    bot.run("Exchange1", "Exchange2", Duration::from_millis(1));
+    assert_eq!(control.recent_opportunities(10).len(), 2);
+
+    let (status, books) = route(&control, "GET", "/books", "");
+    assert_eq!(status, 200);
+    assert_eq!(books["books"][0]["venue"], "Exchange1");
+    assert_eq!(books["books"][0]["best_bid"], 102.0);
+    assert!(books["books"][0]["staleness_secs"].as_f64().unwrap() < 5.0);
+}
+
+#[test]
+fn test_status_reports_inventory_value() {
+    let token = Address::repeat_byte(1);
+    let mut inventory = Inventory::new(Address::zero());
+    inventory.set_balance(Location::Wallet(Address::zero()), token, U256::exp10(18) * 2);
+    let inventory = Arc::new(Mutex::new(inventory));
+    let control = ControlState::new();
+    control.add_inventory("mainnet", inventory.clone());
+
+    let (_, status) = route(&control, "GET", "/status", "");
+    assert_eq!(status["inventories"]["mainnet"]["total_value"], serde_json::Value::Null);
+
+    inventory.lock().unwrap().set_price(token, 1500.0);
+    let (_, status) = route(&control, "GET", "/status", "");
+    assert_eq!(status["inventories"]["mainnet"]["total_value"], 3000.0);
+    assert_eq!(status["inventories"]["mainnet"]["exposure"][format!("{:?}", token)], 3000.0);
+    assert_eq!(status["inventories"]["mainnet"]["realized_pnl"], 0.0);
+}
+
+#[test]
+fn test_route_rejects_bad_requests() {
+    let control = ControlState::new();
+    assert_eq!(route(&control, "PUT", "/thresholds", r#"{"min_profit": -1.0}"#).0, 400);
+    assert_eq!(route(&control, "PUT", "/thresholds", r#"{"chain_min_profit": {"mainnet": -1.0}}"#).0, 400);
+    assert_eq!(route(&control, "PUT", "/thresholds", r#"{"max_loss": 1.0}"#).0, 400);
+    assert_eq!(route(&control, "GET", "/opportunities?limit=lots", "").0, 400);
+    assert_eq!(route(&control, "DELETE", "/pause", "").0, 405);
+    assert_eq!(route(&control, "GET", "/nope", "").0, 404);
+}
+
+#[tokio::test]
+async fn test_server_requires_token_and_kills() {
+    let breaker = Arc::new(CircuitBreaker::new(CircuitBreakerConfig::default()));
+    let mut control = ControlState::new();
+    control.add_circuit_breaker("mainnet", breaker.clone());
+    let server = control_api::serve("127.0.0.1:0", "s3cret", Arc::new(control)).unwrap();
+    let url = format!("http://{}", server.addr);
+    let client = reqwest::Client::new();
+
+    let response = client.get(format!("{}/status", url)).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+    let response = client.get(format!("{}/status", url)).bearer_auth("wrong!").send().await.unwrap();
+    assert_eq!(response.status(), 401);
+
+    let response = client.post(format!("{}/kill", url)).bearer_auth("s3cret").send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let status: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(status["paused"], true);
+    assert_eq!(status["circuit_breakers"]["mainnet"]["reason"], "kill switch engaged by control API");
+    assert!(breaker.is_tripped());
+
+    let response = client.put(format!("{}/thresholds", url)).bearer_auth("s3cret").body(r#"{"max_book_age_secs": 5}"#).send().await.unwrap();
+    let thresholds: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(thresholds["max_book_age_secs"], 5);
+}
@@ -0,0 +1,16 @@
+use rust_components::check_arbitrage;
+use std::ffi::CString;
+
+#[test]
+fn test_check_arbitrage() {
+    let exchange1 = CString::new(r#"{"bids": [[100.0, 5.0]], "asks": [[101.0, 5.0]]}"#).unwrap();
+    let exchange2 = CString::new(r#"{"bids": [[98.0, 5.0]], "asks": [[99.0, 5.0]]}"#).unwrap();
+    let malformed = CString::new(r#"{"bids": [[-1.0, 5.0]], "asks": []}"#).unwrap();
+
+    unsafe {
+        assert!(check_arbitrage(exchange1.as_ptr(), exchange2.as_ptr()));
+        assert!(!check_arbitrage(exchange2.as_ptr(), exchange1.as_ptr()));
+        assert!(!check_arbitrage(malformed.as_ptr(), exchange2.as_ptr()));
+        assert!(!check_arbitrage(std::ptr::null(), exchange2.as_ptr()));
+    }
+}
@@ -0,0 +1,83 @@
+use rust_components::flash_loan::{check_flash_loan_profit, flash_loan_premium, FlashLoanError, FlashLoanParams};
+use ethers::abi::param_type::Reader;
+use ethers::abi::{decode, ParamType, Token};
+use ethers::types::{Address, Bytes, U256};
+
+fn params() -> FlashLoanParams {
+    FlashLoanParams::new(
+        vec![Address::from_low_u64_be(1), Address::from_low_u64_be(2), Address::from_low_u64_be(1)],
+        U256::from(1_005_000u64),
+        U256::from(1_700_000_000u64),
+    )
+}
+
+#[test]
+fn test_params_round_trip() {
+    let params = params();
+    assert_eq!(FlashLoanParams::decode(&params.encode()).unwrap(), params);
+}
+
+/// Types of the `abi.decode(params, (...))` in the contract's `executeOperation`.
+fn receiver_param_types() -> Vec<ParamType> {
+    let source = include_str!("../../contracts/ConcreteArbitrageBot.sol");
+    let start = source.find("abi.decode(params, (").expect("executeOperation decodes params") + "abi.decode(params, (".len();
+    let end = start + source[start..].find("))").unwrap();
+    source[start..end].split(',').map(|ty| Reader::read(ty.trim()).unwrap()).collect()
+}
+
+#[test]
+fn test_params_match_receiver_abi() {
+    let tokens = decode(&receiver_param_types(), &params().encode()).unwrap();
+
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Array(params().path.into_iter().map(Token::Address).collect()),
+            Token::Uint(U256::from(1_005_000u64)),
+            Token::Uint(U256::from(1_700_000_000u64)),
+        ]
+    );
+}
+
+#[test]
+fn test_params_must_round_trip_the_asset() {
+    let (asset, other) = (Address::from_low_u64_be(1), Address::from_low_u64_be(2));
+    assert_eq!(params().check_round_trip(asset), Ok(()));
+    assert_eq!(params().check_round_trip(other), Err(FlashLoanError::NotRoundTrip { asset: other }));
+
+    let one_way = FlashLoanParams::new(vec![asset, other], U256::zero(), U256::zero());
+    assert_eq!(one_way.check_round_trip(asset), Err(FlashLoanError::NotRoundTrip { asset }));
+    let empty = FlashLoanParams::new(vec![asset], U256::zero(), U256::zero());
+    assert!(empty.check_round_trip(asset).is_err());
+}
+
+#[test]
+fn test_decode_invalid_params() {
+    let result = FlashLoanParams::decode(&Bytes::from(vec![0u8; 10]));
+    assert!(matches!(result, Err(FlashLoanError::InvalidParams(_))));
+}
+
+#[test]
+fn test_flash_loan_premium() {
+    // Aave V3 charges 5 bps by default
+    assert_eq!(flash_loan_premium(U256::from(1_000_000u64), U256::from(5u64)), U256::from(500u64));
+    // Rounds half up like `percentMul`
+    assert_eq!(flash_loan_premium(U256::from(1_000u64), U256::from(5u64)), U256::from(1u64));
+    assert_eq!(flash_loan_premium(U256::from(999u64), U256::from(5u64)), U256::zero());
+}
+
+#[test]
+fn test_profit_must_cover_premium_and_costs() {
+    let amount = U256::from(1_000_000u64);
+    let premium = U256::from(500u64);
+
+    assert_eq!(
+        check_flash_loan_profit(amount, U256::from(1_002_000u64), premium, U256::from(1_000u64)),
+        Ok(U256::from(500u64))
+    );
+    assert_eq!(
+        check_flash_loan_profit(amount, U256::from(1_001_000u64), premium, U256::from(500u64)),
+        Err(FlashLoanError::Unprofitable { expected_profit: U256::from(500u64), premium, costs: U256::from(500u64) })
+    );
+    assert!(check_flash_loan_profit(amount, U256::from(999_000u64), premium, U256::zero()).is_err());
+}
@@ -0,0 +1,125 @@
+use rust_components::gas_strategy::{GasFees, GasStrategy, GasStrategyError};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Eip1559TransactionRequest, FeeHistory, U256};
+
+fn gwei(amount: u64) -> U256 {
+    U256::from(amount) * U256::exp10(9)
+}
+
+fn fee_history(base_fees: &[u64], rewards: &[u64]) -> FeeHistory {
+    FeeHistory {
+        base_fee_per_gas: base_fees.iter().map(|fee| gwei(*fee)).collect(),
+        gas_used_ratio: vec![0.5; rewards.len()],
+        oldest_block: U256::from(100u64),
+        reward: rewards.iter().map(|reward| vec![gwei(*reward)]).collect(),
+    }
+}
+
+#[test]
+fn test_fees_from_history() {
+    let strategy = GasStrategy::default();
+    let history = fee_history(&[10, 11, 12, 14], &[1, 3, 2]);
+
+    let fees = strategy.fees_from_history(&history).unwrap();
+    assert_eq!(
+        fees,
+        GasFees::Eip1559 {
+            base_fee_per_gas: gwei(14),
+            max_fee_per_gas: gwei(30),
+            max_priority_fee_per_gas: gwei(2),
+        }
+    );
+}
+
+#[test]
+fn test_fees_from_history_without_base_fee() {
+    let strategy = GasStrategy::default();
+    assert_eq!(strategy.fees_from_history(&fee_history(&[], &[1])), None);
+    assert_eq!(strategy.fees_from_history(&fee_history(&[0, 0], &[1])), None);
+}
+
+#[test]
+fn test_cap_to_profit_within_budget() {
+    let strategy = GasStrategy::new(10, 50.0, 0.5, 0.2);
+    let fees = GasFees::Eip1559 {
+        base_fee_per_gas: gwei(10),
+        max_fee_per_gas: gwei(22),
+        max_priority_fee_per_gas: gwei(2),
+    };
+
+    // 22 gwei * 100_000 gas = 2_200_000 gwei, budget is half of 10_000_000 gwei
+    let capped = strategy.cap_to_profit(fees, U256::from(100_000u64), gwei(10_000_000)).unwrap();
+    assert_eq!(capped, fees);
+}
+
+#[test]
+fn test_cap_to_profit_lowers_max_fee() {
+    let strategy = GasStrategy::new(10, 50.0, 0.5, 0.2);
+    let fees = GasFees::Eip1559 {
+        base_fee_per_gas: gwei(10),
+        max_fee_per_gas: gwei(22),
+        max_priority_fee_per_gas: gwei(2),
+    };
+
+    // The budget of 1_300_000 gwei pays 13 gwei for 100_000 gas, above the expected 12
+    let capped = strategy.cap_to_profit(fees, U256::from(100_000u64), gwei(2_600_000)).unwrap();
+    assert_eq!(
+        capped,
+        GasFees::Eip1559 {
+            base_fee_per_gas: gwei(10),
+            max_fee_per_gas: gwei(13),
+            max_priority_fee_per_gas: gwei(2),
+        }
+    );
+}
+
+#[test]
+fn test_cap_to_profit_over_budget() {
+    let strategy = GasStrategy::new(10, 50.0, 0.5, 0.2);
+    let eip1559 = GasFees::Eip1559 {
+        base_fee_per_gas: gwei(10),
+        max_fee_per_gas: gwei(22),
+        max_priority_fee_per_gas: gwei(2),
+    };
+    let legacy = GasFees::Legacy { gas_price: gwei(22) };
+
+    let result = strategy.cap_to_profit(eip1559, U256::from(100_000u64), gwei(1_000_000));
+    assert!(matches!(result, Err(GasStrategyError::OverBudget { .. })));
+    let result = strategy.cap_to_profit(legacy, U256::from(100_000u64), gwei(1_000_000));
+    assert!(matches!(result, Err(GasStrategyError::OverBudget { .. })));
+}
+
+#[test]
+fn test_cap_to_profit_same_policy_for_legacy_fees() {
+    let strategy = GasStrategy::new(10, 50.0, 0.5, 0.2);
+    let eip1559 = GasFees::Eip1559 {
+        base_fee_per_gas: gwei(10),
+        max_fee_per_gas: gwei(22),
+        max_priority_fee_per_gas: gwei(2),
+    };
+    let legacy = GasFees::Legacy { gas_price: gwei(12) };
+    assert_eq!(eip1559.expected_gas_price(), legacy.expected_gas_price());
+
+    // Both expect to pay 12 gwei, so both fit a 13 gwei budget and neither fits 11
+    assert!(strategy.cap_to_profit(eip1559, U256::from(100_000u64), gwei(2_600_000)).is_ok());
+    assert_eq!(strategy.cap_to_profit(legacy, U256::from(100_000u64), gwei(2_600_000)).unwrap(), legacy);
+    let result = strategy.cap_to_profit(eip1559, U256::from(100_000u64), gwei(2_200_000));
+    assert!(matches!(result, Err(GasStrategyError::OverBudget { .. })));
+    let result = strategy.cap_to_profit(legacy, U256::from(100_000u64), gwei(2_200_000));
+    assert!(matches!(result, Err(GasStrategyError::OverBudget { .. })));
+}
+
+#[test]
+fn test_gas_limit_with_margin() {
+    let strategy = GasStrategy::new(10, 50.0, 0.5, 0.25);
+    assert_eq!(strategy.gas_limit_with_margin(U256::from(200_000u64)), U256::from(250_000u64));
+}
+
+#[test]
+fn test_apply_legacy_fees_converts_transaction() {
+    let mut tx = TypedTransaction::Eip1559(Eip1559TransactionRequest::new());
+    GasFees::Legacy { gas_price: gwei(30) }.apply(&mut tx);
+
+    assert!(matches!(tx, TypedTransaction::Legacy(_)));
+    assert_eq!(tx.gas_price(), Some(gwei(30)));
+}
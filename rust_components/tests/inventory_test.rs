@@ -0,0 +1,179 @@
+use rust_components::inventory::{round_trip_pnl, Discrepancy, Inventory, InventoryError, Location, TransferFilter, NATIVE_CURRENCY};
+use rust_components::paper_executor::{PaperFill, PaperTradeKind};
+use rust_components::risk_manager::{PoolReserves, SizingRequest};
+use ethers::abi::{encode, Token};
+use ethers::contract::EthEvent;
+use ethers::types::{Address, Log, TransactionReceipt, H256, I256, U256};
+use std::collections::HashMap;
+
+fn ether(amount: u64) -> U256 {
+    U256::from(amount) * U256::exp10(18)
+}
+
+fn weth() -> Address {
+    Address::from_low_u64_be(1)
+}
+
+fn dai() -> Address {
+    Address::from_low_u64_be(2)
+}
+
+fn wallet() -> Address {
+    Address::from_low_u64_be(100)
+}
+
+fn inventory() -> Inventory {
+    let mut inventory = Inventory::new(weth());
+    inventory.track_wallet(wallet());
+    inventory.set_price(weth(), 2_000.0);
+    inventory.set_price(dai(), 1.0);
+    inventory.set_balance(Location::Wallet(wallet()), weth(), ether(10));
+    inventory.set_balance(Location::Wallet(wallet()), NATIVE_CURRENCY, ether(1));
+    inventory
+}
+
+fn transfer_log(token: Address, from: Address, to: Address, value: U256) -> Log {
+    Log {
+        address: token,
+        topics: vec![TransferFilter::signature(), H256::from(from), H256::from(to)],
+        data: encode(&[Token::Uint(value)]).into(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_debit_checks_balance() {
+    let mut inventory = inventory();
+    let location = Location::Wallet(wallet());
+
+    assert!(inventory.debit(&location, weth(), ether(4)).is_ok());
+    assert_eq!(inventory.balance(&location, weth()), ether(6));
+    assert_eq!(
+        inventory.debit(&location, weth(), ether(7)),
+        Err(InventoryError::Insufficient { location, token: weth(), available: ether(6), required: ether(7) })
+    );
+}
+
+#[test]
+fn test_apply_fill_updates_exposure_and_pnl() {
+    let mut inventory = inventory();
+    let venue = Location::Venue("Binance".to_string());
+    inventory.set_balance(venue.clone(), weth(), ether(1));
+
+    // Sell 1 WETH for 2010 DAI on the venue, no gas
+    inventory.apply_fill(&venue, weth(), ether(1), dai(), ether(2_010), U256::zero()).unwrap();
+
+    let exposure = inventory.exposure().unwrap();
+    assert!((exposure[&weth()] - 20_000.0).abs() < 1e-6);
+    assert!((exposure[&dai()] - 2_010.0).abs() < 1e-6);
+    assert!((inventory.realized_pnl() - 10.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_apply_fill_is_all_or_nothing() {
+    let mut inventory = inventory();
+    let location = Location::Wallet(wallet());
+
+    // The swap is covered but the gas is not
+    let error = inventory.apply_fill(&location, weth(), ether(1), dai(), ether(2_000), ether(2)).unwrap_err();
+    assert_eq!(error, InventoryError::Insufficient { location: location.clone(), token: NATIVE_CURRENCY, available: ether(1), required: ether(2) });
+    assert_eq!(inventory.balance(&location, weth()), ether(10));
+    assert_eq!(inventory.balance(&location, dai()), U256::zero());
+    assert_eq!(inventory.realized_pnl(), 0.0);
+}
+
+#[test]
+fn test_value_of_large_amounts() {
+    let inventory = inventory();
+    assert!(inventory.value(dai(), U256::MAX).unwrap() > 1e59);
+    // Gas is valued at the wrapped token's price
+    assert!((inventory.value(NATIVE_CURRENCY, ether(1)).unwrap() - 2_000.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_apply_paper_fill_charges_gas() {
+    let mut inventory = inventory();
+    let fill = PaperFill {
+        transaction_hash: H256::zero(),
+        kind: PaperTradeKind::Swap,
+        path: vec![weth(), dai(), weth()],
+        amount_in: ether(1),
+        amount_out: ether(1) + U256::exp10(16),
+        gas_used: U256::from(100_000u64),
+        gas_price: U256::from(10_000_000_000u64),
+        success: true,
+        pnl: I256::from(10_000_000_000_000_000i64),
+    };
+    inventory.apply_paper_fill(wallet(), &fill).unwrap();
+
+    // +0.01 WETH profit, -0.001 ETH gas
+    assert_eq!(inventory.balance(&Location::Wallet(wallet()), weth()), ether(10) + U256::exp10(16));
+    assert_eq!(inventory.balance(&Location::Wallet(wallet()), NATIVE_CURRENCY), ether(1) - U256::exp10(15));
+    assert!((inventory.realized_pnl() - 18.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_apply_receipt_transfers_and_gas() {
+    let mut inventory = inventory();
+    let pool = Address::from_low_u64_be(200);
+    let receipt = TransactionReceipt {
+        from: wallet(),
+        gas_used: Some(U256::from(100_000u64)),
+        effective_gas_price: Some(U256::from(10_000_000_000u64)),
+        logs: vec![
+            transfer_log(weth(), wallet(), pool, ether(1)),
+            transfer_log(dai(), pool, wallet(), ether(2_000)),
+            transfer_log(dai(), pool, Address::from_low_u64_be(300), ether(5)),
+        ],
+        ..Default::default()
+    };
+    inventory.apply_receipt(&receipt);
+
+    let location = Location::Wallet(wallet());
+    assert_eq!(inventory.balance(&location, weth()), ether(9));
+    assert_eq!(inventory.balance(&location, NATIVE_CURRENCY), ether(1) - U256::exp10(15));
+    assert_eq!(inventory.balance(&location, dai()), ether(2_000));
+    // Only the 0.001 ETH of gas was lost at these prices
+    assert!((inventory.realized_pnl() + 2.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_round_trip_pnl_nets_gas_in_native_token() {
+    let bot = Address::from_low_u64_be(200);
+    let mut receipt = TransactionReceipt {
+        from: wallet(),
+        gas_used: Some(U256::from(100_000u64)),
+        effective_gas_price: Some(U256::from(10_000_000_000u64)),
+        logs: vec![
+            transfer_log(weth(), wallet(), bot, ether(10)),
+            transfer_log(dai(), bot, Address::from_low_u64_be(300), ether(20_000)),
+            transfer_log(weth(), bot, wallet(), ether(11)),
+        ],
+        ..Default::default()
+    };
+    let profit = I256::from_raw(ether(1) - U256::exp10(15));
+    assert_eq!(round_trip_pnl(&receipt, weth()), Some((weth(), profit)));
+    assert_eq!(round_trip_pnl(&receipt, dai()), Some((weth(), I256::from_raw(ether(1)))));
+
+    receipt.logs.push(transfer_log(dai(), bot, wallet(), ether(1)));
+    assert_eq!(round_trip_pnl(&receipt, weth()), None);
+}
+
+#[test]
+fn test_reconcile_snapshot() {
+    let mut inventory = inventory();
+    let location = Location::Wallet(wallet());
+    let observed: HashMap<Address, U256> = [(weth(), ether(9)), (dai(), U256::zero())].into_iter().collect();
+
+    let discrepancies = inventory.reconcile_snapshot(&location, &observed);
+    assert_eq!(discrepancies, vec![Discrepancy { location: location.clone(), token: weth(), tracked: ether(10), observed: ether(9) }]);
+    assert_eq!(inventory.balance(&location, weth()), ether(9));
+}
+
+#[test]
+fn test_sizing_request_from_inventory() {
+    let inventory = inventory();
+    let pool = PoolReserves::new(ether(1_000), ether(2_000_000));
+    let request = SizingRequest::from_inventory(&inventory, &Location::Wallet(wallet()), weth(), pool, pool);
+    assert_eq!(request.available_balance, ether(10));
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "metrics")]
+
+use rust_components::arbitrage_finder::ArbitrageOpportunity;
+use rust_components::metrics;
+use rust_components::opportunity_tracker::{OpportunityState, OpportunityTracker};
+use ethers::types::H256;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+#[test]
+fn test_render_includes_recorded_metrics() {
+    metrics::opportunity_found();
+    metrics::opportunity_rejected();
+    metrics::observe_rpc_latency("getReserves", Duration::from_millis(30));
+    metrics::record_book_update("Exchange1");
+    metrics::add_gas_spent(21_000u64.into());
+
+    let rendered = metrics::render();
+    assert!(rendered.contains("arbitrage_opportunities_total{outcome=\"found\"}"));
+    assert!(rendered.contains("arbitrage_opportunities_total{outcome=\"rejected\"}"));
+    assert!(rendered.contains("arbitrage_rpc_latency_seconds_count{method=\"getReserves\"}"));
+    assert!(rendered.contains("arbitrage_order_book_staleness_seconds{venue=\"Exchange1\"}"));
+    assert!(rendered.contains("arbitrage_gas_spent_wei_total"));
+}
+
+#[test]
+fn test_serves_metrics_endpoint() {
+    metrics::opportunity_executed();
+    let server = metrics::serve("127.0.0.1:0").unwrap();
+
+    let mut stream = TcpStream::connect(server.addr).unwrap();
+    stream.write_all(b"GET /metrics HTTP/1.0\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200") || response.starts_with("HTTP/1.0 200"));
+    assert!(response.contains("arbitrage_opportunities_total{outcome=\"executed\"}"));
+}
+
+fn sample(rendered: &str, series: &str) -> f64 {
+    rendered.lines().find_map(|line| line.strip_prefix(series)?.trim().parse().ok()).unwrap_or_default()
+}
+
+#[test]
+fn test_lifecycle_feeds_rejections_and_submit_latency() {
+    let rejected = "arbitrage_opportunities_total{outcome=\"rejected\"}";
+    let submitted = "arbitrage_detection_to_submit_seconds_count";
+    let before = metrics::render();
+    let tracker = OpportunityTracker::new();
+    let (refused, sent) = (ArbitrageOpportunity::new("Uniswap", "Sushiswap", 1.0), ArbitrageOpportunity::new("Uniswap", "Sushiswap", 1.0));
+    tracker.detect(refused.clone());
+    tracker.detect(sent.clone());
+
+    tracker.transition(&refused.id, OpportunityState::RiskRejected("breaker tripped".to_string())).unwrap();
+    tracker.transition(&sent.id, OpportunityState::RiskApproved).unwrap();
+    tracker.transition(&sent.id, OpportunityState::Submitted(H256::zero())).unwrap();
+
+    let after = metrics::render();
+    assert!(sample(&after, rejected) >= sample(&before, rejected) + 1.0);
+    assert_eq!(sample(&after, submitted), sample(&before, submitted) + 1.0);
+}
@@ -0,0 +1,93 @@
+use rust_components::arbitrage_finder::ArbitrageOpportunity;
+use rust_components::opportunity_tracker::{LifecycleError, OpportunityState, OpportunityTracker};
+use chrono::Duration;
+use ethers::types::H256;
+
+fn tracked(tracker: &OpportunityTracker) -> ArbitrageOpportunity {
+    let opportunity = ArbitrageOpportunity::new("Uniswap", "Sushiswap", 1.5).with_block_number(100);
+    tracker.detect(opportunity.clone());
+    opportunity
+}
+
+#[test]
+fn test_full_lifecycle() {
+    let tracker = OpportunityTracker::new();
+    let opportunity = tracked(&tracker);
+    let hash = H256::from_low_u64_be(1);
+    let submitted_at = opportunity.detected_at + Duration::milliseconds(250);
+
+    tracker.transition(&opportunity.id, OpportunityState::RiskApproved).unwrap();
+    tracker.transition(&opportunity.id, OpportunityState::Simulated).unwrap();
+    tracker.transition_at(&opportunity.id, OpportunityState::Submitted(hash), submitted_at).unwrap();
+    tracker.transition(&opportunity.id, OpportunityState::Confirmed(hash)).unwrap();
+
+    let record = tracker.get(&opportunity.id).unwrap();
+    assert_eq!(record.state(), &OpportunityState::Confirmed(hash));
+    assert_eq!(record.transitions.len(), 5);
+    assert_eq!(record.opportunity.block_number, Some(100));
+    assert_eq!(record.detection_to_submit(), Some(Duration::milliseconds(250)));
+    assert_eq!(tracker.detection_to_submit_latencies(), vec![Duration::milliseconds(250)]);
+}
+
+#[test]
+fn test_invalid_transitions() {
+    let tracker = OpportunityTracker::new();
+    let opportunity = tracked(&tracker);
+
+    assert!(matches!(
+        tracker.transition(&opportunity.id, OpportunityState::Submitted(H256::zero())),
+        Err(LifecycleError::InvalidTransition { .. })
+    ));
+    tracker.transition(&opportunity.id, OpportunityState::RiskRejected("too small".to_string())).unwrap();
+    assert!(tracker.transition(&opportunity.id, OpportunityState::RiskApproved).is_err());
+    assert_eq!(
+        tracker.transition("missing", OpportunityState::RiskApproved),
+        Err(LifecycleError::UnknownOpportunity("missing".to_string()))
+    );
+}
+
+#[test]
+fn test_queries_and_failure_reasons() {
+    let tracker = OpportunityTracker::new();
+    let rejected = tracked(&tracker);
+    let failed = tracked(&tracker);
+    let pending = tracked(&tracker);
+    let submitted = tracked(&tracker);
+
+    tracker.transition(&rejected.id, OpportunityState::RiskRejected("too small".to_string())).unwrap();
+    tracker.transition(&submitted.id, OpportunityState::RiskApproved).unwrap();
+    tracker.transition(&submitted.id, OpportunityState::Submitted(H256::zero())).unwrap();
+    tracker.transition(&failed.id, OpportunityState::RiskApproved).unwrap();
+    tracker.transition(&failed.id, OpportunityState::SimulationFailed("No profit".to_string())).unwrap();
+
+    assert_eq!(tracker.in_state("detected").len(), 1);
+    assert_eq!(tracker.in_state("detected")[0].opportunity.id, pending.id);
+
+    let reasons = tracker.failure_reasons();
+    assert_eq!(reasons["risk_rejected (too small)"], 1);
+    assert_eq!(reasons["simulation_failed (No profit)"], 1);
+
+    tracker.prune(chrono::Utc::now() + Duration::seconds(1));
+    assert!(tracker.get(&rejected.id).is_none());
+    assert!(tracker.get(&pending.id).is_none());
+    assert!(tracker.get(&submitted.id).is_some());
+}
+
+#[test]
+fn test_failures_after_approval() {
+    let tracker = OpportunityTracker::new();
+    let over_budget = tracked(&tracker);
+    let dropped = tracked(&tracker);
+
+    tracker.transition(&over_budget.id, OpportunityState::RiskApproved).unwrap();
+    tracker.transition(&over_budget.id, OpportunityState::Simulated).unwrap();
+    tracker.transition(&over_budget.id, OpportunityState::SendFailed("over gas budget".to_string())).unwrap();
+    tracker.transition(&dropped.id, OpportunityState::RiskApproved).unwrap();
+    tracker.transition(&dropped.id, OpportunityState::Submitted(H256::zero())).unwrap();
+    tracker.transition(&dropped.id, OpportunityState::Dropped("stuck".to_string())).unwrap();
+
+    assert!(tracker.transition(&dropped.id, OpportunityState::Confirmed(H256::zero())).is_err());
+    let reasons = tracker.failure_reasons();
+    assert_eq!(reasons["send_failed (over gas budget)"], 1);
+    assert_eq!(reasons["dropped (stuck)"], 1);
+}
@@ -0,0 +1,60 @@
+use rust_components::oracle::{check_deviation, deviation_bps, OracleError, OracleGuard, OracleRound};
+use ethers::types::{Address, I256};
+
+const NOW: u64 = 1_700_000_000;
+
+fn round(answer: i64, updated_at: u64) -> OracleRound {
+    OracleRound {
+        feed: Address::from_low_u64_be(1),
+        round_id: 10,
+        answer: I256::from(answer),
+        decimals: 8,
+        updated_at,
+        answered_in_round: 10,
+    }
+}
+
+#[test]
+fn test_validate_fresh_round() {
+    let price = round(200_000_000_000, NOW - 60).validate(NOW, 3_600).unwrap();
+    assert!((price - 2_000.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_validate_rejects_stale_round() {
+    assert_eq!(
+        round(200_000_000_000, NOW - 7_200).validate(NOW, 3_600),
+        Err(OracleError::Stale { feed: Address::from_low_u64_be(1), age_secs: 7_200, max_age_secs: 3_600 })
+    );
+}
+
+#[test]
+fn test_validate_rejects_bad_answers() {
+    assert!(matches!(round(0, NOW).validate(NOW, 3_600), Err(OracleError::InvalidAnswer { .. })));
+    assert!(matches!(round(-1, NOW).validate(NOW, 3_600), Err(OracleError::InvalidAnswer { .. })));
+
+    let mut huge = round(1, NOW);
+    huge.answer = I256::MAX;
+    assert!(matches!(huge.validate(NOW, 3_600), Err(OracleError::AnswerOutOfRange { .. })));
+
+    let mut unfinished = round(200_000_000_000, NOW);
+    unfinished.answered_in_round = 9;
+    assert!(matches!(unfinished.validate(NOW, 3_600), Err(OracleError::IncompleteRound { .. })));
+}
+
+#[test]
+fn test_deviation() {
+    assert!((deviation_bps(101.0, 100.0) - 100.0).abs() < 1e-9);
+    assert!((deviation_bps(99.0, 100.0) - 100.0).abs() < 1e-9);
+
+    assert!(check_deviation(100.5, 100.0, 50.0).is_ok());
+    assert!(matches!(check_deviation(101.0, 100.0, 50.0), Err(OracleError::Deviation { .. })));
+    assert!(matches!(check_deviation(1.0, 0.0, 50.0), Err(OracleError::Deviation { .. })));
+}
+
+#[tokio::test]
+async fn test_missing_feed() {
+    let guard = OracleGuard::new("http://127.0.0.1:1", 3_600, 50.0).unwrap();
+    let token = Address::from_low_u64_be(7);
+    assert_eq!(guard.price(token).await, Err(OracleError::MissingFeed(token)));
+}
@@ -112,3 +112,15 @@ fn test_multiple_orders() {
     assert_eq!(book.get_best_bid(), Some((100.0, 5.0)));
     assert_eq!(book.get_best_ask(), Some((101.0, 2.0)));
 }
+
+#[test]
+fn test_mid_price() {
+    let mut book = OrderBook::new();
+    assert_eq!(book.get_mid_price(), None);
+
+    book.add_order(&Order::new(100.0, 5.0).unwrap(), true).unwrap();
+    assert_eq!(book.get_mid_price(), None);
+
+    book.add_order(&Order::new(101.0, 2.0).unwrap(), false).unwrap();
+    assert_eq!(book.get_mid_price(), Some(100.5));
+}
@@ -0,0 +1,174 @@
+use rust_components::flash_loan::FlashLoanParams;
+use rust_components::paper_executor::{PaperTradeError, PaperTradeExecutor, PaperTradeKind};
+use rust_components::trade_executor::Executor;
+use ethers::types::{Address, Bytes, I256, U256, U64};
+
+fn token(id: u64) -> Address {
+    Address::from_low_u64_be(id)
+}
+
+fn far_deadline() -> U256 {
+    U256::from(u64::MAX)
+}
+
+/// Token 4 is the native token; a wei of it is worth 1e-12 of token 1.
+fn executor() -> PaperTradeExecutor {
+    let mut executor = PaperTradeExecutor::new(U256::from(20_000_000_000u64));
+    executor.set_native_token(token(4));
+    executor.update_reserves(token(1), token(2), U256::from(1_000_000u64), U256::from(2_000_000u64));
+    executor.update_reserves(token(2), token(3), U256::from(1_000_000u64), U256::from(1_000_000u64));
+    executor.update_reserves(token(3), token(1), U256::from(1_000_000u64), U256::from(1_000_000u64));
+    executor.update_reserves(token(4), token(1), U256::exp10(18), U256::from(1_000_000u64));
+    executor
+}
+
+fn paper_error(error: Box<dyn std::error::Error>) -> PaperTradeError {
+    error.downcast_ref::<PaperTradeError>().cloned().expect("not a paper trade error")
+}
+
+#[test]
+fn test_get_amount_out_single_hop() {
+    let executor = executor();
+    let amount_out = executor.get_amount_out(U256::from(1_000u64), &[token(1), token(2)]).unwrap();
+    // 1000 * 997 * 2_000_000 / (1_000_000 * 1000 + 1000 * 997)
+    assert_eq!(amount_out, U256::from(1_992u64));
+}
+
+#[test]
+fn test_get_amount_out_unknown_pair() {
+    let executor = executor();
+    let result = executor.get_amount_out(U256::from(1_000u64), &[token(1), token(5)]);
+    assert_eq!(result, Err(PaperTradeError::UnknownPair(token(1), token(5))));
+}
+
+#[test]
+fn test_get_amount_out_invalid_path() {
+    let executor = executor();
+    let result = executor.get_amount_out(U256::from(1_000u64), &[token(1)]);
+    assert_eq!(result, Err(PaperTradeError::InvalidPath));
+}
+
+#[test]
+fn test_routed_hops_use_their_router_pools() {
+    let executor = executor();
+    let (cheap, dear) = (token(7), token(8));
+    executor.set_pool_reserves(cheap, token(1), token(2), U256::from(1_000_000u64), U256::from(2_000_000u64));
+    executor.set_pool_reserves(dear, token(1), token(2), U256::from(1_000_000u64), U256::from(2_200_000u64));
+    let path = [token(1), token(2), token(1)];
+
+    // Buying where token 2 is cheap and selling where it is dear pays
+    let amount_out = executor.get_routed_amount_out(U256::from(1_000u64), &[dear, cheap], &path).unwrap();
+    assert!(amount_out > U256::from(1_000u64), "{}", amount_out);
+    // while one router trades both hops on the same pool and loses the fees
+    let amount_out = executor.get_routed_amount_out(U256::from(1_000u64), &[cheap, cheap], &path).unwrap();
+    assert!(amount_out < U256::from(1_000u64), "{}", amount_out);
+
+    let result = executor.get_routed_amount_out(U256::from(1_000u64), &[cheap], &path);
+    assert_eq!(result, Err(PaperTradeError::RouterMismatch { hops: 2, routers: 1 }));
+}
+
+#[tokio::test]
+async fn test_execute_trade_records_fill() {
+    let executor = executor();
+    let path = vec![token(1), token(2), token(3), token(1)];
+
+    let receipt = executor
+        .execute_trade(path.clone(), U256::from(100_000u64), U256::zero(), far_deadline(), token(9))
+        .await
+        .unwrap();
+
+    assert_eq!(receipt.status, Some(U64::from(1)));
+    assert_eq!(receipt.from, token(9));
+
+    let ledger = executor.ledger();
+    assert_eq!(ledger.fills().len(), 1);
+    let fill = &ledger.fills()[0];
+    assert_eq!(fill.kind, PaperTradeKind::Swap);
+    assert_eq!(fill.path, path);
+    assert_eq!(fill.transaction_hash, receipt.transaction_hash);
+    assert!(fill.success);
+    // 150k gas at 20 gwei is 3e15 wei, or 3000 of token 1
+    assert_eq!(ledger.realized_pnl(), I256::from_raw(fill.amount_out) - I256::from(100_000) - I256::from(3_000));
+    assert!(ledger.realized_pnl() > I256::zero());
+    assert_eq!(ledger.total_gas_cost(), fill.gas_used * fill.gas_price);
+}
+
+#[tokio::test]
+async fn test_execute_trade_below_min_amount_reverts() {
+    let executor = executor();
+
+    let receipt = executor
+        .execute_trade(vec![token(1), token(2), token(1)], U256::from(1_000u64), U256::from(5_000u64), far_deadline(), token(9))
+        .await
+        .unwrap();
+
+    assert_eq!(receipt.status, Some(U64::from(0)));
+    let ledger = executor.ledger();
+    assert!(!ledger.fills()[0].success);
+    assert_eq!(ledger.realized_pnl(), I256::from(-3_000));
+    assert!(ledger.total_gas_cost() > U256::zero());
+}
+
+#[tokio::test]
+async fn test_execute_trade_rejects_non_round_trips() {
+    let executor = executor();
+
+    let error = executor
+        .execute_trade(vec![token(1), token(2)], U256::from(1_000u64), U256::zero(), far_deadline(), token(9))
+        .await
+        .unwrap_err();
+    assert_eq!(paper_error(error), PaperTradeError::NotRoundTrip { start: token(1), end: token(2) });
+
+    // Gas can't be charged in a token without a native pool
+    let error = executor
+        .execute_trade(vec![token(2), token(3), token(2)], U256::from(1_000u64), U256::zero(), far_deadline(), token(9))
+        .await
+        .unwrap_err();
+    assert_eq!(paper_error(error), PaperTradeError::NoGasPrice(token(2)));
+    assert!(executor.ledger().fills().is_empty());
+}
+
+#[tokio::test]
+async fn test_execute_flash_loan_trade_simulates_path() {
+    let executor = executor();
+    let path = vec![token(1), token(2), token(3), token(1)];
+    let amount = U256::from(100_000u64);
+    let amount_out = executor.get_amount_out(amount, &path).unwrap();
+    let data = FlashLoanParams::new(path.clone(), U256::zero(), far_deadline()).encode();
+
+    let receipt = executor.execute_flash_loan_trade(token(1), amount, data).await.unwrap();
+
+    assert_eq!(receipt.status, Some(U64::from(1)));
+    let ledger = executor.ledger();
+    let fill = &ledger.fills()[0];
+    assert_eq!(fill.kind, PaperTradeKind::FlashLoan);
+    assert_eq!(fill.transaction_hash, receipt.transaction_hash);
+    assert_eq!(fill.path, path);
+    assert_eq!(fill.amount_out, amount_out);
+    // Aave V3's 5 bps premium on 100k, and 350k gas at 20 gwei is 7000 of token 1
+    assert_eq!(ledger.realized_pnl(), I256::from_raw(amount_out) - I256::from(100_000 + 50 + 7_000));
+}
+
+#[tokio::test]
+async fn test_execute_flash_loan_trade_unprofitable_reverts() {
+    let executor = executor();
+    let data = FlashLoanParams::new(vec![token(1), token(2), token(1)], U256::zero(), far_deadline()).encode();
+
+    let receipt = executor.execute_flash_loan_trade(token(1), U256::from(1_000u64), data).await.unwrap();
+
+    assert_eq!(receipt.status, Some(U64::from(0)));
+    assert_eq!(executor.ledger().realized_pnl(), I256::from(-7_000));
+}
+
+#[tokio::test]
+async fn test_execute_flash_loan_trade_rejects_bad_params() {
+    let executor = executor();
+
+    let error = executor.execute_flash_loan_trade(token(1), U256::from(1_000u64), Bytes::default()).await.unwrap_err();
+    assert!(matches!(paper_error(error), PaperTradeError::FlashLoanParams(_)));
+
+    let data = FlashLoanParams::new(vec![token(2), token(3), token(2)], U256::zero(), far_deadline()).encode();
+    let error = executor.execute_flash_loan_trade(token(1), U256::from(1_000u64), data).await.unwrap_err();
+    assert_eq!(paper_error(error), PaperTradeError::FlashLoanPath { asset: token(1) });
+    assert!(executor.ledger().fills().is_empty());
+}
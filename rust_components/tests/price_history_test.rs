@@ -0,0 +1,51 @@
+use rust_components::order::Order;
+use rust_components::order_book::OrderBook;
+use rust_components::price_history::{ewma_volatility, realized_volatility, PriceHistory};
+use std::collections::HashMap;
+
+const PRICES: [f64; 4] = [100.0, 102.0, 101.0, 105.0];
+
+#[test]
+fn test_realized_volatility_matches_python() {
+    // np.std(np.diff(p) / p[:-1]) for the same prices
+    assert!((realized_volatility(&PRICES) - 0.020313454228698492).abs() < 1e-12);
+    assert_eq!(realized_volatility(&[100.0]), 0.0);
+    assert_eq!(realized_volatility(&[100.0, 100.0, 100.0]), 0.0);
+}
+
+#[test]
+fn test_ewma_volatility() {
+    assert!((ewma_volatility(&PRICES, 0.94) - 0.02128307808258116).abs() < 1e-12);
+    assert_eq!(ewma_volatility(&[], 0.94), 0.0);
+}
+
+#[test]
+fn test_history_is_bounded_per_pair() {
+    let mut history = PriceHistory::new(3);
+    for (i, price) in PRICES.iter().enumerate() {
+        history.record("WETH/DAI", i as u64, *price);
+    }
+    history.record("WETH/DAI", 10, -1.0);
+    history.record("WETH/DAI", 11, f64::NAN);
+
+    assert_eq!(history.prices("WETH/DAI", 10), vec![102.0, 101.0, 105.0]);
+    assert_eq!(history.prices("WETH/DAI", 2), vec![101.0, 105.0]);
+    assert!(history.prices("WBTC/DAI", 10).is_empty());
+    assert_eq!(history.realized_volatility("WETH/DAI", 10), realized_volatility(&[102.0, 101.0, 105.0]));
+}
+
+#[test]
+fn test_record_snapshot_and_order_book() {
+    let mut history = PriceHistory::new(10);
+    let snapshot: HashMap<String, f64> = [("Uniswap".to_string(), 2000.0), ("Sushiswap".to_string(), 2001.0)].into_iter().collect();
+    history.record_snapshot(&snapshot, 1);
+
+    let book = OrderBook::from_orders(vec![Order::new(99.0, 1.0).unwrap()], vec![Order::new(101.0, 1.0).unwrap()]);
+    history.record_order_book("Exchange1", &book, 1);
+    history.record_order_book("Empty", &OrderBook::new(), 1);
+
+    assert_eq!(history.prices("Uniswap", 10), vec![2000.0]);
+    assert_eq!(history.prices("Sushiswap", 10), vec![2001.0]);
+    assert_eq!(history.samples("Exchange1")[0].price, 100.0);
+    assert!(history.samples("Empty").is_empty());
+}
@@ -0,0 +1,59 @@
+use rust_components::price_monitor::{PriceMonitor, PriceReading};
+use rust_components::provider_pool::ProviderPool;
+use rust_components::rpc_retry::RetryPolicy;
+use ethers::abi::{self, Token};
+use ethers::types::{Address, Bytes, U256};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// A JSON-RPC endpoint answering `getReserves` with 2000:1 reserves, or HTTP
+/// 503 while `down` is set.
+fn mock_pair_rpc(down: Arc<AtomicBool>) -> String {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+    thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            if down.load(Ordering::SeqCst) {
+                let _ = request.respond(tiny_http::Response::from_string("unavailable").with_status_code(503));
+                continue;
+            }
+            let call: Value = serde_json::from_str(&body).unwrap();
+            let reserves = abi::encode(&[Token::Uint(U256::from(2_000)), Token::Uint(U256::one()), Token::Uint(U256::zero())]);
+            let response = json!({"jsonrpc": "2.0", "id": call["id"], "result": Bytes::from(reserves)});
+            let _ = request.respond(tiny_http::Response::from_string(response.to_string()));
+        }
+    });
+    url
+}
+
+#[tokio::test]
+async fn test_failed_reads_are_stale_then_missing() {
+    let down = Arc::new(AtomicBool::new(false));
+    let mut pool = ProviderPool::new(&[mock_pair_rpc(down.clone())]).unwrap();
+    pool.set_retry_policy(RetryPolicy { max_retries: 0, ..RetryPolicy::default() });
+    let mut monitor = PriceMonitor::with_provider(Arc::new(pool.into_provider()), HashMap::from([("uniswap".to_string(), Address::zero())]));
+    monitor.set_max_price_age(Duration::from_millis(300));
+    monitor.initialize_pairs().await.unwrap();
+
+    let readings = monitor.read_prices().await;
+    assert_eq!(readings["uniswap"], PriceReading::Fresh(2_000.0));
+
+    down.store(true, Ordering::SeqCst);
+    let readings = monitor.read_prices().await;
+    let PriceReading::Stale { price, ref error, .. } = readings["uniswap"] else {
+        panic!("expected a stale price, got {:?}", readings["uniswap"]);
+    };
+    assert_eq!(price, 2_000.0);
+    assert!(error.contains("failed on all 1 endpoint(s)"), "{}", error);
+    assert_eq!(readings["uniswap"].fresh(), None);
+
+    tokio::time::sleep(Duration::from_millis(350)).await;
+    let readings = monitor.read_prices().await;
+    assert!(matches!(readings["uniswap"], PriceReading::Missing { .. }), "{:?}", readings["uniswap"]);
+}
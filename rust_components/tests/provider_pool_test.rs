@@ -0,0 +1,283 @@
+use rust_components::config::{Config, ChainConfig};
+use rust_components::provider_pool::{ProviderPool, ProviderPoolError};
+use rust_components::rpc_retry::RetryPolicy;
+use rust_components::scanner::{self, ReserveReading};
+use ethers::abi::{self, Token};
+use ethers::providers::{Middleware, RpcError};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Bytes, TransactionRequest, U256};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// A local JSON-RPC endpoint; `handler` maps method and params to a result or
+/// a JSON-RPC error message. While `down`, it answers HTTP 503, and it answers
+/// HTTP 429 to the next `throttled` requests.
+struct MockRpc {
+    url: String,
+    calls: Arc<AtomicUsize>,
+    down: Arc<AtomicBool>,
+    throttled: Arc<AtomicUsize>,
+}
+
+impl MockRpc {
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    fn set_down(&self, down: bool) {
+        self.down.store(down, Ordering::SeqCst);
+    }
+
+    fn throttle(&self, requests: usize) {
+        self.throttled.store(requests, Ordering::SeqCst);
+    }
+}
+
+fn mock_rpc<F>(delay: Duration, handler: F) -> MockRpc
+where
+    F: Fn(&str, &Value) -> Result<Value, String> + Send + 'static,
+{
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    // The path stands in for an API key that must never be logged
+    let url = format!("http://{}/v2/secret-key", server.server_addr().to_ip().unwrap());
+    let calls = Arc::new(AtomicUsize::new(0));
+    let down = Arc::new(AtomicBool::new(false));
+    let throttled = Arc::new(AtomicUsize::new(0));
+    let (thread_calls, thread_down, thread_throttled) = (calls.clone(), down.clone(), throttled.clone());
+
+    thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            thread_calls.fetch_add(1, Ordering::SeqCst);
+            if thread_down.load(Ordering::SeqCst) {
+                let _ = request.respond(tiny_http::Response::from_string("unavailable").with_status_code(503));
+                continue;
+            }
+            if thread_throttled.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1)).is_ok() {
+                let _ = request.respond(tiny_http::Response::from_string("Too Many Requests").with_status_code(429));
+                continue;
+            }
+            thread::sleep(delay);
+            let call: Value = serde_json::from_str(&body).unwrap();
+            let response = match handler(call["method"].as_str().unwrap(), &call["params"]) {
+                Ok(result) => json!({"jsonrpc": "2.0", "id": call["id"], "result": result}),
+                Err(message) => json!({"jsonrpc": "2.0", "id": call["id"], "error": {"code": 3, "message": message}}),
+            };
+            let _ = request.respond(tiny_http::Response::from_string(response.to_string()));
+        }
+    });
+    MockRpc { url, calls, down, throttled }
+}
+
+/// Answers `eth_blockNumber` with `block` and `eth_call` with `call_result`.
+fn chain_at(block: u64, call_result: &'static str) -> impl Fn(&str, &Value) -> Result<Value, String> {
+    move |method, _| match method {
+        "eth_blockNumber" => Ok(json!(format!("{:#x}", block))),
+        "eth_call" if call_result == "revert" => Err("execution reverted: No profit".to_string()),
+        "eth_call" => Ok(json!(call_result)),
+        other => Err(format!("unexpected {}", other)),
+    }
+}
+
+fn urls(mocks: &[&MockRpc]) -> Vec<String> {
+    mocks.iter().map(|mock| mock.url.clone()).collect()
+}
+
+fn quick_retries(max_retries: u32) -> RetryPolicy {
+    RetryPolicy { max_retries, base_delay: Duration::from_millis(20), max_delay: Duration::from_millis(50) }
+}
+
+#[tokio::test]
+async fn test_routes_by_latency_and_fails_over() {
+    let slow = mock_rpc(Duration::from_millis(100), chain_at(16, "0x"));
+    let fast = mock_rpc(Duration::ZERO, chain_at(16, "0x"));
+    let pool = ProviderPool::new(&urls(&[&slow, &fast])).unwrap();
+    let provider = pool.clone().into_provider();
+
+    for _ in 0..4 {
+        assert_eq!(provider.get_block_number().await.unwrap().as_u64(), 16);
+    }
+    // Both get measured once, then the faster one takes the traffic
+    assert_eq!((slow.calls(), fast.calls()), (1, 3));
+
+    fast.set_down(true);
+    for _ in 0..3 {
+        assert_eq!(provider.get_block_number().await.unwrap().as_u64(), 16);
+    }
+    let status = pool.endpoint_status();
+    assert_eq!(status[1].endpoint, fast.url.trim_end_matches("/v2/secret-key"));
+    assert!(!status[1].healthy);
+    assert_eq!(status[1].consecutive_failures, 3);
+
+    // Unhealthy endpoints are only tried after the healthy ones
+    let before = fast.calls();
+    provider.get_block_number().await.unwrap();
+    assert_eq!(fast.calls(), before);
+
+    fast.set_down(false);
+    let status = pool.check_health().await;
+    assert!(status.iter().all(|endpoint| endpoint.healthy && endpoint.block_number == Some(16)), "{:?}", status);
+}
+
+#[tokio::test]
+async fn test_rpc_errors_are_not_failed_over() {
+    let reverting = mock_rpc(Duration::ZERO, chain_at(16, "revert"));
+    let other = mock_rpc(Duration::ZERO, chain_at(16, "0x01"));
+    let mut pool = ProviderPool::new(&urls(&[&reverting, &other])).unwrap();
+    pool.set_retry_policy(quick_retries(1));
+    let provider = pool.into_provider();
+
+    let tx: TypedTransaction = TransactionRequest::new().to(ethers::types::Address::zero()).into();
+    let error = provider.call(&tx, None).await.unwrap_err();
+    assert!(error.as_error_response().is_some_and(|e| e.message.contains("No profit")), "{}", error);
+    assert_eq!(other.calls(), 0);
+
+    reverting.set_down(true);
+    other.set_down(true);
+    let before = other.calls();
+    let error = provider.get_block_number().await.unwrap_err();
+    assert!(error.to_string().contains("failed on all 2 endpoint(s) in 2 round(s)"), "{}", error);
+    assert_eq!(other.calls(), before + 2);
+    assert!(!error.to_string().contains("secret-key"), "{}", error);
+
+    assert!(matches!(ProviderPool::new(&[]), Err(ProviderPoolError::NoEndpoints)));
+    assert!(matches!(ProviderPool::new(&["not a url".to_string()]), Err(ProviderPoolError::InvalidUrl(_))));
+}
+
+#[tokio::test]
+async fn test_lagging_endpoints_are_routed_last() {
+    let behind = mock_rpc(Duration::ZERO, chain_at(90, "0x"));
+    let head = mock_rpc(Duration::from_millis(20), chain_at(100, "0x"));
+    let mut pool = ProviderPool::new(&urls(&[&behind, &head])).unwrap();
+    pool.set_max_block_lag(3);
+
+    let status = pool.check_health().await;
+    assert!(status[0].lagging && !status[0].healthy, "{:?}", status);
+    assert!(status[1].healthy);
+    assert_eq!(pool.into_provider().get_block_number().await.unwrap().as_u64(), 100);
+}
+
+#[tokio::test]
+async fn test_quorum_reads_agree_on_block_and_value() {
+    let a = mock_rpc(Duration::ZERO, chain_at(100, "0x01"));
+    let b = mock_rpc(Duration::ZERO, chain_at(100, "0x01"));
+    let c = mock_rpc(Duration::ZERO, chain_at(99, "0x02"));
+    let pool = ProviderPool::new(&urls(&[&a, &b, &c])).unwrap();
+    let tx: TypedTransaction = TransactionRequest::new().to(ethers::types::Address::zero()).into();
+
+    assert_eq!(pool.quorum_block_number(2).await.unwrap(), 100);
+    assert_eq!(pool.quorum_block_number(3).await.unwrap(), 99);
+    let read = pool.quorum_call(&tx, 2).await.unwrap();
+    assert_eq!((read.block_number, read.value, read.agreeing), (100, Bytes::from(vec![1u8]), 2));
+
+    let error = pool.quorum_call(&tx, 3).await.unwrap_err();
+    assert!(matches!(error, ProviderPoolError::NoQuorum { needed: 3, agreeing: 2, .. }), "{}", error);
+    a.set_down(true);
+    let error = pool.quorum_call(&tx, 3).await.unwrap_err();
+    assert!(matches!(error, ProviderPoolError::NoQuorum { agreeing: 2, .. }), "{}", error);
+}
+
+#[tokio::test]
+async fn test_retries_with_backoff_under_rate_limits() {
+    let endpoint = mock_rpc(Duration::ZERO, chain_at(16, "0x"));
+    let mut pool = ProviderPool::new(&urls(&[&endpoint])).unwrap();
+    pool.set_retry_policy(quick_retries(3));
+    let provider = pool.clone().into_provider();
+
+    // Two throttled rounds, then an answer
+    endpoint.throttle(2);
+    assert_eq!(provider.get_block_number().await.unwrap().as_u64(), 16);
+    assert_eq!(endpoint.calls(), 3);
+
+    endpoint.throttle(10);
+    let error = provider.get_block_number().await.unwrap_err();
+    assert!(error.to_string().contains("in 4 round(s)"), "{}", error);
+    endpoint.throttle(0);
+
+    pool.set_rate_limit(20.0, 1);
+    let started = std::time::Instant::now();
+    for _ in 0..5 {
+        provider.get_block_number().await.unwrap();
+    }
+    // The first request spends the burst, the other four wait 50ms each
+    assert!(started.elapsed() >= Duration::from_millis(190), "{:?}", started.elapsed());
+}
+
+#[tokio::test]
+async fn test_sends_are_not_retried_or_failed_over() {
+    let first = mock_rpc(Duration::ZERO, chain_at(16, "0x"));
+    let second = mock_rpc(Duration::from_millis(20), chain_at(16, "0x"));
+    let mut pool = ProviderPool::new(&urls(&[&first, &second])).unwrap();
+    pool.set_retry_policy(quick_retries(3));
+    let provider = pool.into_provider();
+    provider.get_block_number().await.unwrap();
+    provider.get_block_number().await.unwrap();
+
+    // A throttled send may still have reached the node, so it is not repeated
+    first.throttle(10);
+    let before = (first.calls(), second.calls());
+    let error = provider.send_raw_transaction(Bytes::from(vec![1u8])).await.unwrap_err();
+    assert!(error.to_string().contains("failed on all 1 endpoint(s) in 1 round(s)"), "{}", error);
+    assert_eq!((first.calls(), second.calls()), (before.0 + 1, before.1));
+}
+
+fn mainnet() -> ChainConfig {
+    let config = Config::parse(include_str!("../config.example.toml"), "example", Vec::new()).unwrap();
+    config.chain("mainnet").unwrap().clone()
+}
+
+/// Serves `getReserves` for every configured pool; `usdc` is the USDC reserve
+/// in whole units against 1000 WETH.
+fn reserves(usdc: u64) -> impl Fn(&str, &Value) -> Result<Value, String> {
+    move |method, params| match method {
+        "eth_blockNumber" => Ok(json!("0x64")),
+        "eth_call" => {
+            assert_eq!(params[1], json!("0x64"));
+            // USDC sorts before WETH, so it is token0
+            let encoded = abi::encode(&[
+                Token::Uint(U256::from(usdc) * U256::exp10(6)),
+                Token::Uint(U256::from(1_000) * U256::exp10(18)),
+                Token::Uint(U256::zero()),
+            ]);
+            Ok(json!(Bytes::from(encoded)))
+        }
+        other => Err(format!("unexpected {}", other)),
+    }
+}
+
+#[tokio::test]
+async fn test_snapshot_with_quorum() {
+    let a = mock_rpc(Duration::ZERO, reserves(2_000_000));
+    let b = mock_rpc(Duration::ZERO, reserves(2_000_000));
+    let c = mock_rpc(Duration::ZERO, reserves(2_100_000));
+    let provider = Arc::new(ProviderPool::new(&urls(&[&a, &b, &c])).unwrap().into_provider());
+
+    let snapshot = scanner::fetch_snapshot_quorum(provider.clone(), &mainnet(), 2).await.unwrap();
+    assert_eq!(snapshot.block_number, Some(100));
+    assert_eq!(snapshot.pools.len(), 2);
+    assert_eq!(snapshot.pools[0].reserve_base, U256::from(1_000) * U256::exp10(18));
+    assert_eq!(snapshot.pools[0].reserve_quote, U256::from(2_000_000) * U256::exp10(6));
+
+    assert!(scanner::fetch_snapshot_quorum(provider, &mainnet(), 3).await.is_err());
+}
+
+#[tokio::test]
+async fn test_snapshot_keeps_pools_that_fail() {
+    let uniswap = format!("{:?}", mainnet().pools[0].address);
+    let serve = reserves(2_000_000);
+    let node = mock_rpc(Duration::ZERO, move |method, params| match method {
+        "eth_call" if params[0]["to"] != json!(uniswap) => Err("execution reverted".to_string()),
+        _ => serve(method, params),
+    });
+    let provider = Arc::new(ProviderPool::new(&urls(&[&node])).unwrap().into_provider());
+
+    let snapshot = scanner::fetch_snapshot(provider, &mainnet()).await.unwrap();
+    assert_eq!(snapshot.pools.len(), 2);
+    assert!(snapshot.pools[0].is_fresh());
+    assert_eq!(snapshot.pools[1].dex, "sushiswap");
+    assert!(matches!(&snapshot.pools[1].reading, ReserveReading::Missing { error } if error.contains("reverted")), "{:?}", snapshot.pools[1]);
+}
@@ -0,0 +1,125 @@
+use rust_components::arbitrage_finder::ArbitrageOpportunity;
+use rust_components::risk_manager::{arbitrage_profit, optimal_arbitrage_input, ClampReason, PoolReserves, RiskManager, SizingRequest};
+use ethers::types::{Address, U256};
+
+fn ether(amount: u64) -> U256 {
+    U256::from(amount) * U256::exp10(18)
+}
+
+fn opportunity() -> ArbitrageOpportunity {
+    ArbitrageOpportunity::new("Uniswap", "Sushiswap", 100.0)
+}
+
+fn request() -> SizingRequest {
+    // WETH is cheaper on the buy pool (2000 DAI) than on the sell pool (1900 DAI per WETH)
+    SizingRequest {
+        token: Address::from_low_u64_be(1),
+        buy_pool: PoolReserves::new(ether(1_000), ether(2_000_000)),
+        sell_pool: PoolReserves::new(ether(1_900_000), ether(1_000)),
+        available_balance: ether(1_000),
+    }
+}
+
+fn risk_manager() -> RiskManager {
+    let mut risk_manager = RiskManager::new(ether(1_000), 0.1);
+    risk_manager.set_max_price_impact_bps(10_000.0);
+    risk_manager
+}
+
+#[test]
+fn test_optimal_input_maximises_profit() {
+    let request = request();
+    let optimal = optimal_arbitrage_input(&request.buy_pool, &request.sell_pool);
+    assert!(optimal > ether(11) && optimal < ether(12));
+
+    let best = arbitrage_profit(optimal, &request.buy_pool, &request.sell_pool);
+    assert!(best > arbitrage_profit(optimal * 9 / 10, &request.buy_pool, &request.sell_pool));
+    assert!(best > arbitrage_profit(optimal * 11 / 10, &request.buy_pool, &request.sell_pool));
+}
+
+#[test]
+fn test_unprofitable_round_trip() {
+    let pool = PoolReserves::new(ether(1_000), ether(2_000_000));
+    let reverse = PoolReserves::new(ether(2_000_000), ether(1_000));
+    assert_eq!(optimal_arbitrage_input(&pool, &reverse), U256::zero());
+
+    let mut request = request();
+    request.sell_pool = reverse;
+    let position = risk_manager().calculate_position_size(&opportunity(), &request);
+    assert_eq!(position.size, U256::zero());
+    assert_eq!(position.clamped_by, Some(ClampReason::Unprofitable));
+}
+
+#[test]
+fn test_unclamped_size_is_optimal() {
+    let position = risk_manager().calculate_position_size(&opportunity(), &request());
+    assert_eq!(position.size, position.optimal_size);
+    assert_eq!(position.clamped_by, None);
+    assert!(position.expected_profit > U256::zero());
+}
+
+#[test]
+fn test_size_clamped_by_token_max_position() {
+    let mut risk_manager = risk_manager();
+    risk_manager.set_max_position_size(Address::from_low_u64_be(2), ether(1));
+    assert_eq!(risk_manager.calculate_position_size(&opportunity(), &request()).clamped_by, None);
+
+    risk_manager.set_max_position_size(request().token, ether(3));
+    let position = risk_manager.calculate_position_size(&opportunity(), &request());
+    assert_eq!(position.size, ether(3));
+    assert_eq!(position.clamped_by, Some(ClampReason::MaxPositionSize));
+}
+
+#[test]
+fn test_size_clamped_by_balance_and_price_impact() {
+    let mut low_balance = request();
+    low_balance.available_balance = ether(5);
+    let position = risk_manager().calculate_position_size(&opportunity(), &low_balance);
+    assert_eq!(position.size, ether(5));
+    assert_eq!(position.clamped_by, Some(ClampReason::AvailableBalance));
+
+    let mut risk_manager = risk_manager();
+    // 10 bps on a 1000 WETH pool allows just over 1 WETH
+    risk_manager.set_max_price_impact_bps(10.0);
+    let position = risk_manager.calculate_position_size(&opportunity(), &request());
+    assert_eq!(position.clamped_by, Some(ClampReason::PriceImpact));
+    assert!(position.size > ether(1) && position.size < ether(2));
+}
+
+#[test]
+fn test_price_impact_bounds_execution_price() {
+    let pool = PoolReserves::new(ether(1000), ether(2_000_000));
+    let amount_in = pool.max_input_for_price_impact(50.0);
+    let (reserve_in, reserve_out, amount_in) = (1000.0, 2_000_000.0, amount_in.as_u128() as f64 / 1e18);
+
+    // Without fees the execution price is 50 bps below spot
+    let execution_price = reserve_out / (reserve_in + amount_in);
+    let spot_price = reserve_out / reserve_in;
+    assert!((1.0 - execution_price / spot_price - 0.005).abs() < 1e-9);
+    // while the spot price moves by about twice that
+    let spot_after = (reserve_out - amount_in * execution_price) / (reserve_in + amount_in);
+    assert!((1.0 - spot_after / spot_price - 0.01).abs() < 1e-4);
+}
+
+#[test]
+fn test_size_clamped_by_exposure_caps() {
+    let token = request().token;
+    let mut risk_manager = risk_manager();
+    risk_manager.set_token_exposure_cap(token, ether(8));
+    risk_manager.set_venue_exposure_cap("Sushiswap", ether(10));
+
+    let position = risk_manager.calculate_position_size(&opportunity(), &request());
+    assert_eq!(position.size, ether(8));
+    assert_eq!(position.clamped_by, Some(ClampReason::TokenExposureCap(token)));
+
+    // Open positions count against the caps until released
+    risk_manager.add_exposure(&opportunity(), token, ether(2));
+    risk_manager.set_token_exposure_cap(token, ether(100));
+    let position = risk_manager.calculate_position_size(&opportunity(), &request());
+    assert_eq!(position.size, ether(8));
+    assert_eq!(position.clamped_by, Some(ClampReason::VenueExposureCap("Sushiswap".to_string())));
+
+    risk_manager.release_exposure(&opportunity(), token, ether(2));
+    let position = risk_manager.calculate_position_size(&opportunity(), &request());
+    assert_eq!(position.size, ether(10));
+}
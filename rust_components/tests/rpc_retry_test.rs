@@ -0,0 +1,58 @@
+use rust_components::rpc_retry::{classify, ErrorClass, RetryPolicy, TokenBucket};
+use ethers::providers::{HttpClientError, JsonRpcError};
+use std::time::Duration;
+
+fn rpc_error(code: i64, message: &str) -> HttpClientError {
+    HttpClientError::JsonRpcError(JsonRpcError { code, message: message.to_string(), data: None })
+}
+
+fn unparsable(text: &str) -> HttpClientError {
+    let err = serde_json::from_str::<u8>("not a number").unwrap_err();
+    HttpClientError::SerdeJson { err, text: text.to_string() }
+}
+
+#[test]
+fn test_classifies_errors() {
+    assert_eq!(classify(&rpc_error(3, "execution reverted: No profit")), ErrorClass::Fatal);
+    assert_eq!(classify(&rpc_error(-32602, "invalid argument 0")), ErrorClass::Fatal);
+    assert_eq!(classify(&rpc_error(-32005, "request limit reached")), ErrorClass::RateLimited);
+    assert_eq!(classify(&rpc_error(-32000, "Too Many Requests, slow down")), ErrorClass::RateLimited);
+
+    assert_eq!(classify(&unparsable("Too Many Requests")), ErrorClass::RateLimited);
+    assert_eq!(classify(&unparsable(r#"{"code":429,"message":"compute units exhausted"}"#)), ErrorClass::RateLimited);
+    assert_eq!(classify(&unparsable("<html>502 Bad Gateway</html>")), ErrorClass::Transient);
+    assert_eq!(classify(&unparsable(r#"{"jsonrpc":"2.0","id":1,"result":"0xzz"}"#)), ErrorClass::Fatal);
+    assert!(ErrorClass::Transient.is_retryable() && !ErrorClass::Fatal.is_retryable());
+}
+
+#[test]
+fn test_backoff_grows_with_jitter_up_to_the_cap() {
+    let policy = RetryPolicy { max_retries: 5, base_delay: Duration::from_millis(100), max_delay: Duration::from_secs(1) };
+    for (attempt, ceiling) in [(0, 100), (1, 200), (2, 400), (3, 800), (4, 1_000), (20, 1_000)] {
+        let ceiling = Duration::from_millis(ceiling);
+        for _ in 0..20 {
+            let delay = policy.backoff(attempt);
+            assert!(delay >= ceiling / 2 && delay <= ceiling, "attempt {}: {:?}", attempt, delay);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_token_bucket() {
+    let bucket = TokenBucket::new(20.0, 2);
+    assert!(bucket.try_acquire().is_ok());
+    assert!(bucket.try_acquire().is_ok());
+    let wait = bucket.try_acquire().unwrap_err();
+    assert!(wait > Duration::ZERO && wait <= Duration::from_millis(50), "{:?}", wait);
+
+    let started = std::time::Instant::now();
+    bucket.acquire().await;
+    bucket.acquire().await;
+    assert!(started.elapsed() >= Duration::from_millis(80), "{:?}", started.elapsed());
+
+    bucket.drain();
+    assert!(bucket.try_acquire().is_err());
+    let unlimited = TokenBucket::unlimited();
+    unlimited.drain();
+    assert!((0..1_000).all(|_| unlimited.try_acquire().is_ok()));
+}
@@ -0,0 +1,369 @@
+use rust_components::circuit_breaker::{BreakerReason, CircuitBreaker, CircuitBreakerConfig};
+use rust_components::config::{ChainConfig, Config};
+use rust_components::control_api::{ControlState, ThresholdUpdate};
+use rust_components::inventory::{Inventory, Location};
+use rust_components::opportunity_tracker::OpportunityTracker;
+use rust_components::oracle::OracleGuard;
+use rust_components::paper_executor::PaperTradeExecutor;
+use rust_components::risk_manager::ClampReason;
+use rust_components::scanner::{self, MarketSnapshot, PoolSnapshot, ReserveReading, Scanner, SessionReport, SnapshotRecorder};
+use rust_components::trade_executor::{Executor, RoutedTrade, TradeObserver};
+use async_trait::async_trait;
+use chrono::Utc;
+use ethers::abi::{encode, Token};
+use ethers::types::{Address, Bytes, TransactionReceipt, H256, I256, U256};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tokio::sync::Notify;
+
+fn mainnet() -> ChainConfig {
+    let config = Config::parse(include_str!("../config.example.toml"), "example", Vec::new()).unwrap();
+    config.chain("mainnet").unwrap().clone()
+}
+
+fn pool(dex: &str, weth: u64, usdc: u64) -> PoolSnapshot {
+    PoolSnapshot {
+        dex: dex.to_string(),
+        pair: "WETH/USDC".to_string(),
+        reserve_base: U256::from(weth) * U256::exp10(18),
+        reserve_quote: U256::from(usdc) * U256::exp10(6),
+        reading: ReserveReading::Fresh,
+    }
+}
+
+fn snapshot() -> MarketSnapshot {
+    MarketSnapshot {
+        chain: "mainnet".to_string(),
+        block_number: Some(100),
+        at: Utc::now(),
+        pools: vec![pool("uniswap", 1_000, 2_000_000), pool("sushiswap", 1_000, 2_100_000)],
+    }
+}
+
+fn scanner(min_profit: f64) -> Scanner {
+    scanner_on(mainnet(), min_profit)
+}
+
+fn scanner_on(chain: ChainConfig, min_profit: f64) -> Scanner {
+    let mut risk = Config::default().risk;
+    risk.max_price_impact_bps = 10_000.0;
+    Scanner::new(chain.clone(), risk.risk_manager(&chain), min_profit)
+}
+
+#[test]
+fn test_scan_and_quote() {
+    let candidates = scanner(0.0).scan(&snapshot());
+    assert_eq!(candidates.len(), 1);
+    let best = &candidates[0];
+    assert_eq!((best.opportunity.buy_dex.as_str(), best.opportunity.sell_dex.as_str()), ("uniswap", "sushiswap"));
+    assert_eq!(best.opportunity.block_number, Some(100));
+    assert_eq!(best.opportunity.chain_id, Some(1));
+    assert!(best.opportunity.profit > 100.0 && best.opportunity.profit < 50_000.0, "{}", best.opportunity.profit);
+    assert_eq!(best.path()[0], mainnet().token("USDC").unwrap().address);
+    assert_eq!(best.routers(), vec![mainnet().router("uniswap").unwrap(), mainnet().router("sushiswap").unwrap()]);
+
+    assert!(scanner(1_000_000.0).scan(&snapshot()).is_empty());
+    // A single router would trade both hops on one pair
+    let mut one_router = mainnet();
+    one_router.pools.iter_mut().for_each(|pool| pool.router = None);
+    assert!(scanner_on(one_router, 0.0).scan(&snapshot()).is_empty());
+
+    let confirmed = scanner(0.0).confirm(best, &snapshot()).unwrap();
+    assert_eq!(confirmed.opportunity.id, best.opportunity.id);
+    let mut converged = snapshot();
+    converged.pools[1] = pool("sushiswap", 1_000, 2_000_000);
+    assert!(scanner(0.0).confirm(best, &converged).is_none());
+
+    let quotes = scanner(0.0).quote(&snapshot(), "WETH/USDC", U256::exp10(18)).unwrap();
+    assert_eq!(quotes[0].dex, "sushiswap");
+    assert!(quotes[0].price < 2_100.0 && quotes[0].price > 2_090.0, "{}", quotes[0].price);
+    assert!(quotes[1].slippage_bps > 39.0 && quotes[1].slippage_bps < 41.0, "{}", quotes[1].slippage_bps);
+    assert!(scanner(0.0).quote(&snapshot(), "WBTC/USDC", U256::one()).is_err());
+}
+
+#[tokio::test]
+async fn test_paper_execution_tracks_lifecycle() {
+    let tracker = Arc::new(OpportunityTracker::new());
+    let mut scanner = scanner(0.0);
+    scanner.set_tracker(tracker.clone());
+    let candidates = scanner.scan(&snapshot());
+    let best = &candidates[0];
+
+    let paper = PaperTradeExecutor::new(U256::exp10(9) * 30);
+    best.set_paper_reserves(&paper);
+    let receipt = scanner.execute(&paper, best, Address::zero()).await.unwrap();
+    assert_eq!(receipt.status, Some(1u64.into()));
+    let record = tracker.get(&best.opportunity.id).unwrap();
+    let states: Vec<_> = record.transitions.iter().map(|transition| transition.state.name()).collect();
+    assert_eq!(states, ["detected", "risk_approved", "simulated", "submitted", "confirmed"]);
+
+    let unpriced = scanner.scan(&snapshot()).remove(0);
+    assert!(scanner.execute(&PaperTradeExecutor::new(U256::zero()), &unpriced, Address::zero()).await.is_err());
+    assert_eq!(tracker.get(&unpriced.opportunity.id).unwrap().state().name(), "simulation_failed");
+
+    // 0.0045 ETH of gas is charged in USDC at the buy pool's price
+    let gas_in_usdc: U256 = U256::exp10(9) * 30 * 150_000 * best.buy_pool.reserve_in / best.buy_pool.reserve_out;
+    let ledger = paper.ledger();
+    assert_eq!(ledger.fills()[0].pnl.into_raw() + gas_in_usdc, best.position.expected_profit);
+    let mut report = SessionReport::new(0.0);
+    report.record_snapshot(&candidates);
+    report.record_ledger(&ledger, scanner.chain());
+    assert_eq!((report.snapshots, report.opportunities, report.trades, report.reverted), (1, 1, 1, 0));
+    assert!((report.realized_pnl - (best.opportunity.profit - gas_in_usdc.as_u128() as f64 / 1e6)).abs() < 1e-6);
+    assert!((report.gas_cost - 0.0045).abs() < 1e-9);
+}
+
+#[test]
+fn test_empty_pools_are_not_priced() {
+    let control = Arc::new(ControlState::new());
+    let mut scanner = scanner(0.0);
+    scanner.set_control(control.clone());
+    // A drained pool next to a live one
+    let drained = MarketSnapshot { pools: vec![pool("uniswap", 1_000, 2_000_000), pool("sushiswap", 0, 2_100_000)], ..snapshot() };
+    let fresh = MarketSnapshot { pools: vec![pool("uniswap", 1_000, 2_000_000), pool("sushiswap", 0, 0)], ..snapshot() };
+
+    assert!(scanner.scan(&drained).is_empty());
+    assert!(scanner.scan(&fresh).is_empty());
+
+    let pools = control.pools();
+    assert_eq!(pools.iter().map(|pool| pool.dex.as_str()).collect::<Vec<_>>(), ["mainnet/uniswap"]);
+    let prices = scanner.risk_manager().price_history().prices("WETH/USDC", 10);
+    assert_eq!(prices, [2_000.0, 2_000.0]);
+    assert_eq!(scanner.risk_manager().calculate_market_volatility("WETH/USDC"), 0.0);
+}
+
+#[test]
+fn test_missing_pools_are_skipped() {
+    let control = Arc::new(ControlState::new());
+    let mut scanner = scanner(0.0);
+    scanner.set_control(control.clone());
+    let missing = PoolSnapshot { reading: ReserveReading::Missing { error: "timed out".to_string() }, ..pool("sushiswap", 1_000, 2_100_000) };
+    let snapshot = MarketSnapshot { pools: vec![pool("uniswap", 1_000, 2_000_000), missing], ..snapshot() };
+
+    assert!(scanner.scan(&snapshot).is_empty());
+    let pools = control.pools();
+    assert_eq!(pools.iter().map(|pool| pool.dex.as_str()).collect::<Vec<_>>(), ["mainnet/uniswap"]);
+
+    // Nor does a candidate confirm against a snapshot that lost one of its pools
+    let best = scanner_on(mainnet(), 0.0).scan(&self::snapshot()).remove(0);
+    assert!(scanner.confirm(&best, &snapshot).is_none());
+}
+
+#[test]
+fn test_stale_snapshots_are_not_traded() {
+    let control = Arc::new(ControlState::new());
+    control.update_thresholds(ThresholdUpdate { max_book_age_secs: Some(5), ..Default::default() }).unwrap();
+    let mut scanner = scanner(0.0);
+    scanner.set_control(control);
+
+    let fresh = snapshot();
+    let candidate = scanner.scan(&fresh).remove(0);
+    let stale = MarketSnapshot { at: Utc::now() - chrono::Duration::seconds(10), ..snapshot() };
+    assert!(scanner.scan(&stale).is_empty());
+    assert!(scanner.confirm(&candidate, &stale).is_none());
+    assert!(scanner.confirm(&candidate, &fresh).is_some());
+}
+
+#[test]
+fn test_scan_prices_inventory() {
+    let chain = mainnet();
+    let (weth, usdc) = (chain.token("WETH").unwrap().address, chain.token("USDC").unwrap().address);
+    let wallet = Location::Wallet(Address::zero());
+    let mut inventory = Inventory::new(weth);
+    inventory.set_decimals(usdc, 6);
+    inventory.set_balance(wallet.clone(), weth, U256::exp10(18));
+    inventory.set_balance(wallet.clone(), usdc, U256::from(100) * U256::exp10(6));
+    let inventory = Arc::new(Mutex::new(inventory));
+    let mut scanner = scanner(0.0);
+    scanner.set_inventory(inventory.clone(), wallet);
+    assert!(inventory.lock().unwrap().total_value().is_err());
+
+    scanner.scan(&snapshot());
+    // WETH at the mean of its pools' 2000 and 2100 USDC
+    let inventory = inventory.lock().unwrap();
+    assert_eq!(inventory.value(weth, U256::exp10(18)).unwrap(), 2050.0);
+    assert_eq!(inventory.total_value().unwrap(), 2150.0);
+}
+
+#[tokio::test]
+async fn test_risk_gate_sizes_to_inventory_and_rejects() {
+    let usdc = mainnet().token("USDC").unwrap().address;
+    let wallet = Location::Wallet(Address::zero());
+    let mut inventory = Inventory::new(Address::zero());
+    inventory.set_balance(wallet.clone(), usdc, U256::from(100) * U256::exp10(6));
+    let inventory = Arc::new(Mutex::new(inventory));
+    let circuit_breaker = Arc::new(CircuitBreaker::new(CircuitBreakerConfig::default()));
+    let tracker = Arc::new(OpportunityTracker::new());
+    let mut scanner = scanner(0.0);
+    scanner.set_inventory(inventory.clone(), wallet.clone());
+    scanner.set_circuit_breaker(circuit_breaker.clone());
+    scanner.set_tracker(tracker.clone());
+
+    let best = scanner.scan(&snapshot()).remove(0);
+    assert_eq!(best.position.size, U256::from(100) * U256::exp10(6));
+
+    circuit_breaker.trip(BreakerReason::KillSwitch("test".to_string()));
+    let paper = PaperTradeExecutor::new(U256::zero());
+    best.set_paper_reserves(&paper);
+    assert!(scanner.execute(&paper, &best, Address::zero()).await.is_err());
+    assert_eq!(tracker.get(&best.opportunity.id).unwrap().state().name(), "risk_rejected");
+    assert!(paper.ledger().fills().is_empty());
+
+    inventory.lock().unwrap().set_balance(wallet, usdc, U256::zero());
+    assert!(scanner.scan(&snapshot()).is_empty());
+}
+
+#[tokio::test]
+async fn test_volatile_pairs_trip_the_breaker() {
+    let circuit_breaker = Arc::new(CircuitBreaker::new(CircuitBreakerConfig::default()));
+    let tracker = Arc::new(OpportunityTracker::new());
+    let mut scanner = scanner(0.0);
+    scanner.set_circuit_breaker(circuit_breaker.clone());
+    scanner.set_tracker(tracker.clone());
+
+    let mut best = None;
+    for usdc in [2_000_000, 3_000_000, 2_000_000, 3_000_000] {
+        let mut swinging = snapshot();
+        swinging.pools = vec![pool("uniswap", 1_000, usdc), pool("sushiswap", 1_000, usdc + 100_000)];
+        best = scanner.scan(&swinging).into_iter().next();
+    }
+    let best = best.unwrap();
+    let paper = PaperTradeExecutor::new(U256::zero());
+    best.set_paper_reserves(&paper);
+    let error = scanner.execute(&paper, &best, Address::zero()).await.unwrap_err();
+    assert!(error.to_string().contains("Volatility of WETH/USDC"), "{}", error);
+    assert_eq!(tracker.get(&best.opportunity.id).unwrap().state().name(), "risk_rejected");
+    assert!(matches!(circuit_breaker.tripped().unwrap().reason, BreakerReason::Volatility { .. }));
+}
+
+/// A node whose only contracts are Chainlink feeds: `latestRoundData` at a
+/// feed address answers its price with 8 decimals, updated just now.
+fn chainlink_node(prices: Vec<(Address, i64)>) -> String {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+    thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            let call: Value = serde_json::from_str(&body).unwrap();
+            let tx = &call["params"][0];
+            let to: Address = tx["to"].as_str().unwrap().parse().unwrap();
+            let data = tx["input"].as_str().or(tx["data"].as_str()).unwrap();
+            let price = prices.iter().find(|(feed, _)| *feed == to).unwrap().1;
+            let now = U256::from(Utc::now().timestamp());
+            let result = match &data[..10] {
+                "0x313ce567" => encode(&[Token::Uint(8.into())]),
+                _ => encode(&[Token::Uint(1.into()), Token::Int(I256::from(price * 100_000_000).into_raw()), Token::Uint(now), Token::Uint(now), Token::Uint(1.into())]),
+            };
+            let response = json!({"jsonrpc": "2.0", "id": call["id"], "result": format!("0x{}", ethers::utils::hex::encode(result))});
+            let _ = request.respond(tiny_http::Response::from_string(response.to_string()));
+        }
+    });
+    url
+}
+
+#[tokio::test]
+async fn test_oracle_guard_rejects_off_market_pools() {
+    let chain = mainnet();
+    let (weth, usdc) = (chain.token("WETH").unwrap().address, chain.token("USDC").unwrap().address);
+    let (weth_feed, usdc_feed) = (Address::from_low_u64_be(1), Address::from_low_u64_be(2));
+    let url = chainlink_node(vec![(weth_feed, 2_000), (usdc_feed, 1)]);
+    let mut guard = OracleGuard::new(&url, 3_600, 200.0).unwrap();
+    guard.add_feed(weth, weth_feed);
+    guard.add_feed(usdc, usdc_feed);
+    let tracker = Arc::new(OpportunityTracker::new());
+    let mut scanner = scanner(0.0);
+    scanner.set_oracle_guard(guard);
+    scanner.set_tracker(tracker.clone());
+
+    // Sushiswap prices WETH 5% above the feeds
+    let best = scanner.scan(&snapshot()).remove(0);
+    let paper = PaperTradeExecutor::new(U256::zero());
+    best.set_paper_reserves(&paper);
+    let error = scanner.execute(&paper, &best, Address::zero()).await.unwrap_err();
+    assert!(error.to_string().contains("deviates"), "{}", error);
+    assert_eq!(tracker.get(&best.opportunity.id).unwrap().state().name(), "risk_rejected");
+}
+
+#[test]
+fn test_recorded_snapshots_round_trip() {
+    let path = std::env::temp_dir().join(format!("scanner_test_{}.jsonl", std::process::id()));
+    let recorder = SnapshotRecorder::open(&path).unwrap();
+    let first = snapshot();
+    let mut second = snapshot();
+    second.block_number = None;
+    second.pools[1].reserve_quote = U256::MAX;
+    recorder.record(&first).unwrap();
+    recorder.record(&second).unwrap();
+
+    assert_eq!(scanner::read_snapshots(&path).unwrap(), vec![first, second]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_old_recordings_hold_fresh_pools() {
+    let path = std::env::temp_dir().join(format!("scanner_test_old_{}.jsonl", std::process::id()));
+    let recorded = snapshot();
+    let mut line = serde_json::to_value(&recorded).unwrap();
+    for pool in line["pools"].as_array_mut().unwrap() {
+        pool.as_object_mut().unwrap().remove("reading");
+    }
+    std::fs::write(&path, format!("{}\n", line)).unwrap();
+
+    assert_eq!(scanner::read_snapshots(&path).unwrap(), vec![recorded]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// Holds each trade in flight after submitting it until `settle` is notified.
+#[derive(Default)]
+struct HeldExecutor {
+    submitted: Notify,
+    settle: Notify,
+}
+
+#[async_trait]
+impl Executor for HeldExecutor {
+    async fn execute_trade(&self, _path: Vec<Address>, _amount: U256, _min_amount_out: U256, _deadline: U256, _account: Address) -> Result<TransactionReceipt, Box<dyn std::error::Error>> {
+        unimplemented!()
+    }
+
+    async fn execute_routed_trade(&self, _trade: RoutedTrade, observer: &dyn TradeObserver) -> Result<TransactionReceipt, Box<dyn std::error::Error>> {
+        observer.simulated();
+        observer.submitted(H256::zero());
+        self.submitted.notify_one();
+        self.settle.notified().await;
+        Ok(TransactionReceipt { status: Some(1.into()), ..Default::default() })
+    }
+
+    async fn execute_flash_loan_trade(&self, _token: Address, _amount: U256, _data: Bytes) -> Result<TransactionReceipt, Box<dyn std::error::Error>> {
+        unimplemented!()
+    }
+}
+
+#[tokio::test]
+async fn test_trade_in_flight_clamps_next_opportunity() {
+    let chain = mainnet();
+    let usdc = chain.token("USDC").unwrap().address;
+    let size = scanner(0.0).scan(&snapshot())[0].position.size;
+    let mut risk = Config::default().risk;
+    risk.max_price_impact_bps = 10_000.0;
+    let mut risk_manager = risk.risk_manager(&chain);
+    risk_manager.set_token_exposure_cap(usdc, size + size / 2);
+    let scanner = Scanner::new(chain, risk_manager, 0.0);
+
+    let first = scanner.scan(&snapshot()).remove(0);
+    assert_eq!(first.position.size, size);
+    let executor = HeldExecutor::default();
+    let (result, ()) = tokio::join!(scanner.execute(&executor, &first, Address::zero()), async {
+        executor.submitted.notified().await;
+        let second = scanner.scan(&snapshot()).remove(0);
+        assert_eq!(second.position.size, size / 2);
+        assert_eq!(second.position.clamped_by, Some(ClampReason::TokenExposureCap(usdc)));
+        executor.settle.notify_one();
+    });
+    result.unwrap();
+
+    // Settling the first trade frees its exposure again
+    assert_eq!(scanner.scan(&snapshot())[0].position.size, size);
+}
@@ -0,0 +1,157 @@
+use rust_components::logger;
+use rust_components::signer::{LocalSigner, RemoteSigner, SignerError, SignerPool, TransactionSigner};
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, TransactionRequest};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+// First default anvil/hardhat development account
+const DEV_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+const DEV_ADDRESS: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+
+fn transaction() -> TypedTransaction {
+    TransactionRequest::new()
+        .to(Address::from_low_u64_be(1))
+        .value(1u64)
+        .nonce(0u64)
+        .gas(21_000u64)
+        .gas_price(1u64)
+        .chain_id(31337u64)
+        .into()
+}
+
+/// Serves a single HTTP request with `body` and hands back its method, URL,
+/// headers and body.
+fn serve_once(body: String) -> (String, mpsc::Receiver<String>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut request = server.recv().unwrap();
+        let mut request_body = String::new();
+        request.as_reader().read_to_string(&mut request_body).unwrap();
+        let headers: Vec<String> = request.headers().iter().map(|header| header.to_string()).collect();
+        sender.send(format!("{} {} {}\n{}", request.method(), request.url(), headers.join("\n"), request_body)).unwrap();
+        let header = tiny_http::Header::from_bytes("Content-Type", "application/json").unwrap();
+        request.respond(tiny_http::Response::from_string(body).with_header(header)).unwrap();
+    });
+
+    (url, receiver)
+}
+
+#[test]
+fn test_local_signer_from_env() {
+    std::env::set_var("SIGNER_TEST_PRIVATE_KEY", format!("0x{}", DEV_KEY));
+    let signer = LocalSigner::from_env("SIGNER_TEST_PRIVATE_KEY", 31337).unwrap();
+
+    assert_eq!(signer.address(), DEV_ADDRESS.parse::<Address>().unwrap());
+    assert_eq!(signer.chain_id(), 31337);
+}
+
+#[test]
+fn test_local_signer_missing_env_var() {
+    let result = LocalSigner::from_env("SIGNER_TEST_UNSET_VARIABLE", 1);
+    assert!(matches!(result, Err(SignerError::MissingEnvVar(var)) if var == "SIGNER_TEST_UNSET_VARIABLE"));
+}
+
+#[test]
+fn test_invalid_key_is_not_echoed() {
+    std::env::set_var("SIGNER_TEST_BAD_KEY", "0xnot-a-key");
+    let error = LocalSigner::from_env("SIGNER_TEST_BAD_KEY", 1).unwrap_err();
+
+    assert!(error.to_string().contains("SIGNER_TEST_BAD_KEY"));
+    assert!(!error.to_string().contains("not-a-key"));
+}
+
+#[test]
+fn test_loaded_keys_are_redacted_from_logs() {
+    let path = std::env::temp_dir().join(format!("signer-test-key-{}", std::process::id()));
+    std::fs::write(&path, DEV_KEY).unwrap();
+    LocalSigner::from_file(&path, 1).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let mixed_case: String = DEV_KEY
+        .chars()
+        .enumerate()
+        .map(|(index, c)| if index % 2 == 0 { c.to_ascii_uppercase() } else { c })
+        .collect();
+    let message = logger::redact(&format!("loaded 0x{} and {} and 0X{}", DEV_KEY, DEV_KEY.to_uppercase(), mixed_case));
+    assert_eq!(message, "loaded [REDACTED] and [REDACTED] and [REDACTED]");
+}
+
+#[test]
+fn test_local_signer_from_keystore() {
+    let dir = std::env::temp_dir().join(format!("signer-test-keystore-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut rng = ethers::core::rand::thread_rng();
+    let (wallet, uuid) = LocalWallet::new_keystore(&dir, &mut rng, "passphrase", None).unwrap();
+
+    let signer = LocalSigner::from_keystore(dir.join(&uuid), "passphrase", 5).unwrap();
+    assert_eq!(signer.address(), wallet.address());
+    assert!(LocalSigner::from_keystore(dir.join(&uuid), "wrong", 5).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_local_signer_signs_transaction() {
+    let wallet: LocalWallet = DEV_KEY.parse().unwrap();
+    std::env::set_var("SIGNER_TEST_SIGNING_KEY", DEV_KEY);
+    let signer = LocalSigner::from_env("SIGNER_TEST_SIGNING_KEY", 31337).unwrap();
+
+    let tx = transaction();
+    let signature = signer.sign_transaction(&tx).await.unwrap();
+    assert_eq!(signature.recover(tx.sighash()).unwrap(), wallet.address());
+}
+
+#[tokio::test]
+async fn test_remote_signer() {
+    let wallet: LocalWallet = DEV_KEY.parse::<LocalWallet>().unwrap().with_chain_id(31337u64);
+    let tx = transaction();
+    let expected = wallet.sign_transaction(&tx).await.unwrap();
+    let (url, requests) = serve_once(format!(r#"{{"signature":{}}}"#, serde_json::to_string(&expected).unwrap()));
+
+    let signer = RemoteSigner::new(&url, wallet.address(), 31337, Some("secret-token".to_string()));
+    let signature = signer.sign_transaction(&tx).await.unwrap();
+
+    assert_eq!(signature, expected);
+    let request = requests.recv().unwrap();
+    assert!(request.starts_with("POST /sign "));
+    assert!(request.contains("Bearer secret-token"));
+    assert!(request.to_lowercase().contains(&format!("{:?}", wallet.address())));
+}
+
+#[tokio::test]
+async fn test_remote_signer_rejects_other_keys() {
+    let tx = transaction();
+    // Second default anvil/hardhat development account
+    let other: LocalWallet = "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d".parse::<LocalWallet>().unwrap().with_chain_id(31337u64);
+    let signature = other.sign_transaction(&tx).await.unwrap();
+    let (url, _requests) = serve_once(format!(r#"{{"signature":{}}}"#, serde_json::to_string(&signature).unwrap()));
+
+    let expected = DEV_ADDRESS.parse::<Address>().unwrap();
+    let signer = RemoteSigner::new(&url, expected, 31337, None);
+    let error = signer.sign_transaction(&tx).await.unwrap_err();
+    assert!(matches!(error, SignerError::WrongSigner { recovered, .. } if recovered == other.address()), "{}", error);
+}
+
+#[test]
+fn test_signer_pool() {
+    let mut pool = SignerPool::new();
+    assert!(pool.next_signer().is_none());
+
+    let first = Arc::new(RemoteSigner::new("http://127.0.0.1:1", Address::from_low_u64_be(1), 1, None));
+    let second = Arc::new(RemoteSigner::new("http://127.0.0.1:1", Address::from_low_u64_be(2), 1, None));
+    pool.add(first);
+    pool.add(second);
+
+    assert_eq!(pool.addresses(), vec![Address::from_low_u64_be(1), Address::from_low_u64_be(2)]);
+    assert_eq!(pool.next_signer().unwrap().address(), Address::from_low_u64_be(1));
+    assert_eq!(pool.next_signer().unwrap().address(), Address::from_low_u64_be(2));
+    assert_eq!(pool.next_signer().unwrap().address(), Address::from_low_u64_be(1));
+    assert!(pool.get(Address::from_low_u64_be(2)).is_ok());
+    assert!(matches!(pool.get(Address::from_low_u64_be(3)), Err(SignerError::UnknownAccount(_))));
+}
@@ -0,0 +1,118 @@
+use rust_components::arbitrage_finder::ArbitrageOpportunity;
+use rust_components::config::Config;
+use rust_components::paper_executor::PaperTradeExecutor;
+use rust_components::risk_manager::{PoolReserves, RiskManager, SizingRequest};
+use rust_components::scanner::{MarketSnapshot, PoolSnapshot, ReserveReading, Scanner};
+use rust_components::spans::{self, opportunity_span, SpanExporter};
+use chrono::Utc;
+use ethers::types::{Address, U256};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::Registry;
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Buffer {
+    fn spans(&self) -> Vec<serde_json::Value> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+}
+
+fn with_exporter<F: FnOnce()>(buffer: &Buffer, f: F) {
+    let subscriber = Registry::default().with(SpanExporter::to_writer(Box::new(buffer.clone())));
+    tracing::subscriber::with_default(subscriber, f);
+}
+
+#[test]
+fn test_stage_spans_inherit_opportunity() {
+    let buffer = Buffer::default();
+    let opportunity = ArbitrageOpportunity::new("Uniswap", "Sushiswap", 100.0).with_block_number(42);
+    let ether = U256::exp10(18);
+    let request = SizingRequest {
+        token: Address::from_low_u64_be(1),
+        buy_pool: PoolReserves::new(ether * 1_000, ether * 2_000_000),
+        sell_pool: PoolReserves::new(ether * 1_900_000, ether * 1_000),
+        available_balance: ether * 1_000,
+    };
+
+    with_exporter(&buffer, || {
+        opportunity_span(&opportunity).in_scope(|| {
+            RiskManager::new(ether * 1_000, 0.1).calculate_position_size(&opportunity, &request);
+        });
+    });
+
+    let exported = buffer.spans();
+    assert_eq!(exported.len(), 2);
+    assert_eq!(exported[0]["stage"], "risk");
+    assert_eq!(exported[0]["parent"], "opportunity");
+    assert_eq!(exported[1]["stage"], "opportunity");
+    for span in &exported {
+        assert_eq!(span["opportunity_id"], opportunity.id.as_str());
+        assert_eq!(span["block_number"], 42);
+        assert!(span["duration_us"].is_u64());
+    }
+}
+
+#[test]
+fn test_current_context_follows_entered_spans() {
+    let buffer = Buffer::default();
+    let opportunity = ArbitrageOpportunity::new("Uniswap", "Curve", 1.0);
+
+    with_exporter(&buffer, || {
+        assert_eq!(spans::current_context(), None);
+        opportunity_span(&opportunity).in_scope(|| {
+            let stage = tracing::info_span!(target: "rust_components::trade_executor", "simulate");
+            let _entered = stage.enter();
+            let context = spans::current_context().unwrap();
+            assert_eq!(context.stage, "simulate");
+            assert_eq!(context.opportunity_id.as_deref(), Some(opportunity.id.as_str()));
+            assert_eq!(context.block_number, None);
+        });
+        assert_eq!(spans::current_context(), None);
+    });
+}
+
+#[tokio::test]
+async fn test_scanner_sizing_and_execution_carry_opportunity() {
+    let buffer = Buffer::default();
+    let config = Config::parse(include_str!("../config.example.toml"), "example", Vec::new()).unwrap();
+    let mut risk = config.risk.clone();
+    risk.max_price_impact_bps = 10_000.0;
+    let chain = config.chain("mainnet").unwrap().clone();
+    let scanner = Scanner::new(chain.clone(), risk.risk_manager(&chain), 0.0);
+    let pool = |dex: &str, usdc: u64| PoolSnapshot {
+        dex: dex.to_string(),
+        pair: "WETH/USDC".to_string(),
+        reserve_base: U256::exp10(21),
+        reserve_quote: U256::from(usdc) * U256::exp10(6),
+        reading: ReserveReading::Fresh,
+    };
+    let snapshot = MarketSnapshot { chain: "mainnet".to_string(), block_number: Some(7), at: Utc::now(), pools: vec![pool("uniswap", 2_000_000), pool("sushiswap", 2_100_000)] };
+
+    let _default = tracing::subscriber::set_default(Registry::default().with(SpanExporter::to_writer(Box::new(buffer.clone()))));
+    let best = scanner.scan(&snapshot).remove(0);
+    let paper = PaperTradeExecutor::new(U256::zero());
+    best.set_paper_reserves(&paper);
+    scanner.execute(&paper, &best, Address::zero()).await.unwrap();
+
+    let exported: Vec<_> = buffer.spans().into_iter().filter(|span| span["opportunity_id"] == best.opportunity.id.as_str()).collect();
+    let stages: Vec<_> = exported.iter().map(|span| span["stage"].as_str().unwrap()).collect();
+    assert_eq!(stages, ["risk", "opportunity", "opportunity"]);
+    assert!(exported.iter().all(|span| span["block_number"] == 7));
+}
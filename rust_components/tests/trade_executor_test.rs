@@ -0,0 +1,202 @@
+use rust_components::gas_strategy::GasStrategyError;
+use rust_components::provider_pool::ProviderPool;
+use rust_components::trade_executor::{decode_revert_reason, RoutedTrade, SimulationError, SimulationResult, TradeExecutor};
+use ethers::abi::{decode, encode, ParamType, Token};
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::types::{Address, Bytes, TransactionRequest, U256};
+use ethers::utils::{hex, keccak256};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// Mainnet addresses, for the simulation tests against an anvil fork:
+// `anvil --fork-url <mainnet rpc>`
+const UNISWAP_V2_ROUTER: &str = "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D";
+const WETH: &str = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2";
+const DAI: &str = "0x6B175474E89094C44Da98b954EedeAC495271d0F";
+
+fn anvil_url() -> String {
+    std::env::var("ANVIL_RPC_URL").unwrap_or_else(|_| "http://127.0.0.1:8545".to_string())
+}
+
+fn fork_executor() -> TradeExecutor {
+    TradeExecutor::new(&anvil_url(), UNISWAP_V2_ROUTER.parse().unwrap(), Address::zero())
+}
+
+/// A fresh account on the fork holding 1 WETH that the router may spend.
+async fn funded_account() -> Address {
+    let provider = Provider::<Http>::try_from(anvil_url()).unwrap();
+    let (account, weth, router): (Address, Address, Address) = (Address::random(), WETH.parse().unwrap(), UNISWAP_V2_ROUTER.parse().unwrap());
+    provider.request::<_, ()>("anvil_setBalance", (account, U256::exp10(19))).await.unwrap();
+    provider.request::<_, ()>("anvil_impersonateAccount", [account]).await.unwrap();
+
+    // WETH deposit(), then approve(router, amount)
+    let deposit = TransactionRequest::new().from(account).to(weth).value(U256::exp10(18)).data(keccak256("deposit()")[..4].to_vec());
+    let mut approve = keccak256("approve(address,uint256)")[..4].to_vec();
+    approve.extend(encode(&[Token::Address(router), Token::Uint(U256::exp10(18))]));
+    let approve = TransactionRequest::new().from(account).to(weth).data(approve);
+    for tx in [deposit, approve] {
+        let receipt = provider.send_transaction(tx, None).await.unwrap().await.unwrap().unwrap();
+        assert_eq!(receipt.status, Some(1u64.into()));
+    }
+    account
+}
+
+fn revert_data(reason: &str) -> Bytes {
+    // Error(string) selector followed by the ABI-encoded reason
+    let mut data = vec![0x08, 0xc3, 0x79, 0xa0];
+    data.extend(encode(&[Token::String(reason.to_string())]));
+    Bytes::from(data)
+}
+
+#[test]
+fn test_decode_revert_reason() {
+    assert_eq!(decode_revert_reason(&revert_data("Transaction expired")), "Transaction expired");
+    assert_eq!(decode_revert_reason(&revert_data("No profit")), "No profit");
+}
+
+#[test]
+fn test_decode_revert_reason_unknown_selector_as_hex() {
+    let data = Bytes::from(vec![0xde, 0xad, 0xbe, 0xef]);
+    assert_eq!(decode_revert_reason(&data), "0xdeadbeef");
+}
+
+#[test]
+fn test_decode_revert_reason_bot_custom_error() {
+    // OwnableUnauthorizedAccount(address), as reverted by `onlyOwner`
+    let account = Address::from_low_u64_be(0xabc);
+    let mut data = keccak256("OwnableUnauthorizedAccount(address)")[..4].to_vec();
+    data.extend(encode(&[Token::Address(account)]));
+    assert_eq!(decode_revert_reason(&Bytes::from(data)), format!("OwnableUnauthorizedAccount({:?})", account));
+
+    let data = Bytes::from(keccak256("FailedInnerCall()")[..4].to_vec());
+    assert_eq!(decode_revert_reason(&data), "FailedInnerCall()");
+}
+
+/// Node whose routers return a little more than they are given on every hop
+/// and that charges `gas_price`. Records the methods it is called with.
+fn node_with_gas_price(gas_price: U256) -> (String, Arc<Mutex<Vec<String>>>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+    let methods = Arc::new(Mutex::new(Vec::new()));
+    let seen = methods.clone();
+    thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            let call: Value = serde_json::from_str(&body).unwrap();
+            let method = call["method"].as_str().unwrap().to_string();
+            seen.lock().unwrap().push(method.clone());
+            let result = match method.as_str() {
+                "eth_call" => {
+                    let tx = &call["params"][0];
+                    let data = tx["data"].as_str().or(tx["input"].as_str()).unwrap();
+                    let data = hex::decode(data.trim_start_matches("0x")).unwrap();
+                    // getAmountsOut(uint256,address[])
+                    if data[..4] == [0xd0, 0x6c, 0xa6, 0x1f] {
+                        let args = decode(&[ParamType::Uint(256), ParamType::Array(Box::new(ParamType::Address))], &data[4..]).unwrap();
+                        let amount = args[0].clone().into_uint().unwrap();
+                        let amounts = vec![Token::Uint(amount), Token::Uint(amount + U256::exp10(15))];
+                        json!(format!("0x{}", hex::encode(encode(&[Token::Array(amounts)]))))
+                    } else {
+                        json!("0x")
+                    }
+                }
+                "eth_estimateGas" => json!("0x30d40"),
+                "eth_gasPrice" => json!(format!("{:#x}", gas_price)),
+                "eth_chainId" => json!("0x1"),
+                _ => Value::Null,
+            };
+            let response = json!({"jsonrpc": "2.0", "id": call["id"], "result": result});
+            let _ = request.respond(tiny_http::Response::from_string(response.to_string()));
+        }
+    });
+    (url, methods)
+}
+
+#[tokio::test]
+async fn test_bot_trade_gas_capped_to_profit() {
+    // A WETH round trip expected to make 2e15 wei, half of which may go to gas
+    let (url, methods) = node_with_gas_price(U256::exp10(10));
+    let provider = Arc::new(ProviderPool::new(&[url]).unwrap().into_provider());
+    let router: Address = UNISWAP_V2_ROUTER.parse().unwrap();
+    let mut executor = TradeExecutor::with_provider(provider, router, Address::zero());
+    executor.set_arbitrage_bot(Address::from_low_u64_be(7));
+    let weth: Address = WETH.parse().unwrap();
+    let trade = RoutedTrade {
+        routers: vec![router, router],
+        path: vec![weth, DAI.parse().unwrap(), weth],
+        amount: U256::exp10(18),
+        min_amount_out: U256::exp10(18),
+        deadline: U256::from(u64::MAX),
+        account: Address::from_low_u64_be(8),
+    };
+
+    // 240_000 gas at 10 gwei is 2.4e15 wei, over the 1e15 budget
+    let error = executor.execute_bot_trade(&trade, &()).await.unwrap_err();
+    match error.downcast_ref::<GasStrategyError>() {
+        Some(GasStrategyError::OverBudget { gas_cost, budget }) => {
+            assert_eq!(*gas_cost, U256::from(240_000u64) * U256::exp10(10));
+            assert_eq!(*budget, U256::exp10(15));
+        }
+        other => panic!("expected OverBudget, got {:?} ({})", other, error),
+    }
+    assert!(!methods.lock().unwrap().iter().any(|method| method.starts_with("eth_send")));
+}
+
+#[test]
+fn test_check_output_covers_costs() {
+    let result = SimulationResult { amounts: vec![U256::from(100u64), U256::from(150u64)] };
+    assert!(result.check_output(U256::from(120u64), U256::from(30u64)).is_ok());
+}
+
+#[test]
+fn test_check_output_below_costs() {
+    let result = SimulationResult { amounts: vec![U256::from(100u64), U256::from(150u64)] };
+    assert_eq!(
+        result.check_output(U256::from(120u64), U256::from(31u64)),
+        Err(SimulationError::InsufficientOutput { amount_out: U256::from(150u64), required: U256::from(151u64) })
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires a local anvil node forked from mainnet"]
+async fn test_simulate_trade_on_fork() {
+    let executor = fork_executor();
+    let path = vec![WETH.parse().unwrap(), DAI.parse().unwrap()];
+    let account = funded_account().await;
+
+    let result = executor
+        .simulate_trade(path, U256::exp10(18), U256::zero(), U256::MAX, account)
+        .await
+        .unwrap();
+
+    assert_eq!(result.amounts.len(), 2);
+    assert!(result.amount_out() > U256::zero());
+}
+
+#[tokio::test]
+#[ignore = "requires a local anvil node forked from mainnet"]
+async fn test_simulate_trade_decodes_expired_deadline() {
+    let executor = fork_executor();
+    let path = vec![WETH.parse().unwrap(), DAI.parse().unwrap()];
+
+    let result = executor
+        .simulate_trade(path, U256::exp10(18), U256::zero(), U256::one(), Address::random())
+        .await;
+
+    assert_eq!(result, Err(SimulationError::Reverted("UniswapV2Router: EXPIRED".to_string())));
+}
+
+#[tokio::test]
+#[ignore = "requires a local anvil node forked from mainnet"]
+async fn test_simulate_trade_decodes_insufficient_output() {
+    let executor = fork_executor();
+    let path = vec![WETH.parse().unwrap(), DAI.parse().unwrap()];
+
+    let result = executor
+        .simulate_trade(path, U256::exp10(18), U256::MAX, U256::MAX, Address::random())
+        .await;
+
+    assert_eq!(result, Err(SimulationError::Reverted("UniswapV2Router: INSUFFICIENT_OUTPUT_AMOUNT".to_string())));
+}
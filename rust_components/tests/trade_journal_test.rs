@@ -0,0 +1,110 @@
+use rust_components::arbitrage_finder::ArbitrageOpportunity;
+use rust_components::opportunity_tracker::{OpportunityState, OpportunityTracker};
+use rust_components::trade_journal::{DailyPnl, TradeJournal};
+use chrono::{NaiveDate, TimeZone, Utc};
+use ethers::types::{Address, TransactionReceipt, H256, U256};
+use std::sync::Arc;
+
+fn receipt(hash: H256, gas_used: u64, gas_price: u64) -> TransactionReceipt {
+    TransactionReceipt {
+        transaction_hash: hash,
+        status: Some(1u64.into()),
+        gas_used: Some(gas_used.into()),
+        effective_gas_price: Some(gas_price.into()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_migrations_survive_reopen() {
+    let path = std::env::temp_dir().join(format!("trade_journal_test_{}.sqlite", std::process::id()));
+    let opportunity = ArbitrageOpportunity::new("Uniswap", "Sushiswap", 2.0);
+    {
+        let journal = TradeJournal::open(&path).unwrap();
+        assert_eq!(journal.schema_version().unwrap(), 2);
+        journal.record_opportunity(&opportunity).unwrap();
+    }
+
+    let journal = TradeJournal::open(&path).unwrap();
+    assert_eq!(journal.schema_version().unwrap(), 2);
+    let history = journal.state_history(&opportunity.id).unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].state, "detected");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_tracker_journals_lifecycle() {
+    let journal = Arc::new(TradeJournal::open_in_memory().unwrap());
+    let mut tracker = OpportunityTracker::new();
+    tracker.set_journal(journal.clone());
+
+    let hash = H256::from_low_u64_be(7);
+    let opportunity = ArbitrageOpportunity::new("Uniswap", "Sushiswap", 2.0);
+    tracker.detect(opportunity.clone());
+    tracker.transition(&opportunity.id, OpportunityState::RiskApproved).unwrap();
+    tracker.transition(&opportunity.id, OpportunityState::Simulated).unwrap();
+    tracker.transition(&opportunity.id, OpportunityState::Submitted(hash)).unwrap();
+
+    let rejected = ArbitrageOpportunity::new("Uniswap", "Curve", 0.1);
+    tracker.detect(rejected.clone());
+    tracker.transition(&rejected.id, OpportunityState::RiskRejected("position too small".to_string())).unwrap();
+
+    let states: Vec<String> = journal.state_history(&opportunity.id).unwrap().into_iter().map(|entry| entry.state).collect();
+    assert_eq!(states, vec!["detected", "risk_approved", "simulated", "submitted"]);
+    assert_eq!(journal.opportunity_for_transaction(hash).unwrap(), Some(opportunity.id.clone()));
+
+    let history = journal.state_history(&rejected.id).unwrap();
+    assert_eq!(history[1].detail.as_deref(), Some("position too small"));
+}
+
+#[test]
+fn test_submissions_keep_broadcast_time() {
+    let journal = TradeJournal::open_in_memory().unwrap();
+    let opportunity = ArbitrageOpportunity::new("Uniswap", "Sushiswap", 2.0);
+    let (hash, replacement, sender) = (H256::from_low_u64_be(1), H256::from_low_u64_be(2), Address::from_low_u64_be(3));
+    let broadcast_at = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+    let settled_at = Utc.with_ymd_and_hms(2024, 5, 1, 12, 5, 0).unwrap();
+    journal.record_opportunity(&opportunity).unwrap();
+
+    journal.record_submission(hash, sender, None, broadcast_at).unwrap();
+    journal.record_state(&opportunity.id, &OpportunityState::Submitted(hash), broadcast_at).unwrap();
+    journal.record_submission(replacement, sender, Some(hash), settled_at).unwrap();
+    journal.record_receipt(&receipt(replacement, 21_000, 1), Some(0.5), settled_at).unwrap();
+
+    assert_eq!(journal.submitted_at(hash).unwrap(), Some(broadcast_at));
+    assert_eq!(journal.submitted_at(replacement).unwrap(), Some(broadcast_at));
+    assert_eq!(journal.opportunity_for_transaction(replacement).unwrap(), Some(opportunity.id));
+}
+
+#[test]
+fn test_daily_pnl_by_pair_chain_and_venue() {
+    let journal = TradeJournal::open_in_memory().unwrap();
+    let day = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+    let settled_at = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+
+    for (index, (buy, sell, pnl, chain_id)) in [("Uniswap", "Sushiswap", 10.0, 1), ("Uniswap", "Curve", -4.0, 42161)].into_iter().enumerate() {
+        let hash = H256::from_low_u64_be(index as u64 + 1);
+        let opportunity = ArbitrageOpportunity::new(buy, sell, pnl).with_pair("WETH/USDC").with_chain_id(chain_id);
+        journal.record_opportunity(&opportunity).unwrap();
+        journal.record_state(&opportunity.id, &OpportunityState::Submitted(hash), settled_at).unwrap();
+        journal.record_receipt(&receipt(hash, 100_000, 20), Some(pnl), settled_at).unwrap();
+    }
+    // A trade sent outside the tracker has no pair or venues.
+    journal.record_receipt(&receipt(H256::from_low_u64_be(9), 50_000, 20), Some(1.0), settled_at).unwrap();
+
+    assert_eq!(journal.daily_pnl_by_pair(day).unwrap(), vec![
+        DailyPnl { day, key: "WETH/USDC".to_string(), realized_pnl: 6.0, trades: 2 },
+        DailyPnl { day, key: "unknown".to_string(), realized_pnl: 1.0, trades: 1 },
+    ]);
+    let chains: Vec<(String, f64)> = journal.daily_pnl_by_chain(day).unwrap().into_iter().map(|pnl| (pnl.key, pnl.realized_pnl)).collect();
+    assert_eq!(chains, vec![("1".to_string(), 10.0), ("42161".to_string(), -4.0), ("unknown".to_string(), 1.0)]);
+    let venues = journal.daily_pnl_by_venue(day).unwrap();
+    let uniswap = venues.iter().find(|pnl| pnl.key == "Uniswap").unwrap();
+    assert_eq!((uniswap.realized_pnl, uniswap.trades), (6.0, 2));
+    let curve = venues.iter().find(|pnl| pnl.key == "Curve").unwrap();
+    assert_eq!(curve.realized_pnl, -4.0);
+
+    assert_eq!(journal.gas_paid(day).unwrap(), U256::from(5_000_000u64));
+    assert!(journal.daily_pnl_by_pair(day.succ_opt().unwrap()).unwrap().is_empty());
+}
@@ -0,0 +1,285 @@
+use rust_components::gas_strategy::GasFees;
+use rust_components::provider_pool::{PooledProvider, ProviderPool};
+use rust_components::signer::{LocalSigner, SignerPool, TransactionSigner};
+use rust_components::transaction_manager::{bump_fees, load_pending, save_pending, TrackedTransaction, TransactionManager, TransactionManagerConfig, TransactionManagerError};
+use ethers::providers::Middleware;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, TransactionReceipt, TransactionRequest, H256, U256, U64};
+use ethers::utils::rlp::Rlp;
+use ethers::utils::keccak256;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+fn anvil_url() -> String {
+    std::env::var("ANVIL_RPC_URL").unwrap_or_else(|_| "http://127.0.0.1:8545".to_string())
+}
+
+fn provider() -> Arc<PooledProvider> {
+    Arc::new(ProviderPool::new(&[anvil_url()]).unwrap().into_provider())
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("{}-{}.json", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn tracked(account: Address, nonce: u64) -> TrackedTransaction {
+    let tx: TypedTransaction = TransactionRequest::new().from(account).to(account).nonce(nonce).into();
+    TrackedTransaction {
+        account,
+        nonce: U256::from(nonce),
+        tx,
+        fees: GasFees::Legacy { gas_price: U256::from(100u64) },
+        hashes: vec![H256::from_low_u64_be(nonce)],
+        submitted_block: 10,
+        replacements: 0,
+    }
+}
+
+/// A node that answers every raw transaction with `send_error`, as a JSON-RPC
+/// error or, if `None`, as an HTTP 503. Returns its url and the raw
+/// transactions it received.
+fn node_failing_sends(send_error: Option<&'static str>) -> (String, Arc<Mutex<Vec<String>>>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let thread_received = received.clone();
+    thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            let call: Value = serde_json::from_str(&body).unwrap();
+            let response = match (call["method"].as_str().unwrap(), send_error) {
+                ("eth_blockNumber", _) => json!({"jsonrpc": "2.0", "id": call["id"], "result": "0x10"}),
+                ("eth_getTransactionCount", _) => json!({"jsonrpc": "2.0", "id": call["id"], "result": "0x0"}),
+                ("eth_sendRawTransaction", Some(message)) => {
+                    thread_received.lock().unwrap().push(call["params"][0].as_str().unwrap().to_string());
+                    json!({"jsonrpc": "2.0", "id": call["id"], "error": {"code": -32000, "message": message}})
+                }
+                ("eth_sendRawTransaction", None) => {
+                    thread_received.lock().unwrap().push(call["params"][0].as_str().unwrap().to_string());
+                    let _ = request.respond(tiny_http::Response::from_string("unavailable").with_status_code(503));
+                    continue;
+                }
+                (other, _) => json!({"jsonrpc": "2.0", "id": call["id"], "error": {"code": -32601, "message": other}}),
+            };
+            let _ = request.respond(tiny_http::Response::from_string(response.to_string()));
+        }
+    });
+    (url, received)
+}
+
+/// A node whose block number goes up on every call and that never mines
+/// `stuck_nonce`. Its pending count covers every nonce it was sent. Returns its
+/// url and the raw transactions it received.
+fn node_stuck_at(stuck_nonce: u64) -> (String, Arc<Mutex<Vec<String>>>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+    let received = Arc::new(Mutex::new(Vec::<String>::new()));
+    let thread_received = received.clone();
+    thread::spawn(move || {
+        let mut block = 0x10u64;
+        let nonce_of = |raw: &str| {
+            let bytes = ethers::utils::hex::decode(raw.trim_start_matches("0x")).unwrap();
+            TypedTransaction::decode_signed(&Rlp::new(&bytes)).unwrap().0.nonce().unwrap().as_u64()
+        };
+        for mut request in server.incoming_requests() {
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            let call: Value = serde_json::from_str(&body).unwrap();
+            let raws = thread_received.lock().unwrap().clone();
+            let result = match call["method"].as_str().unwrap() {
+                "eth_blockNumber" => {
+                    block += 1;
+                    json!(format!("{:#x}", block))
+                }
+                "eth_getTransactionCount" => {
+                    let nonces: std::collections::BTreeSet<u64> = raws.iter().map(|raw| nonce_of(raw)).collect();
+                    json!(format!("{:#x}", nonces.len()))
+                }
+                "eth_sendRawTransaction" => {
+                    let raw = call["params"][0].as_str().unwrap().to_string();
+                    thread_received.lock().unwrap().push(raw.clone());
+                    json!(raw_hash(&raw))
+                }
+                "eth_getTransactionReceipt" => {
+                    let hash: H256 = serde_json::from_value(call["params"][0].clone()).unwrap();
+                    match raws.iter().find(|raw| raw_hash(raw) == hash && nonce_of(raw) != stuck_nonce) {
+                        Some(_) => {
+                            let receipt = TransactionReceipt { transaction_hash: hash, block_number: Some(U64::from(block)), status: Some(U64::from(1)), ..Default::default() };
+                            serde_json::to_value(receipt).unwrap()
+                        }
+                        None => Value::Null,
+                    }
+                }
+                _ => Value::Null,
+            };
+            let response = json!({"jsonrpc": "2.0", "id": call["id"], "result": result});
+            let _ = request.respond(tiny_http::Response::from_string(response.to_string()));
+        }
+    });
+    (url, received)
+}
+
+/// A manager signing with the first anvil/hardhat development account.
+fn signing_manager(url: String, persistence_path: Option<PathBuf>) -> (TransactionManager, Address) {
+    signing_manager_with(url, TransactionManagerConfig { persistence_path, ..Default::default() })
+}
+
+fn signing_manager_with(url: String, config: TransactionManagerConfig) -> (TransactionManager, Address) {
+    let provider = Arc::new(ProviderPool::new(&[url]).unwrap().into_provider());
+    std::env::set_var("TX_MANAGER_TEST_PRIVATE_KEY", "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80");
+    let signer = LocalSigner::from_env("TX_MANAGER_TEST_PRIVATE_KEY", 31337).unwrap();
+    let account = signer.address();
+    let mut signers = SignerPool::new();
+    signers.add(Arc::new(signer));
+    let mut manager = TransactionManager::new(provider, config).unwrap();
+    manager.set_signers(Arc::new(signers));
+    (manager, account)
+}
+
+fn transfer(account: Address) -> TypedTransaction {
+    TransactionRequest::new().from(account).to(account).value(1u64).gas(21_000u64).into()
+}
+
+fn raw_hash(raw: &str) -> H256 {
+    H256::from(keccak256(ethers::utils::hex::decode(raw.trim_start_matches("0x")).unwrap()))
+}
+
+#[test]
+fn test_bump_fees() {
+    let eip1559 = GasFees::Eip1559 {
+        base_fee_per_gas: U256::from(100u64),
+        max_fee_per_gas: U256::from(250u64),
+        max_priority_fee_per_gas: U256::from(50u64),
+    };
+    assert_eq!(
+        bump_fees(eip1559, 12),
+        GasFees::Eip1559 {
+            base_fee_per_gas: U256::from(100u64),
+            max_fee_per_gas: U256::from(280u64),
+            max_priority_fee_per_gas: U256::from(56u64),
+        }
+    );
+    assert_eq!(
+        bump_fees(GasFees::Legacy { gas_price: U256::from(100u64) }, 10),
+        GasFees::Legacy { gas_price: U256::from(110u64) }
+    );
+}
+
+#[test]
+fn test_save_and_load_pending() {
+    let path = temp_path("tx-manager-roundtrip");
+    let pending = vec![tracked(Address::from_low_u64_be(1), 3), tracked(Address::from_low_u64_be(2), 7)];
+
+    save_pending(&path, &pending).unwrap();
+    assert_eq!(load_pending(&path).unwrap(), pending);
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(load_pending(&path).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_restart_restores_pending_and_nonces() {
+    let path = temp_path("tx-manager-restore");
+    let account = Address::from_low_u64_be(1);
+    save_pending(&path, &[tracked(account, 4), tracked(account, 5)]).unwrap();
+
+    let config = TransactionManagerConfig { persistence_path: Some(path.clone()), ..Default::default() };
+    let manager = TransactionManager::new(provider(), config).unwrap();
+
+    assert_eq!(manager.pending_transactions().len(), 2);
+    // Restored nonces are served from the cache without asking the node
+    assert_eq!(manager.next_nonce(account).await.unwrap(), U256::from(6u64));
+    assert_eq!(manager.next_nonce(account).await.unwrap(), U256::from(7u64));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+#[ignore = "requires a local anvil node"]
+async fn test_send_and_confirm_on_anvil() {
+    let provider = provider();
+    let account = provider.get_accounts().await.unwrap()[0];
+    let config = TransactionManagerConfig { poll_interval: Duration::from_millis(100), ..Default::default() };
+    let manager = TransactionManager::new(provider.clone(), config).unwrap();
+
+    let tx: TypedTransaction = TransactionRequest::new().from(account).to(account).value(1u64).gas(21_000u64).into();
+    let fees = GasFees::Legacy { gas_price: provider.get_gas_price().await.unwrap() };
+    let receipt = manager.send_and_confirm(tx, fees).await.unwrap();
+
+    assert_eq!(receipt.status, Some(U64::from(1)));
+    assert!(manager.pending_transactions().is_empty());
+}
+
+#[tokio::test]
+async fn test_already_known_counts_as_sent() {
+    let (url, received) = node_failing_sends(Some("already known"));
+    let (manager, account) = signing_manager(url, None);
+
+    let pending = manager.send(transfer(account), GasFees::Legacy { gas_price: U256::from(1u64) }).await.unwrap();
+
+    assert_eq!(pending.hashes, vec![raw_hash(&received.lock().unwrap()[0])]);
+    assert_eq!(manager.pending_transactions(), vec![pending]);
+}
+
+#[tokio::test]
+async fn test_sends_are_persisted_before_broadcast() {
+    let fees = GasFees::Legacy { gas_price: U256::from(1u64) };
+
+    // The node may have taken the transaction before failing, so it stays on disk
+    let path = temp_path("tx-manager-unconfirmed-send");
+    let (url, received) = node_failing_sends(None);
+    let (manager, account) = signing_manager(url, Some(path.clone()));
+    let pending = manager.send(transfer(account), fees).await.unwrap();
+    assert_eq!(pending.hashes, vec![raw_hash(&received.lock().unwrap()[0])]);
+    assert_eq!(load_pending(&path).unwrap(), vec![pending]);
+    std::fs::remove_file(&path).unwrap();
+
+    // A rejection means it never reached the mempool
+    let path = temp_path("tx-manager-rejected-send");
+    let (url, _) = node_failing_sends(Some("insufficient funds for gas * price + value"));
+    let (manager, account) = signing_manager(url, Some(path.clone()));
+    assert!(manager.send(transfer(account), fees).await.is_err());
+    assert!(manager.pending_transactions().is_empty());
+    assert!(load_pending(&path).unwrap().is_empty());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_next_trade_goes_through_after_stuck() {
+    let path = temp_path("tx-manager-stuck");
+    let (url, received) = node_stuck_at(0);
+    let config = TransactionManagerConfig {
+        stuck_after_blocks: 1,
+        max_replacements: 1,
+        poll_interval: Duration::from_millis(10),
+        persistence_path: Some(path.clone()),
+        ..Default::default()
+    };
+    let (manager, account) = signing_manager_with(url, config);
+    let fees = GasFees::Legacy { gas_price: U256::from(1u64) };
+
+    let stuck = manager.send(transfer(account), fees).await.unwrap();
+    let result = manager.wait_for_confirmation(account, stuck.nonce).await;
+    assert!(matches!(result, Err(TransactionManagerError::Stuck(_, _, 1))), "{:?}", result);
+    assert!(manager.pending_transactions().is_empty());
+    assert!(load_pending(&path).unwrap().is_empty());
+
+    // The original, its replacement and a last zero-value cancellation to self
+    let raws = received.lock().unwrap().clone();
+    assert_eq!(raws.len(), 3);
+    let bytes = ethers::utils::hex::decode(raws[2].trim_start_matches("0x")).unwrap();
+    let (cancel, _) = TypedTransaction::decode_signed(&Rlp::new(&bytes)).unwrap();
+    assert_eq!((cancel.nonce(), cancel.value()), (Some(&U256::zero()), Some(&U256::zero())));
+
+    // The nonce is re-read from the node, so the next trade does not wait on the gap
+    let receipt = manager.send_and_confirm(transfer(account), fees).await.unwrap();
+    assert_eq!(receipt.status, Some(U64::from(1)));
+    assert!(manager.pending_transactions().is_empty());
+    std::fs::remove_file(&path).unwrap();
+}
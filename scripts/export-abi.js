@@ -0,0 +1,40 @@
+const fs = require("fs");
+const path = require("path");
+const hre = require("hardhat");
+
+// Copies the compiled ArbitrageBot ABI into rust_components/abi for the Rust bindings.
+async function main() {
+  await hre.run("compile");
+
+  const artifact = await hre.artifacts.readArtifact("ConcreteArbitrageBot");
+  const outputDir = path.join(__dirname, "..", "rust_components", "abi");
+  const outputPath = path.join(outputDir, "ConcreteArbitrageBot.json");
+
+  fs.mkdirSync(outputDir, { recursive: true });
+  fs.writeFileSync(
+    outputPath,
+    JSON.stringify(
+      {
+        _format: artifact._format,
+        contractName: artifact.contractName,
+        sourceName: artifact.sourceName,
+        abi: artifact.abi,
+        bytecode: "0x",
+        deployedBytecode: "0x",
+        linkReferences: {},
+        deployedLinkReferences: {},
+      },
+      null,
+      2
+    ) + "\n"
+  );
+
+  console.log("ABI exported to:", outputPath);
+}
+
+main()
+  .then(() => process.exit(0))
+  .catch((error) => {
+    console.error(error);
+    process.exit(1);
+  });